@@ -0,0 +1,26 @@
+.global _start
+.align 4
+.text
+_start:
+bl main
+mov x0, #0
+mov x8, #93
+svc #0
+main:
+str x30, [sp, #-16]!
+ldr x30, [sp], #16
+ret
+general_syscall_functions__term:
+str x30, [sp, #-16]!
+mov x8, #93
+svc #0
+ldr x30, [sp], #16
+ret
+general_syscall_functions__print:
+str x30, [sp, #-16]!
+mov x0, #1
+mov x8, #64
+svc #0
+ldr x30, [sp], #16
+ret
+.section .note.GNU-stack,"",@progbits
@@ -0,0 +1,261 @@
+use std::path::PathBuf;
+
+use language::compiler::Profile;
+use language::datatypes::target::Target;
+
+// Command line arguments, parsed by hand to keep the compiler free of dependencies it doesn't need.
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Command {
+    Run,
+    Build,
+    Check,
+    Init,
+    Install
+}
+
+impl Command {
+    pub fn from_name(name : &str) -> Option<Command> {
+        return match name {
+            "run" => Some(Command::Run),
+            "build" => Some(Command::Build),
+            "check" => Some(Command::Check),
+            "init" => Some(Command::Init),
+            "install" => Some(Command::Install),
+            _ => None
+        };
+    }
+}
+
+// Last artifact produced by build.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Emit {
+    Tokens,
+    Ast,
+    Ir,
+    // Stack frame layout of every function.
+    Layout,
+    // C declarations of the exported functions.
+    Header,
+    Asm,
+    Obj,
+    Exe
+}
+
+impl Emit {
+    pub fn from_name(name : &str) -> Option<Emit> {
+        return match name {
+            "tokens" => Some(Emit::Tokens),
+            "ast" => Some(Emit::Ast),
+            "ir" => Some(Emit::Ir),
+            "layout" => Some(Emit::Layout),
+            "header" => Some(Emit::Header),
+            "asm" => Some(Emit::Asm),
+            "obj" => Some(Emit::Obj),
+            "exe" => Some(Emit::Exe),
+            _ => None
+        };
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, PartialOrd)]
+pub enum Verbosity {
+    // Only errors.
+    Quiet,
+    Normal,
+    // Also the output of every phase and the time the command took.
+    Verbose
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct CliArgs {
+    pub command : Option<Command>,
+    // Source file for run, build and check, project name for init, package for install.
+    pub positional : Vec<String>,
+    pub output : Option<PathBuf>,
+    pub target : Option<Target>,
+    pub emit : Emit,
+    pub profile : Profile,
+    pub verbosity : Verbosity,
+    // Libraries linked into the executable with -l, searched in the directories given with -L.
+    pub libraries : Vec<String>,
+    pub library_dirs : Vec<PathBuf>,
+    // Build a static library instead of an executable.
+    pub lib : bool,
+    pub help : bool
+}
+
+impl CliArgs {
+    // args without the program name.
+    pub fn parse(args : &[String]) -> Result<Self, String> {
+        let mut cli_args = Self { command: None, positional: Vec::new(), output: None, target: None, emit: Emit::Exe, profile: Profile::Release, verbosity: Verbosity::Normal, libraries: Vec::new(), library_dirs: Vec::new(), lib: false, help: false };
+
+        let mut emit_given = false;
+        let mut index = 0;
+
+        while index < args.len() {
+            let arg = args[index].as_str();
+
+            match arg {
+                "-h" | "--help" => cli_args.help = true,
+                "-q" | "--quiet" => cli_args.verbosity = Verbosity::Quiet,
+                "-v" | "--verbose" => cli_args.verbosity = Verbosity::Verbose,
+                "--lib" => cli_args.lib = true,
+                "-o" | "--target" | "--emit" | "--profile" | "-l" | "-L" => {
+                    let Some(value) = args.get(index + 1) else {
+                        return Err(format!("Missing value after {}", arg));
+                    };
+
+                    match arg {
+                        "-o" => cli_args.output = Some(PathBuf::from(value)),
+                        "-l" => cli_args.libraries.push(value.clone()),
+                        "-L" => cli_args.library_dirs.push(PathBuf::from(value)),
+                        "--target" => {
+                            cli_args.target = Some(Target::from_name(value).ok_or(format!("Unknown target {}, expected aarch64-macos or aarch64-linux", value))?);
+                        },
+                        "--profile" => {
+                            cli_args.profile = Profile::from_name(value).ok_or(format!("Unknown profile {}, expected release or debug", value))?;
+                        },
+                        _ => {
+                            cli_args.emit = Emit::from_name(value).ok_or(format!("Unknown --emit {}, expected tokens, ast, ir, layout, header, asm, obj or exe", value))?;
+                            emit_given = true;
+                        }
+                    }
+
+                    index += 1;
+                },
+                _ if arg.starts_with('-') => {
+                    return Err(format!("Unknown flag {}", arg));
+                },
+                _ => {
+                    if cli_args.command.is_none() {
+                        if arg == "help" {
+                            cli_args.help = true;
+                        } else {
+                            cli_args.command = Some(Command::from_name(arg).ok_or(format!("Unknown command {}", arg))?);
+                        }
+                    } else {
+                        cli_args.positional.push(String::from(arg));
+                    }
+                }
+            }
+
+            index += 1;
+        }
+
+        if cli_args.help {
+            return Ok(cli_args);
+        }
+
+        let max_positional = match cli_args.command {
+            Some(Command::Install) => 2,
+            _ => 1
+        };
+
+        if cli_args.positional.len() > max_positional {
+            return Err(format!("Unexpected argument {}", cli_args.positional[max_positional]));
+        }
+
+        if emit_given && cli_args.command != Some(Command::Build) {
+            return Err(String::from("--emit can only be used with build"));
+        }
+
+        if cli_args.lib && cli_args.command != Some(Command::Build) {
+            return Err(String::from("--lib can only be used with build"));
+        }
+
+        if cli_args.lib && emit_given {
+            return Err(String::from("--lib builds a library, it can't be combined with --emit"));
+        }
+
+        if cli_args.output.is_some() && !matches!(cli_args.command, Some(Command::Build) | Some(Command::Run)) {
+            return Err(String::from("-o can only be used with build and run"));
+        }
+
+        if (!cli_args.libraries.is_empty() || !cli_args.library_dirs.is_empty()) && !matches!(cli_args.command, Some(Command::Build) | Some(Command::Run)) {
+            return Err(String::from("-l and -L can only be used with build and run"));
+        }
+
+        return Ok(cli_args);
+    }
+
+    pub fn file(&self) -> Option<&String> {
+        return self.positional.first();
+    }
+}
+
+pub fn usage(command : Option<Command>) -> String {
+    return match command {
+        Some(Command::Run) => String::from(
+"Builds the program and runs it.
+
+Usage: byte-lang run [file] [options]
+
+Without a file the project around the current directory is built from its byte-config.json.
+
+Options:
+  -o <path>          Path of the executable
+  --target <target>  aarch64-macos or aarch64-linux, the host by default
+  --profile <name>   release (default) or debug, which traps on array indices out of bounds
+  -l <library>       Link the library, for extern functions declared in the program
+  -L <dir>           Look for libraries in the directory
+  -q, --quiet        Only print errors
+  -v, --verbose      Print the output of every phase and the time it took
+  -h, --help         Print this help
+"),
+        Some(Command::Build) => String::from(
+"Builds the program.
+
+Usage: byte-lang build [file] [options]
+
+Without a file the project around the current directory is built from its byte-config.json.
+
+Options:
+  -o <path>          Path of the emitted artifact, tokens, ast, ir, layout and header go to stdout without it
+  --emit <kind>      tokens, ast, ir, layout, header, asm, obj or exe (default)
+  --lib              Build a static library (.a) instead of an executable without a main,
+                     only exported functions are visible to other objects, globals are not
+  --target <target>  aarch64-macos or aarch64-linux, the host by default
+  --profile <name>   release (default) or debug, which traps on array indices out of bounds
+  -l <library>       Link the library, for extern functions declared in the program
+  -L <dir>           Look for libraries in the directory
+  -q, --quiet        Only print errors
+  -v, --verbose      Print the output of every phase and the time it took
+  -h, --help         Print this help
+"),
+        Some(Command::Check) => String::from(
+"Reports errors and warnings without generating any code.
+
+Usage: byte-lang check [file] [options]
+
+Options:
+  --target <target>  aarch64-macos or aarch64-linux, the host by default
+  --profile <name>   release (default) or debug, which traps on array indices out of bounds
+  -q, --quiet        Only print errors
+  -v, --verbose      Print the output of every phase and the time it took
+  -h, --help         Print this help
+"),
+        Some(Command::Init) => String::from(
+"Creates a project directory with a byte-config.json and main.byte.
+
+Usage: byte-lang init <name>
+"),
+        Some(Command::Install) => String::from(
+"Installs a dependency into the dependencies directory of the project.
+
+Usage: byte-lang install <package> [version]
+"),
+        None => String::from(
+"Usage: byte-lang <command> [options]
+
+Commands:
+  run      Build and run the program
+  build    Build the program
+  check    Report errors without generating code
+  init     Create a new project
+  install  Install a dependency
+
+Run byte-lang <command> --help for the options of a command.
+")
+    };
+}
@@ -1,70 +1,156 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::fs;
 
-pub fn compile_asm(current_dir : PathBuf) {
-    // Create paths of output assembly file, output file and final output file.
-    let mut assembly_file = PathBuf::from(current_dir.clone());
-    assembly_file.push("output.s");
+use crate::datatypes::target::Target;
 
-    let mut output_file = PathBuf::from(current_dir.clone());
-    output_file.push("output.o");
+// What the program is linked with besides its own code.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct LinkOptions {
+    // The program calls extern functions, the C library is linked through the C compiler.
+    pub libc : bool,
+    // Libraries given with -l and the directories given with -L to look for them in.
+    pub libraries : Vec<String>,
+    pub library_dirs : Vec<PathBuf>
+}
+
+impl LinkOptions {
+    fn library_args(&self) -> Vec<String> {
+        let mut args : Vec<String> = self.library_dirs.iter().map(|dir| format!("-L{}", dir.display())).collect();
+
+        args.extend(self.libraries.iter().map(|library| format!("-l{}", library)));
 
-    let mut final_file = PathBuf::from(current_dir.clone());
-    final_file.push("output");
+        return args;
+    }
+}
+
+// Assembles assembly_file and links it into executable_file, the object file next to the
+// executable is removed afterwards.
+pub fn compile_asm(assembly_file : &Path, executable_file : &Path, target : Target, link_options : &LinkOptions) -> Result<(), String> {
+    let output_file = executable_file.with_extension("o");
+
+    assemble(assembly_file, &output_file, target)?;
+    link(&output_file, executable_file, target, link_options)?;
+
+    // Remove output file.
+    fs::remove_file(output_file).map_err(|err| format!("Error removing object file: {}", err))?;
 
-    let mut new_file_location = PathBuf::from(current_dir);
-    new_file_location.push("output.s");
+    return Ok(());
+}
+
+// Assembles assembly_file into a static library with a single object, which C compilers and
+// other linkers take like any other archive. The object file is removed afterwards.
+pub fn build_library(assembly_file : &Path, library_file : &Path, target : Target) -> Result<(), String> {
+    let output_file = library_file.with_extension("o");
+
+    assemble(assembly_file, &output_file, target)?;
+
+    // ar adds to an existing archive, objects of an earlier build would stay in it.
+    if library_file.exists() {
+        fs::remove_file(library_file).map_err(|err| format!("Error removing old library: {}", err))?;
+    }
+
+    let archiver = format!("{}ar", target.toolchain_prefix());
+
+    let status = Command::new(&archiver)
+                        .arg("rcs")
+                        .arg(library_file)
+                        .arg(&output_file)
+                        .status()
+                        .map_err(|err| format!("Failed to execute {}: {}", archiver, err))?;
+
+    if status.success() == false {
+        return Err(String::from("Failed to archive object file to library"));
+    }
+
+    fs::remove_file(output_file).map_err(|err| format!("Error removing object file: {}", err))?;
+
+    return Ok(());
+}
+
+pub fn assemble(assembly_file : &Path, object_file : &Path, target : Target) -> Result<(), String> {
+    let assembler = format!("{}as", target.toolchain_prefix());
 
     // Execute the compiling commands
-    let status = Command::new("as")
+    let status = Command::new(&assembler)
                         .arg("-o")
-                        .arg(output_file.clone())
-                        .arg(new_file_location)
+                        .arg(object_file)
+                        .arg(assembly_file)
                         .status()
-                        .expect("Failed to execute command");
+                        .map_err(|err| format!("Failed to execute {}: {}", assembler, err))?;
 
     if status.success() == false {
-        println!("Failed to compile assembly file to output");
-        return;
+        return Err(String::from("Failed to compile assembly file to output"));
     }
 
-    let sdk_path_output = Command::new("xcrun")
-        .arg("-sdk")
-        .arg("macosx")
-        .arg("--show-sdk-path")
-        .output()
-        .expect("Failed to execute xcrun");
-
-    // Convert the output to a string
-    let sdk_path = String::from_utf8(sdk_path_output.stdout)
-        .expect("Failed to convert SDK path to string")
-        .trim()
-        .to_string();
-
-    let status = Command::new("ld")
-        .arg("-macos_version_min")
-        .arg("17.0.0")
-        .arg("-o")
-        .arg("output")
-        .arg("output.o")
-        .arg("-lSystem")
-        .arg("-syslibroot")
-        .arg(sdk_path)
-        .arg("-e")
-        .arg("_main")
-        .arg("-arch")
-        .arg("arm64")
-        .status()
-        .expect("Failed to execute ld command");
-    
+    return Ok(());
+}
+
+pub fn link(object_file : &Path, executable_file : &Path, target : Target, link_options : &LinkOptions) -> Result<(), String> {
+    let status = match target {
+        Target::Aarch64Macos => {
+            let sdk_path_output = Command::new("xcrun")
+                .arg("-sdk")
+                .arg("macosx")
+                .arg("--show-sdk-path")
+                .output()
+                .map_err(|err| format!("Failed to execute xcrun: {}", err))?;
+
+            // Convert the output to a string
+            let sdk_path = String::from_utf8(sdk_path_output.stdout)
+                .map_err(|_| String::from("Failed to convert SDK path to string"))?
+                .trim()
+                .to_string();
+
+            Command::new("ld")
+                .arg("-macos_version_min")
+                .arg("17.0.0")
+                .arg("-o")
+                .arg(executable_file)
+                .arg(object_file)
+                .args(link_options.library_args())
+                .arg("-lSystem")
+                .arg("-syslibroot")
+                .arg(sdk_path)
+                .arg("-e")
+                .arg(target.entry_symbol())
+                .arg("-arch")
+                .arg("arm64")
+                .status()
+                .map_err(|err| format!("Failed to execute ld: {}", err))?
+        },
+        // _start is still ours, the dynamic loader sets up the C library before it runs.
+        Target::Aarch64Linux if link_options.libc => {
+            let c_compiler = target.c_compiler();
+
+            Command::new(&c_compiler)
+                .arg("-nostartfiles")
+                .arg("-o")
+                .arg(executable_file)
+                .arg(object_file)
+                .args(link_options.library_args())
+                .status()
+                .map_err(|err| format!("Failed to execute {}: {}", c_compiler, err))?
+        },
+        Target::Aarch64Linux => {
+            let linker = format!("{}ld", target.toolchain_prefix());
+
+            // Static binary without libc, _start is emitted by the code generator.
+            Command::new(&linker)
+                .arg("-o")
+                .arg(executable_file)
+                .arg(object_file)
+                .args(link_options.library_args())
+                .arg("-e")
+                .arg(target.entry_symbol())
+                .status()
+                .map_err(|err| format!("Failed to execute {}: {}", linker, err))?
+        }
+    };
+
     if status.success() == false {
-        println!("Failed to compile output file to final");
-        return;
+        return Err(String::from("Failed to compile output file to final"));
     }
 
-    //fs::remove_file(assembly_file).expect("Error Removing Assembly File");
-
-    // Remove output file.
-    fs::remove_file(output_file).expect("Error removing output file");
+    return Ok(());
 }
@@ -0,0 +1,488 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::datatypes::ast_statements::{Function, MemoryLocationsAst, ScopeKind, Statement, VariableType};
+use crate::datatypes::code_generator::CodeGenerator;
+use crate::datatypes::diagnostic::{Diagnostic, DiagnosticCode, Span};
+use crate::datatypes::parser::Parser;
+use crate::datatypes::program_data::ProgramData;
+use crate::datatypes::scope_analysis::ScopeAnalysis;
+use crate::datatypes::semantic_analysis::SemanticAnaytis;
+use crate::datatypes::source_map::{tokenize_all_files, SourceFile};
+use crate::datatypes::stack_frame::StackFrame;
+use crate::datatypes::target::Target;
+use crate::datatypes::token::Token;
+
+// Runs the compiler phases on a source and hands back what every phase produced, without
+// writing anything. The command line and the tests are built on top of this.
+
+// Last phase that runs, the artifacts of the phases after it are left empty.
+#[derive(Debug, PartialEq, Clone, Copy, PartialOrd)]
+pub enum Phase {
+    Tokens,
+    Ast,
+    // Scope and semantic analysis, ends with the statements the code generator lowers.
+    Ir,
+    Asm
+}
+
+// Checks added to the generated code.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Profile {
+    Release,
+    // Array indices are checked at runtime.
+    Debug
+}
+
+impl Profile {
+    pub fn from_name(name : &str) -> Option<Profile> {
+        return match name {
+            "release" => Some(Profile::Release),
+            "debug" => Some(Profile::Debug),
+            _ => None
+        };
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Options {
+    // Path of the source given to compile_source, shown in diagnostics and used to resolve
+    // relative imports.
+    pub file_name : String,
+    pub target : Target,
+    pub stop_after : Phase,
+    pub profile : Profile,
+    // Phases print what they produce.
+    pub verbose : bool,
+    // Built into an object or archive that is linked into another program, there is no entry
+    // point and main isn't needed.
+    pub library : bool,
+    // Files that can be imported without touching the filesystem, by their path relative to the
+    // root file. Imports not found here are read from disk, like the std-lib.
+    pub memory_files : HashMap<String, String>
+}
+
+impl Options {
+    pub fn new(file_name : &str) -> Self {
+        return Self { file_name: String::from(file_name), target: Target::host(), stop_after: Phase::Asm, profile: Profile::Release, verbose: false, library: false, memory_files: HashMap::new() };
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Artifacts {
+    // Root file first, followed by the files it imports.
+    pub files : Vec<SourceFile>,
+    pub tokens : Vec<Token>,
+    pub statements : Vec<Statement>,
+    pub functions : HashMap<String, Function>,
+    // Qualified function names in declaration order.
+    pub function_order : Vec<String>,
+    pub stack_frames : Vec<StackFrame>,
+    // Stack frames of every function with the statements the code generator lowers.
+    pub ir : String,
+    // Where every arg, return slot and variable lives, as an offset from sp inside its frame.
+    pub layout : String,
+    // C declarations of the exported functions, empty without any.
+    pub header : String,
+    pub assembly : String,
+    // Extern functions are declared, the program has to be linked with the C library.
+    pub links_libc : bool,
+    // Only warnings, errors end the compilation.
+    pub diagnostics : Vec<Diagnostic>
+}
+
+impl Artifacts {
+    pub fn token_dump(&self) -> String {
+        return self.tokens.iter().map(|token| format!("{:?}\n", token)).collect();
+    }
+
+    pub fn ast_dump(&self) -> String {
+        return self.statements.iter().map(|statement| format!("{:?}\n", statement)).collect();
+    }
+
+    pub fn render_diagnostics(&self) -> String {
+        return render_diagnostics(&self.diagnostics, &self.files);
+    }
+}
+
+// Diagnostics of a compilation that failed, together with the files their spans point into.
+#[derive(Debug, PartialEq, Clone)]
+pub struct CompileErrors {
+    pub files : Vec<SourceFile>,
+    pub diagnostics : Vec<Diagnostic>
+}
+
+impl CompileErrors {
+    pub fn render(&self) -> String {
+        return render_diagnostics(&self.diagnostics, &self.files);
+    }
+}
+
+// Compiles a source held in memory, file_name of the options is used as its path.
+pub fn compile_source(source : &str, options : &Options) -> Result<Artifacts, CompileErrors> {
+    let root_file = SourceFile::new(PathBuf::from(&options.file_name), options.file_name.clone(), String::from(source), String::new());
+
+    return compile(root_file, options);
+}
+
+// Compiles a file read from disk, file_name of the options is only used in diagnostics. A file
+// that can't be read is reported like any other error.
+pub fn compile_file(path : &Path, options : &Options) -> Result<Artifacts, CompileErrors> {
+    let source = std::fs::read_to_string(path).map_err(|err| file_error(path, options, &format!("Failed to read the file: {}", err)))?;
+    let canonical_path = path.canonicalize().map_err(|err| file_error(path, options, &format!("Failed to resolve the path of the file: {}", err)))?;
+
+    let root_file = SourceFile::new(canonical_path, options.file_name.clone(), source, String::new());
+
+    return compile(root_file, options);
+}
+
+fn file_error(path : &Path, options : &Options, message : &str) -> CompileErrors {
+    let root_file = SourceFile::new(path.to_path_buf(), options.file_name.clone(), String::new(), String::new());
+
+    return CompileErrors { files: vec![root_file], diagnostics: vec![Diagnostic::error(DiagnosticCode::FileNotFound, message, Span::whole_file(0))] };
+}
+
+fn compile(root_file : SourceFile, options : &Options) -> Result<Artifacts, CompileErrors> {
+    let mut program_data = ProgramData::new();
+    program_data.target = options.target;
+    program_data.verbose = options.verbose;
+    program_data.bounds_checks = options.profile == Profile::Debug;
+
+    let root_dir = root_file.path.parent().map(Path::to_path_buf).unwrap_or_default();
+
+    for (path, source) in options.memory_files.iter() {
+        program_data.memory_files.insert(root_dir.join(path), source.clone());
+    }
+
+    program_data.files.push(root_file);
+
+    // Tokenizes the file and everything it imports.
+    tokenize_all_files(&mut program_data);
+
+    stop_on_errors(&mut program_data)?;
+
+    if options.stop_after == Phase::Tokens {
+        return Ok(into_artifacts(program_data, String::new(), String::new(), String::new(), String::new()));
+    }
+
+    let mut parser = Parser::new(&mut program_data);
+    parser.parse_all();
+
+    stop_on_errors(&mut program_data)?;
+
+    if options.stop_after == Phase::Ast {
+        return Ok(into_artifacts(program_data, String::new(), String::new(), String::new(), String::new()));
+    }
+
+    let mut scope_analysis = ScopeAnalysis::new(&mut program_data);
+    scope_analysis.process_all();
+
+    stop_on_errors(&mut program_data)?;
+
+    if program_data.verbose {
+        print!("Functions: {:?}\nStack Frames: {:?}\n", program_data.functions, program_data.stack_frames);
+    }
+
+    let mut semantic_analysis = SemanticAnaytis::new(&mut program_data);
+    semantic_analysis.process_all_functions();
+
+    stop_on_errors(&mut program_data)?;
+
+    let ir = ir_dump(&program_data);
+    let layout = layout_dump(&program_data);
+    let header = c_header(&program_data, &options.file_name);
+
+    if options.stop_after == Phase::Ir {
+        return Ok(into_artifacts(program_data, ir, layout, header, String::new()));
+    }
+
+    let mut code_generator = CodeGenerator::new(&mut program_data);
+    let compiled_code = code_generator.process_all_functions();
+    let data = code_generator.process_all_globals();
+
+    let assembly_header = if options.library {
+        program_data.target.library_header()
+    } else {
+        // Create entry point, it depends on where main returns its value.
+        let main_return_register = match program_data.functions.get("main").and_then(|main| main.return_location.clone()) {
+            Some(MemoryLocationsAst::Register(register)) => Some(register),
+            _ => None
+        };
+
+        program_data.target.assembly_header(main_return_register.as_deref(), program_data.links_libc())
+    };
+
+    let assembly = format!("{}{}{}{}", assembly_header, compiled_code, data, program_data.target.assembly_footer());
+
+    return Ok(into_artifacts(program_data, ir, layout, header, assembly));
+}
+
+fn into_artifacts(program_data : ProgramData, ir : String, layout : String, header : String, assembly : String) -> Artifacts {
+    let links_libc = program_data.links_libc();
+
+    return Artifacts {
+        files: program_data.files,
+        tokens: program_data.tokens,
+        statements: program_data.statements,
+        functions: program_data.functions,
+        function_order: program_data.function_order,
+        stack_frames: program_data.stack_frames,
+        ir,
+        layout,
+        header,
+        assembly,
+        links_libc,
+        diagnostics: program_data.diagnostics
+    };
+}
+
+// Ends the compilation if a phase reported an error, warnings are kept for the artifacts.
+fn stop_on_errors(program_data : &mut ProgramData) -> Result<(), CompileErrors> {
+    if program_data.has_errors() {
+        return Err(CompileErrors { files: std::mem::take(&mut program_data.files), diagnostics: std::mem::take(&mut program_data.diagnostics) });
+    }
+
+    return Ok(());
+}
+
+fn render_diagnostics(diagnostics : &[Diagnostic], files : &[SourceFile]) -> String {
+    let mut result = String::new();
+
+    for diagnostic in diagnostics.iter() {
+        result.push_str(&diagnostic.render(files));
+        result.push('\n');
+    }
+
+    return result;
+}
+
+fn ir_dump(program_data : &ProgramData) -> String {
+    let mut result = String::new();
+
+    for function_name in program_data.function_order.iter() {
+        let function = program_data.functions.get(function_name).unwrap();
+
+        result.push_str(&format!("function {} -> {:?}\n", function_name, function.return_type));
+
+        let mut stack_frames = vec![function.first_stack_frame];
+
+        while let Some(stack_frame_index) = stack_frames.pop() {
+            let stack_frame = program_data.get_stack_frame_by_index(stack_frame_index);
+
+            result.push_str(&format!("  frame {} {:?} mem {}\n", stack_frame_index, stack_frame.kind, stack_frame.stack_mem_allocated));
+
+            for statement in stack_frame.cg_statements.iter() {
+                result.push_str(&format!("    {:?}\n", statement.statement_type));
+            }
+
+            stack_frames.extend(stack_frame.children.iter().rev());
+        }
+    }
+
+    return result;
+}
+
+// Padding is the number of bytes skipped before a value to align it, tail padding the bytes
+// between the last value and the end of the frame.
+fn layout_dump(program_data : &ProgramData) -> String {
+    let mut result = String::new();
+
+    for struct_name in program_data.struct_order.iter() {
+        let struct_definition = program_data.structs.get(struct_name).unwrap();
+
+        result.push_str(&format!("struct {} size {} align {}{}\n", struct_name, struct_definition.size, struct_definition.alignment, if struct_definition.packed { " packed" } else { "" }));
+
+        let mut fields_end = 0;
+
+        for field in struct_definition.fields.iter() {
+            result.push_str(&format!("  field {} {:?} size {} offset {} padding {}\n", field.name, field.variable_type, field.variable_type.get_variable_size(), field.offset, field.offset - fields_end));
+
+            fields_end = field.offset + field.variable_type.get_variable_size();
+        }
+
+        result.push_str(&format!("  tail padding {}\n", struct_definition.size - fields_end));
+    }
+
+    for global_name in program_data.global_order.iter() {
+        let global = program_data.globals.get(global_name).unwrap();
+
+        result.push_str(&format!("global {} {:?} size {} align {} {:?}\n", global_name, global.variable_type, global.variable_type.get_variable_size(), global.variable_type.get_variable_alignment(), global.section));
+    }
+
+    for function_name in program_data.function_order.iter() {
+        let function = program_data.functions.get(function_name).unwrap();
+
+        result.push_str(&format!("function {} args mem {}{}\n", function_name, function.stack_mem_allocated, if function.packed { " packed" } else { "" }));
+
+        // Stack args and the return slot are allocated in declaration order.
+        let mut args_end = 0;
+
+        for arg in function.args.iter() {
+            let location = match &arg.memory_location {
+                MemoryLocationsAst::Register(register) => format!("reg {}", register),
+                MemoryLocationsAst::Stack(offset) => {
+                    let padding = offset - args_end;
+                    args_end = offset + arg.arg_var_type.get_variable_size();
+
+                    format!("offset {} padding {} sp+{}", offset, padding, program_data.get_function_stack_arg_ref(function.first_stack_frame, &arg.arg_name).unwrap().local_offset)
+                }
+            };
+
+            result.push_str(&format!("  arg {} {:?} size {} {}\n", arg.arg_name, arg.arg_var_type, arg.arg_var_type.get_variable_size(), location));
+        }
+
+        match &function.return_location {
+            Some(MemoryLocationsAst::Register(register)) => result.push_str(&format!("  return {:?} reg {}\n", function.return_type, register)),
+            Some(MemoryLocationsAst::Stack(offset)) => {
+                result.push_str(&format!("  return {:?} size {} offset {} padding {} sp+{}\n", function.return_type, function.return_type.get_variable_size(), offset, offset - args_end, program_data.get_function_stack_return_offset(function.first_stack_frame).unwrap()));
+            },
+            None => {}
+        }
+
+        let mut stack_frames = vec![(function.first_stack_frame, 1)];
+
+        while let Some((stack_frame_index, indent)) = stack_frames.pop() {
+            let stack_frame = program_data.get_stack_frame_by_index(stack_frame_index);
+            let indentation = "  ".repeat(indent);
+
+            let mut variables : Vec<(&String, usize)> = stack_frame.variables.iter().map(|(name, variable)| (name, variable.offset)).collect();
+            variables.sort_by_key(|(name, offset)| (*offset, (*name).clone()));
+
+            let mut variable_lines = String::new();
+            let mut variables_end = 0;
+
+            for (name, _) in variables {
+                let variable_ref = program_data.get_stack_variable_ref(stack_frame_index, name, 0).unwrap();
+
+                variable_lines.push_str(&format!("{}  var {} {:?} size {} offset {} padding {} sp+{}\n", indentation, name, variable_ref.var.variable_type, variable_ref.var.variable_size, variable_ref.var.offset, variable_ref.var.offset - variables_end, variable_ref.local_offset));
+
+                variables_end = variable_ref.var.offset + variable_ref.var.variable_size;
+            }
+
+            result.push_str(&format!("{}frame {} {} mem {} saved {} tail padding {}\n", indentation, stack_frame_index, scope_kind_name(&stack_frame.kind), stack_frame.stack_mem_allocated, stack_frame.saved_registers_size(), stack_frame.stack_mem_allocated - variables_end));
+            result.push_str(&variable_lines);
+
+            stack_frames.extend(stack_frame.children.iter().rev().map(|child| (*child, indent + 1)));
+        }
+    }
+
+    return result;
+}
+
+// Declares the exported functions for C, together with the structs their signatures use. The
+// include guard is named after the root file.
+fn c_header(program_data : &ProgramData, file_name : &str) -> String {
+    let exported : Vec<(&String, &Function)> = program_data.function_order.iter()
+        .map(|name| (name, program_data.functions.get(name).unwrap()))
+        .filter(|(_, function)| function.exported)
+        .collect();
+
+    if exported.is_empty() {
+        return String::new();
+    }
+
+    let stem = Path::new(file_name).file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+    let guard : String = stem.chars().map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' }).collect();
+
+    let mut result = format!("/* Generated by byte-lang from {}, do not edit. */\n#ifndef {}_H\n#define {}_H\n\n#include <stdint.h>\n\n", file_name, guard, guard);
+
+    // Fields of a struct are resolved before the struct, so struct_order already defines every
+    // struct after the ones it contains.
+    let mut used_structs : Vec<String> = Vec::new();
+
+    for (_, function) in exported.iter() {
+        for var_type in function.args.iter().map(|arg| &arg.arg_var_type).chain(std::iter::once(&function.return_type)) {
+            collect_structs(program_data, var_type, &mut used_structs);
+        }
+    }
+
+    for struct_name in program_data.struct_order.iter().filter(|name| used_structs.contains(name)) {
+        let struct_definition = program_data.structs.get(struct_name).unwrap();
+
+        result.push_str(&format!("struct {} {{\n", c_name(struct_name)));
+
+        for field in struct_definition.fields.iter() {
+            result.push_str(&format!("    {};\n", c_declaration(&field.variable_type, &field.name)));
+        }
+
+        result.push_str(if struct_definition.packed { "} __attribute__((packed));\n\n" } else { "};\n\n" });
+    }
+
+    for (function_name, function) in exported.iter() {
+        let args : Vec<String> = function.args.iter().map(|arg| c_declaration(&arg.arg_var_type, &arg.arg_name)).collect();
+        let args = if args.is_empty() { String::from("void") } else { args.join(", ") };
+
+        result.push_str(&format!("{}({});\n", c_declaration(&function.return_type, function_name.rsplit("::").next().unwrap()), args));
+    }
+
+    result.push_str(&format!("\n#endif /* {}_H */\n", guard));
+
+    return result;
+}
+
+fn collect_structs(program_data : &ProgramData, var_type : &VariableType, used_structs : &mut Vec<String>) {
+    match var_type {
+        VariableType::Pointer(inner) | VariableType::Array(inner, _) => collect_structs(program_data, inner, used_structs),
+        VariableType::Struct(struct_type) => {
+            if used_structs.contains(&struct_type.name) {
+                return;
+            }
+
+            used_structs.push(struct_type.name.clone());
+
+            for field in program_data.structs.get(&struct_type.name).unwrap().fields.iter() {
+                collect_structs(program_data, &field.variable_type, used_structs);
+            }
+        },
+        _ => {}
+    }
+}
+
+// Names of the type in C, byte integers have the same width and signedness as the <stdint.h> ones.
+fn c_type(var_type : &VariableType) -> String {
+    return match var_type {
+        VariableType::I8 => String::from("int8_t"),
+        VariableType::I16 => String::from("int16_t"),
+        VariableType::I32 => String::from("int32_t"),
+        VariableType::I64 => String::from("int64_t"),
+        VariableType::U8 => String::from("uint8_t"),
+        VariableType::U16 => String::from("uint16_t"),
+        VariableType::U32 => String::from("uint32_t"),
+        VariableType::U64 => String::from("uint64_t"),
+        VariableType::Void => String::from("void"),
+        VariableType::Pointer(inner) if inner.is_pointer() => format!("{}*", c_type(inner)),
+        VariableType::Pointer(inner) => format!("{} *", c_type(inner)),
+        VariableType::Array(inner, length) => format!("{}[{}]", c_type(inner), length),
+        VariableType::Struct(struct_type) => format!("struct {}", c_name(&struct_type.name))
+    };
+}
+
+// Declares name with the type, arrays put their lengths after the name.
+fn c_declaration(var_type : &VariableType, name : &str) -> String {
+    let mut lengths = String::new();
+    let mut element_type = var_type;
+
+    while let VariableType::Array(inner, length) = element_type {
+        lengths.push_str(&format!("[{}]", length));
+        element_type = inner;
+    }
+
+    let c_type = c_type(element_type);
+    let separator = if c_type.ends_with('*') { "" } else { " " };
+
+    return format!("{}{}{}{}", c_type, separator, name, lengths);
+}
+
+fn c_name(name : &str) -> String {
+    return name.replace("::", "__");
+}
+
+fn scope_kind_name(kind : &ScopeKind) -> String {
+    return match kind {
+        ScopeKind::Function => String::from("function"),
+        ScopeKind::Block => String::from("block"),
+        ScopeKind::Loop => String::from("loop"),
+        ScopeKind::Compare(_) => String::from("compare"),
+        ScopeKind::CompareArm(condition) => format!("arm {:?}", condition)
+    };
+}
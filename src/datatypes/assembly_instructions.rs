@@ -3,7 +3,12 @@ use crate::datatypes::ast_statements::CgExpression;
 pub mod asm {
     use std::fmt::format;
 
-    use crate::datatypes::ast_statements::VariableType;
+    use crate::datatypes::ast_statements::{BinaryOperators, UnaryOperators, VariableType};
+    use crate::datatypes::type_checker::check_conversion;
+
+    // Expressions are evaluated in x9 - x15, one register per nesting level.
+    pub const EXPRESSION_REGISTER_START : usize = 9;
+    pub const EXPRESSION_REGISTER_COUNT : usize = 7;
 
     pub enum StackDestination {
         StackPointer,
@@ -11,27 +16,165 @@ pub mod asm {
     }
 
     pub fn temp_reg_for_type(var_type : VariableType, load_instruction : bool) -> String {
-        let res : &str = match var_type {
-            VariableType::U8 | VariableType::U16 | VariableType::U32 => "w10",
-            VariableType::I64 | VariableType::U64 => "x10",
+        return reg_for_type(10, var_type, load_instruction);
+    }
+
+    pub fn reg_for_type(reg_num : usize, var_type : VariableType, load_instruction : bool) -> String {
+        let prefix : &str = match var_type {
+            VariableType::U8 | VariableType::U16 | VariableType::U32 => "w",
+            VariableType::I64 | VariableType::U64 | VariableType::Pointer(_) => "x",
             VariableType::I8 | VariableType::I16 | VariableType::I32 => {
-                if load_instruction { "x10" } else { "w10" }
+                if load_instruction { "x" } else { "w" }
             }
 
             _ => unreachable!(),
         };
 
+        return format!("{}{}", prefix, reg_num);
+    }
+
+    pub fn expression_reg_num(depth : usize) -> usize {
+        return EXPRESSION_REGISTER_START + depth;
+    }
+
+    pub fn mov_reg_to_reg(destination : &str, source : &str) -> String {
+        return format!("mov {}, {}\n", destination, source);
+    }
+
+    // Sign or zero extends the low bits of the register so it holds a valid value of var_type
+    // again after an operation that could overflow it.
+    pub fn extend_reg_for_type(reg_num : usize, var_type : VariableType) -> String {
+        return match var_type {
+            VariableType::I8 => format!("sxtb x{}, w{}\n", reg_num, reg_num),
+            VariableType::I16 => format!("sxth x{}, w{}\n", reg_num, reg_num),
+            VariableType::I32 => format!("sxtw x{}, w{}\n", reg_num, reg_num),
+            VariableType::U8 => format!("uxtb w{}, w{}\n", reg_num, reg_num),
+            VariableType::U16 => format!("uxth w{}, w{}\n", reg_num, reg_num),
+            VariableType::U32 => format!("mov w{}, w{}\n", reg_num, reg_num),
+            _ => String::new()
+        };
+    }
+
+    // Converts the value of type from in the register to type to. The low bits of to are kept and
+    // extended like to is, nothing has to be done if to holds every value of from.
+    pub fn cast_reg_to_type(reg_num : usize, from : VariableType, to : VariableType) -> String {
+        if check_conversion(&from, &to).is_ok() {
+            return String::new();
+        }
+
+        return extend_reg_for_type(reg_num, to);
+    }
+
+    // Result is written to the left register.
+    pub fn binary_operation_to_reg(operator : &BinaryOperators, left : usize, right : usize, var_type : VariableType) -> String {
+        let signed = var_type.is_signed();
+
+        if let (BinaryOperators::Add | BinaryOperators::Subtract, Some(pointee)) = (operator, var_type.pointee()) {
+            return pointer_offset_to_reg(operator, left, right, pointee.get_variable_size());
+        }
+
+        let instruction = match operator {
+            BinaryOperators::Add => format!("add x{}, x{}, x{}\n", left, left, right),
+            BinaryOperators::Subtract => format!("sub x{}, x{}, x{}\n", left, left, right),
+            BinaryOperators::Multiply => format!("mul x{}, x{}, x{}\n", left, left, right),
+            BinaryOperators::Divide => format!("{} x{}, x{}, x{}\n", if signed { "sdiv" } else { "udiv" }, left, left, right),
+            // x17 is the intra procedure call scratch register, free to use between two instructions.
+            BinaryOperators::Modulo => format!("{} x17, x{}, x{}\nmsub x{}, x17, x{}, x{}\n", if signed { "sdiv" } else { "udiv" }, left, right, left, right, left),
+            BinaryOperators::BitAnd => format!("and x{}, x{}, x{}\n", left, left, right),
+            BinaryOperators::BitOr => format!("orr x{}, x{}, x{}\n", left, left, right),
+            BinaryOperators::BitXor => format!("eor x{}, x{}, x{}\n", left, left, right),
+            BinaryOperators::ShiftLeft => format!("lsl x{}, x{}, x{}\n", left, left, right),
+            BinaryOperators::ShiftRight => format!("{} x{}, x{}, x{}\n", if signed { "asr" } else { "lsr" }, left, left, right),
+            BinaryOperators::Equal | BinaryOperators::NotEqual | BinaryOperators::Less | BinaryOperators::LessEqual | BinaryOperators::Greater | BinaryOperators::GreaterEqual => {
+                return format!("cmp x{}, x{}\ncset x{}, {}\n", left, right, left, condition_code(operator, signed));
+            }
+        };
+
+        return match operator {
+            BinaryOperators::Add | BinaryOperators::Subtract | BinaryOperators::Multiply | BinaryOperators::Divide | BinaryOperators::ShiftLeft => {
+                format!("{}{}", instruction, extend_reg_for_type(left, var_type))
+            },
+            _ => instruction
+        };
+    }
+
+    // Moves the pointer in the left register by the number of pointees in the right one.
+    pub fn pointer_offset_to_reg(operator : &BinaryOperators, left : usize, right : usize, pointee_size : usize) -> String {
+        let instruction = if *operator == BinaryOperators::Add { "add" } else { "sub" };
+
+        if pointee_size == 1 {
+            return format!("{} x{}, x{}, x{}\n", instruction, left, left, right);
+        }
+
+        if pointee_size.is_power_of_two() {
+            return format!("{} x{}, x{}, x{}, lsl #{}\n", instruction, left, left, right, pointee_size.trailing_zeros());
+        }
+
+        let multiply = if *operator == BinaryOperators::Add { "madd" } else { "msub" };
+
+        return format!("mov x17, #{}\n{} x{}, x{}, x17, x{}\n", pointee_size, multiply, left, right, left);
+    }
+
+    pub fn unary_operation_to_reg(operator : &UnaryOperators, reg : usize, var_type : VariableType) -> String {
+        let instruction = match operator {
+            UnaryOperators::Negate => format!("neg x{}, x{}\n", reg, reg),
+            UnaryOperators::BitNot => format!("mvn x{}, x{}\n", reg, reg)
+        };
+
+        return format!("{}{}", instruction, extend_reg_for_type(reg, var_type));
+    }
+
+    // Condition code that holds after cmp when the comparison is true.
+    pub fn condition_code(operator : &BinaryOperators, signed : bool) -> String {
+        let res : &str = match (operator, signed) {
+            (BinaryOperators::Equal, _) => "eq",
+            (BinaryOperators::NotEqual, _) => "ne",
+            (BinaryOperators::Less, true) => "lt",
+            (BinaryOperators::Less, false) => "lo",
+            (BinaryOperators::LessEqual, true) => "le",
+            (BinaryOperators::LessEqual, false) => "ls",
+            (BinaryOperators::Greater, true) => "gt",
+            (BinaryOperators::Greater, false) => "hi",
+            (BinaryOperators::GreaterEqual, true) => "ge",
+            (BinaryOperators::GreaterEqual, false) => "hs",
+            _ => unreachable!()
+        };
+
         return String::from(res);
     }
 
     pub fn store_literal_to_stack(var_type : VariableType, num : i64, offset : usize) -> String {
         let temp_reg = temp_reg_for_type(var_type.clone(), false);
 
-        return format!("mov {}, #{}\n{} {}, [sp, #{}]\n", temp_reg, num, store_instruction_for_type(var_type), temp_reg, offset);
+        return format!("{}{}", mov_num_to_reg(&temp_reg, num), store_reg_to_stack(&temp_reg, offset, var_type));
     }
 
     pub fn store_reg_to_stack(reg : &str, offset : usize, var_type : VariableType) -> String {
-        return format!("{} {}, [sp, #{}]\n", store_instruction_for_type(var_type), reg, offset);
+        let instruction = store_instruction_for_type(var_type.clone());
+        let (setup, address) = sp_address(offset, var_type.get_variable_size(), &instruction);
+
+        return format!("{}{} {}, {}\n", setup, instruction, reg, address);
+    }
+
+    // x16 is the other intra procedure call scratch register, x17 is taken by modulo.
+    pub const SCRATCH_REGISTER : &str = "x16";
+
+    // ldr and str take offsets up to 4095 times the size of the value if the offset is a multiple
+    // of it, or -256 to 255 unscaled.
+    pub fn sp_offset_fits(offset : usize, size : usize) -> bool {
+        return offset <= 255 || (offset % size == 0 && offset / size <= 4095);
+    }
+
+    // Instructions that have to run before the access and the address operand for a value of size
+    // bytes at sp + offset. Offsets the instruction can't encode are moved into the scratch register.
+    pub fn sp_address(offset : usize, size : usize, instruction : &str) -> (String, String) {
+        if sp_offset_fits(offset, size) {
+            return (String::new(), format!("[sp, #{}]", offset));
+        }
+
+        let setup = format!("// sp offset {} is out of range for {}, addressed through {}\n{}", offset, instruction, SCRATCH_REGISTER, mov_num_to_reg(SCRATCH_REGISTER, offset as i64));
+
+        return (setup, format!("[sp, {}]", SCRATCH_REGISTER));
     }
 
     pub fn load_instruction_for_type(var_type : VariableType) -> String {
@@ -44,6 +187,7 @@ pub mod asm {
             VariableType::U16 => "ldrh",
             VariableType::U32 => "ldr",
             VariableType::U64 => "ldr",
+            VariableType::Pointer(_) => "ldr",
             _ => unreachable!()
         };
 
@@ -60,6 +204,7 @@ pub mod asm {
             VariableType::U16 => "strh",
             VariableType::U32 => "str",
             VariableType::U64 => "str",
+            VariableType::Pointer(_) => "str",
             _ => unreachable!()
         };
 
@@ -67,26 +212,214 @@ pub mod asm {
     }
 
     pub fn allocate_stack_memory(bytes : usize) -> String {
-        return format!("sub sp, sp, #{}\n", bytes);
+        return adjust_stack_pointer("sub", bytes);
     }
 
     pub fn deallocate_stack_memory(bytes : usize) -> String {
-        return format!("add sp, sp, #{}\n", bytes);
+        return adjust_stack_pointer("add", bytes);
     }
 
+    // add and sub take an immediate up to 4095, larger amounts are moved into the scratch register.
+    fn adjust_stack_pointer(instruction : &str, bytes : usize) -> String {
+        if bytes <= 4095 {
+            return format!("{} sp, sp, #{}\n", instruction, bytes);
+        }
+
+        return format!("// {} bytes are out of range for {}, moved through {}\n{}{} sp, sp, {}\n", bytes, instruction, SCRATCH_REGISTER, mov_num_to_reg(SCRATCH_REGISTER, bytes as i64), instruction, SCRATCH_REGISTER);
+    }
+
+    // A single mov takes a 16 bit value or its inverse, every other constant is built 16 bits at a
+    // time. Halves that are already right after the first movz or movn are skipped. Only the low 32
+    // bits are used for w registers.
     pub fn mov_num_to_reg(reg : &str, num : i64) -> String {
-        return format!("mov {}, #{}\n", reg, num);
+        if (-65536..=65535).contains(&num) {
+            return format!("mov {}, #{}\n", reg, num);
+        }
+
+        let halves_count = if reg.starts_with('w') { 2 } else { 4 };
+        let halves : Vec<u64> = (0..halves_count).map(|half| (num as u64 >> (half * 16)) & 0xffff).collect();
+
+        // Start from all ones with movn if more halves are 0xffff than 0.
+        let inverted = halves.iter().filter(|half| **half == 0xffff).count() > halves.iter().filter(|half| **half == 0).count();
+        let skipped = if inverted { 0xffff } else { 0 };
+
+        let mut result = String::new();
+
+        for (index, half) in halves.iter().enumerate() {
+            if *half == skipped {
+                continue;
+            }
+
+            let shift = if index == 0 { String::new() } else { format!(", lsl #{}", index * 16) };
+
+            if result.is_empty() {
+                if inverted {
+                    result.push_str(&format!("movn {}, #{:#x}{}\n", reg, !half & 0xffff, shift));
+                } else {
+                    result.push_str(&format!("movz {}, #{:#x}{}\n", reg, half, shift));
+                }
+            } else {
+                result.push_str(&format!("movk {}, #{:#x}{}\n", reg, half, shift));
+            }
+        }
+
+        // Every half was skipped, like the low 32 bits of 0xffffffff in a w register.
+        if result.is_empty() {
+            result.push_str(&format!("{} {}, #0\n", if inverted { "movn" } else { "movz" }, reg));
+        }
+
+        return result;
+    }
+
+    // Address of the value at sp + offset.
+    pub fn stack_address_to_reg(reg_num : usize, offset : usize) -> String {
+        if offset <= 4095 {
+            return format!("add x{}, sp, #{}\n", reg_num, offset);
+        }
+
+        return format!("// sp offset {} is out of range for add, moved through {}\n{}add x{}, sp, {}\n", offset, SCRATCH_REGISTER, mov_num_to_reg(SCRATCH_REGISTER, offset as i64), reg_num, SCRATCH_REGISTER);
+    }
+
+    // Adds the offset of a field to the address in the register.
+    pub fn add_offset_to_reg(reg_num : usize, offset : usize) -> String {
+        if offset == 0 {
+            return String::new();
+        }
+
+        if offset <= 4095 {
+            return format!("add x{}, x{}, #{}\n", reg_num, reg_num, offset);
+        }
+
+        return format!("{}add x{}, x{}, {}\n", mov_num_to_reg(SCRATCH_REGISTER, offset as i64), reg_num, reg_num, SCRATCH_REGISTER);
+    }
+
+    // Copies size bytes from sp + from_offset to sp + to_offset through the temp register, 8
+    // bytes at a time and the rest in smaller pieces.
+    pub fn copy_stack_memory(from_offset : usize, to_offset : usize, size : usize) -> String {
+        let mut result = String::new();
+        let mut copied = 0;
+
+        for piece_type in [VariableType::U64, VariableType::U32, VariableType::U16, VariableType::U8] {
+            let piece_size = piece_type.get_variable_size();
+
+            while size - copied >= piece_size {
+                result.push_str(&variable_to_reg(&temp_reg_for_type(piece_type.clone(), true), from_offset + copied, piece_type.clone()));
+                result.push_str(&store_reg_to_stack(&temp_reg_for_type(piece_type.clone(), false), to_offset + copied, piece_type.clone()));
+
+                copied += piece_size;
+            }
+        }
+
+        return result;
+    }
+
+    // Registers args and return values can be placed in. x9 to x17 are used by expressions and as
+    // scratch registers, x18 belongs to the platform and may change during any call, x29 and x30
+    // hold the frame record and the return address.
+    pub fn is_location_register(register : &str) -> bool {
+        return match register.strip_prefix('x').and_then(|num| num.parse::<usize>().ok()) {
+            Some(num) => num <= 8 || (19..=28).contains(&num),
+            None => false
+        };
+    }
+
+    // Registers AAPCS64 doesn't preserve across calls that can hold values between statements.
+    // is_location_register keeps args out of x9 to x18.
+    pub fn is_caller_saved(register : &str) -> bool {
+        return match register.strip_prefix('x').and_then(|num| num.parse::<usize>().ok()) {
+            Some(num) => num <= 8,
+            None => false
+        };
+    }
+
+    // brk stops the program if the index in the register is outside of an array of length elements.
+    pub fn index_bounds_check(reg_num : usize, length : usize) -> String {
+        let mut result = String::new();
+
+        if length <= 4095 {
+            result.push_str(&format!("cmp x{}, #{}\n", reg_num, length));
+        } else {
+            result.push_str(&format!("{}cmp x{}, {}\n", mov_num_to_reg(SCRATCH_REGISTER, length as i64), reg_num, SCRATCH_REGISTER));
+        }
+
+        // Negative indices are compared as large unsigned ones.
+        result.push_str(&format!("// index out of bounds of {} elements traps\nb.lo .+8\nbrk #1\n", length));
+
+        return result;
+    }
+
+    // Replaces the index in the register with the address of the element at base + offset, base is
+    // sp for arrays on the stack or a register holding the address of a global.
+    pub fn element_address_to_reg(reg_num : usize, base : &str, offset : usize, element_size : usize) -> String {
+        let mut result = String::new();
+
+        if element_size == 1 {
+            result.push_str(&format!("add x{}, {}, x{}\n", reg_num, base, reg_num));
+        } else if element_size.is_power_of_two() && element_size <= 16 {
+            result.push_str(&format!("add x{}, {}, x{}, lsl #{}\n", reg_num, base, reg_num, element_size.trailing_zeros()));
+        } else {
+            result.push_str(&format!("mov x17, #{}\nmul x{}, x{}, x17\nadd x{}, {}, x{}\n", element_size, reg_num, reg_num, reg_num, base, reg_num));
+        }
+
+        if offset > 4095 {
+            result.push_str(&format!("// sp offset {} is out of range for add, moved through {}\n{}add x{}, x{}, {}\n", offset, SCRATCH_REGISTER, mov_num_to_reg(SCRATCH_REGISTER, offset as i64), reg_num, reg_num, SCRATCH_REGISTER));
+        } else if offset != 0 {
+            result.push_str(&format!("add x{}, x{}, #{}\n", reg_num, reg_num, offset));
+        }
+
+        return result;
+    }
+
+    // Replaces the address in the register with the value of var_type it points to.
+    pub fn load_from_pointer(reg_num : usize, var_type : VariableType) -> String {
+        return format!("{} {}, [x{}]\n", load_instruction_for_type(var_type.clone()), reg_for_type(reg_num, var_type, true), reg_num);
+    }
+
+    pub fn store_to_pointer(value_reg_num : usize, pointer_reg_num : usize, var_type : VariableType) -> String {
+        return format!("{} {}, [x{}]\n", store_instruction_for_type(var_type.clone()), reg_for_type(value_reg_num, var_type, false), pointer_reg_num);
+    }
+
+    // Bytes of a string followed by a zero, so C functions can read it too. Quotes, backslashes and
+    // bytes that aren't printable are written as octal escapes.
+    pub fn string_data(string : &str) -> String {
+        let mut escaped = String::new();
+
+        for byte in string.bytes() {
+            if (byte.is_ascii_graphic() && byte != b'"' && byte != b'\\') || byte == b' ' {
+                escaped.push(byte as char);
+            } else {
+                escaped.push_str(&format!("\\{:03o}", byte));
+            }
+        }
+
+        return format!(".asciz \"{}\"\n", escaped);
     }
 
-    pub fn jump_to_function(function_name : &str) -> String {
-        return format!("bl _{}\n", function_name);
+    pub fn label(label : &str) -> String {
+        return format!("{}:\n", label);
+    }
+
+    pub fn branch(label : &str) -> String {
+        return format!("b {}\n", label);
+    }
+
+    pub fn branch_if(condition_code : &str, label : &str) -> String {
+        return format!("b.{} {}\n", condition_code, label);
+    }
+
+    pub fn global_symbol(symbol : &str) -> String {
+        return format!(".global {}\n", symbol);
+    }
+
+    pub fn jump_to_function(symbol : &str) -> String {
+        return format!("bl {}\n", symbol);
     }
 
     pub fn create_stack_frame(stack_memory_allocate : usize) -> String {
         if stack_memory_allocate == 0 {
             return format!("str x30, [sp, #-16]!\n");
         } else {
-            return format!("str x30, [sp, #-16]!\nsub sp, sp, #{}\n", stack_memory_allocate);
+            return format!("str x30, [sp, #-16]!\n{}", allocate_stack_memory(stack_memory_allocate));
         }
     }
 
@@ -94,11 +427,46 @@ pub mod asm {
         if stack_memory_allocated == 0 {
             return format!("ldr x30, [sp], #16\nret\n");
         } else {
-            return format!("add sp, sp, #{}\nldr x30, [sp], #16\nret\n", stack_memory_allocated);
+            return format!("{}ldr x30, [sp], #16\nret\n", deallocate_stack_memory(stack_memory_allocated));
         }
     }
 
+    // Exported functions are called from C, which expects a frame record in x29 and x19 to x28 to
+    // survive the call. Byte functions called from the body may use any of them for their args.
+    pub fn create_exported_stack_frame(stack_memory_allocate : usize) -> String {
+        let mut result = format!("stp x29, x30, [sp, #-96]!\nmov x29, sp\n");
+
+        for i in 0..5 {
+            result.push_str(&format!("stp x{}, x{}, [sp, #{}]\n", 19 + i * 2, 20 + i * 2, 16 + i * 16));
+        }
+
+        if stack_memory_allocate != 0 {
+            result.push_str(&allocate_stack_memory(stack_memory_allocate));
+        }
+
+        return result;
+    }
+
+    pub fn destroy_exported_stack_frame(stack_memory_allocated : usize) -> String {
+        let mut result = String::new();
+
+        if stack_memory_allocated != 0 {
+            result.push_str(&deallocate_stack_memory(stack_memory_allocated));
+        }
+
+        for i in 0..5 {
+            result.push_str(&format!("ldp x{}, x{}, [sp, #{}]\n", 19 + i * 2, 20 + i * 2, 16 + i * 16));
+        }
+
+        result.push_str("ldp x29, x30, [sp], #96\nret\n");
+
+        return result;
+    }
+
     pub fn variable_to_reg(reg : &str, offset : usize, var_type : VariableType) -> String {
-        return format!("{} {}, [sp, #{}]\n", load_instruction_for_type(var_type), reg, offset);
+        let instruction = load_instruction_for_type(var_type.clone());
+        let (setup, address) = sp_address(offset, var_type.get_variable_size(), &instruction);
+
+        return format!("{}{} {}, {}\n", setup, instruction, reg, address);
     }
 }
@@ -1,9 +1,10 @@
-use std::panic;
+use std::fmt;
 
-use crate::datatypes::{program_data::{self, ProgramData}, stack_frame, token::{BuiltInFunctions, Identifiers, MemoryLocations, Token, TokenType}};
+use crate::datatypes::{diagnostic::Span, program_data::{self, ProgramData}, stack_frame, type_checker, token::{BuiltInFunctions, Identifiers, MemoryLocations, Token, TokenType}};
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct Statement {
+    pub file: usize,
     pub col: usize,
     pub line: usize,
     pub start_pos: usize,
@@ -15,14 +16,25 @@ pub struct Statement {
 pub struct Function {
     pub return_type: VariableType,
     pub args: Vec<FunctionArg>,
+    // Where the caller finds the returned value, None for void functions.
+    pub return_location: Option<MemoryLocationsAst>,
     pub first_stack_frame: usize,
-    pub stack_mem_allocated: usize
+    pub stack_mem_allocated: usize,
+    // Args and variables are placed back to back instead of at their natural alignment.
+    pub packed: bool,
+    // Declared with extern and linked from a C library, it has no body or stack frame and is
+    // called through its unmangled name.
+    pub external: bool,
+    // Declared with export, it keeps its unmangled name and can be called from C.
+    pub exported: bool,
+    pub span: Span
 }
 
 impl Statement {
     #[inline]
     pub fn new(token: &Token, end_pos: usize, statement_type: Statements) -> Self {
         Self {
+            file: token.file,
             col: token.col,
             line: token.line,
             start_pos: token.start_pos,
@@ -45,12 +57,75 @@ pub struct FunctionArg {
     pub memory_location : MemoryLocationsAst
 }
 
+#[derive(Debug, PartialEq, Clone)]
+pub enum GlobalSection {
+    // static with a value.
+    Data,
+    // const, never written.
+    ReadOnly,
+    // static without a value, zeroed when the program is loaded.
+    Bss
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct GlobalVariable {
+    pub variable_type : VariableType,
+    // One value for numbers, one per element for arrays. Elements without a value are zero.
+    pub values : Vec<i64>,
+    pub section : GlobalSection,
+    pub span : Span
+}
+
+// static type name = value; or const type name = value; at the top level. Arrays take their
+// elements as { value, value, ... }.
+#[derive(Debug, PartialEq, Clone)]
+pub struct GlobalDeclaration {
+    pub name : String,
+    pub variable_type : VariableType,
+    pub values : Option<Vec<Expression>>,
+    pub constant : bool
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct StructField {
+    pub name : String,
+    pub variable_type : VariableType,
+    // Bytes from the start of the struct.
+    pub offset : usize
+}
+
+// Layout of a struct, computed by the parser before anything else is parsed.
+#[derive(Debug, PartialEq, Clone)]
+pub struct StructDefinition {
+    pub name : String,
+    pub fields : Vec<StructField>,
+    pub size : usize,
+    pub alignment : usize,
+    // Fields are placed back to back instead of at their natural alignment.
+    pub packed : bool,
+    pub span : Span
+}
+
+impl StructDefinition {
+    pub fn field(&self, name : &str) -> Option<&StructField> {
+        return self.fields.iter().find(|field| field.name == name);
+    }
+
+    pub fn variable_type(&self) -> VariableType {
+        return VariableType::Struct(StructType{name: self.name.clone(), size: self.size, alignment: self.alignment});
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct FunctionDeclaration {
     pub args : Vec<FunctionArg>,
     pub name : String,
     pub return_type : VariableType,
-    pub args_stack_mem_allocated : usize
+    pub return_location : Option<MemoryLocationsAst>,
+    pub args_stack_mem_allocated : usize,
+    pub packed : bool,
+    pub external : bool,
+    pub exported : bool
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -61,14 +136,71 @@ pub enum Statements {
     FunctionDeclaration(FunctionDeclaration),
     StackFramePop,
     Expression(Expression),
+    // Opens a new stack frame, closed by StackFramePop.
+    OpenScope(ScopeKind),
+    // Marks where a child stack frame sits inside its parent, added by scope analysis.
+    ChildStackFrame(usize),
+    Break,
+    Continue,
+    Return(Option<Expression>),
+    Assignment(VariableAssignment),
+    // name[index] = value;
+    ElementAssignment(ElementAssignment),
+    // name.field = value;
+    FieldAssignment(FieldAssignment),
+    // Laid out before parsing, the statement is only checked to be at the top level.
+    StructDeclaration(String),
+    GlobalDeclaration(GlobalDeclaration),
+    // Files are loaded before parsing, the statement is only checked to be at the top level.
+    Import(String)
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ScopeKind {
+    Function,
+    Block,
+    Loop,
+    Compare(CompareAst),
+    CompareArm(CompareArmCondition)
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct CompareAst {
+    pub left : Expression,
+    pub right : Expression
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum CompareArmCondition {
+    Condition(BinaryOperators),
+    Else
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum BuiltInFunctionsAst {
     Assembly(Box<Expression>),
     Format(Format),
-    StackOffset(String),
-    BranchLinked(BranchLinkedAst)
+    // stack_offset(name) or stack_offset(name[index]) for an element of an array.
+    StackOffset(String, Option<usize>),
+    // size_of(name), size of a variable or struct in bytes.
+    SizeOf(String),
+    // size_of("string"), number of bytes in a string literal without the terminating zero.
+    SizeOfString(String),
+    // offset_of(Struct, field), bytes from the start of the struct to the field.
+    OffsetOf(String, String),
+    BranchLinked(BranchLinkedAst),
+    // addr(variable), pointer to a variable or stack arg.
+    Address(String),
+    // load(pointer), value the pointer points to.
+    Load(Box<Expression>),
+    // store(pointer, value);
+    Store(StoreAst)
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct StoreAst {
+    pub pointer : Box<Expression>,
+    pub value : Box<Expression>
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -84,15 +216,52 @@ pub struct BranchLinkedAst {
 }
 
 impl BuiltInFunctionsAst {
-    pub fn parse(&self, program_data : &mut ProgramData, stack_frame : usize) -> Option<Literal> {
+    // Evaluates compile time built in functions, returns an error message if they can't be evaluated.
+    pub fn parse(&self, program_data : &ProgramData, stack_frame : usize) -> Result<Literal, String> {
         return match self {
-            BuiltInFunctionsAst::StackOffset(identifier) => {
+            BuiltInFunctionsAst::StackOffset(identifier, Some(index)) => {
+                let Some(var) = program_data.get_stack_variable_ref(stack_frame, identifier, 0) else {
+                    return Err(format!("stack_offset of unknown variable: {}", identifier));
+                };
+
+                let VariableType::Array(element_type, length) = &var.var.variable_type else {
+                    return Err(format!("stack_offset of an element of {}, which is not an array", identifier));
+                };
+
+                if index >= length {
+                    return Err(format!("stack_offset of element {} of {}, which has {} elements", index, identifier, length));
+                }
+
+                Ok(Literal::Number((var.local_offset + index * element_type.get_variable_size()) as i64))
+            },
+            BuiltInFunctionsAst::StackOffset(identifier, None) => {
                 if let Some(var) = program_data.get_stack_variable_ref(stack_frame, identifier, 0) {
-                    Some(Literal::Number(var.local_offset as i64))
+                    Ok(Literal::Number(var.local_offset as i64))
                 } else if let Some(arg) = program_data.get_function_stack_arg_ref(stack_frame, identifier) {
-                    Some(Literal::Number(arg.local_offset as i64))
+                    Ok(Literal::Number(arg.local_offset as i64))
+                } else {
+                    Err(format!("stack_offset of unknown variable: {}", identifier))
+                }
+            },
+            BuiltInFunctionsAst::SizeOf(identifier) => {
+                // Variables shadow structs with the same name.
+                if let Some(variable_type) = type_checker::identifier_type(program_data, stack_frame, identifier) {
+                    Ok(Literal::Number(variable_type.get_variable_size() as i64))
+                } else if let Some(struct_definition) = program_data.structs.get(identifier) {
+                    Ok(Literal::Number(struct_definition.size as i64))
                 } else {
-                    panic!()
+                    Err(format!("size_of unknown variable or struct: {}", identifier))
+                }
+            },
+            BuiltInFunctionsAst::SizeOfString(string) => Ok(Literal::Number(string.len() as i64)),
+            BuiltInFunctionsAst::OffsetOf(struct_name, field_name) => {
+                let Some(struct_definition) = program_data.structs.get(struct_name) else {
+                    return Err(format!("offset_of unknown struct: {}", struct_name));
+                };
+
+                match struct_definition.field(field_name) {
+                    Some(field) => Ok(Literal::Number(field.offset as i64)),
+                    None => Err(format!("Struct {} has no field {}", struct_name, field_name))
                 }
             },
             BuiltInFunctionsAst::Format(format) => {
@@ -102,12 +271,12 @@ impl BuiltInFunctionsAst {
 
                 for arg in format.args_provided.clone() {
                     loop {
-                        match format.string.chars().nth(position).unwrap() {
-                            '{' => {
+                        match format.string.chars().nth(position) {
+                            Some('{') => {
                                 position += 1;
 
-                                if format.string.chars().nth(position).unwrap() != '}' {
-                                    panic!("Expected '}}' after '{{' in format");
+                                if format.string.chars().nth(position) != Some('}') {
+                                    return Err(String::from("Expected '}' after '{' in format"));
                                 }
 
                                 position += 1;
@@ -120,20 +289,23 @@ impl BuiltInFunctionsAst {
                                         result.push_str(&string);
                                     },
                                     Expression::BuiltInFunction(func) => {
-                                        let parsed_func = func.parse(program_data, stack_frame);
-                                        result.push_str(&parsed_func.unwrap().to_string());
+                                        let parsed_func = func.parse(program_data, stack_frame)?;
+                                        result.push_str(&parsed_func.to_string());
                                     }
                                     _ => {
-                                        panic!("Invalid Format");
+                                        return Err(String::from("Invalid Format"));
                                     }
                                 }
 
                                 break;
                             },
-                            _ => {
-                                result.push(format.string.chars().nth(position).unwrap());
+                            Some(character) => {
+                                result.push(character);
 
                                 position += 1;
+                            },
+                            None => {
+                                return Err(String::from("More args given to format than '{}' in string"));
                             }
                         }
                     }
@@ -144,7 +316,7 @@ impl BuiltInFunctionsAst {
                     position += 1;
                 }
 
-                return Some(Literal::String(result));
+                return Ok(Literal::String(result));
 
             }
             _ => unreachable!()
@@ -165,11 +337,119 @@ pub struct VariableDeclaration {
     pub value: Option<Expression>,
 }
 
+#[derive(Debug, PartialEq, Clone)]
+pub struct VariableAssignment {
+    pub name: String,
+    pub value: Expression,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ElementAssignment {
+    // A variable or a field of a struct, like values or packet.tag.
+    pub array : FieldAst,
+    pub index : Expression,
+    pub value : Expression
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct FieldAssignment {
+    pub field : FieldAst,
+    pub value : Expression
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum Expression {
     Literal(Literal),
     Identifier(Identifiers),
-    BuiltInFunction(BuiltInFunctionsAst)
+    BuiltInFunction(BuiltInFunctionsAst),
+    BinaryOperation(BinaryOperation),
+    UnaryOperation(UnaryOperation),
+    Cast(Cast),
+    // name[index] or name.field[index], element of an array.
+    Index(IndexAst),
+    // name.field, field of a struct or of the struct a pointer points to.
+    Field(FieldAst)
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct IndexAst {
+    // A variable or a field of a struct, like values or packet.tag.
+    pub array : FieldAst,
+    pub index : Box<Expression>
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct FieldAst {
+    pub name : String,
+    // Fields of nested structs follow each other, like a.b.c.
+    pub fields : Vec<String>
+}
+
+impl FieldAst {
+    // The field as written in the source.
+    pub fn path(&self) -> String {
+        let mut path = self.name.clone();
+
+        for field in self.fields.iter() {
+            path.push('.');
+            path.push_str(field);
+        }
+
+        return path;
+    }
+}
+
+// value as variable_type
+#[derive(Debug, PartialEq, Clone)]
+pub struct Cast {
+    pub value : Box<Expression>,
+    pub variable_type : VariableType
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum BinaryOperators {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+    BitAnd,
+    BitOr,
+    BitXor,
+    ShiftLeft,
+    ShiftRight,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual
+}
+
+impl BinaryOperators {
+    // Comparisons evaluate to 0 or 1 instead of a value of their operand type.
+    pub fn is_comparison(&self) -> bool {
+        return matches!(self, BinaryOperators::Equal | BinaryOperators::NotEqual | BinaryOperators::Less | BinaryOperators::LessEqual | BinaryOperators::Greater | BinaryOperators::GreaterEqual);
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum UnaryOperators {
+    Negate,
+    BitNot
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct BinaryOperation {
+    pub operator : BinaryOperators,
+    pub left : Box<Expression>,
+    pub right : Box<Expression>
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct UnaryOperation {
+    pub operator : UnaryOperators,
+    pub operand : Box<Expression>
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -199,7 +479,27 @@ pub enum VariableType {
     U16,
     U32,
     U64,
-    Void
+    Void,
+    // Address of a value of the inner type.
+    Pointer(Box<VariableType>),
+    // Fixed number of elements placed back to back, only declared as stack variables.
+    Array(Box<VariableType>, usize),
+    Struct(StructType)
+}
+
+// Size and alignment are copied from the StructDefinition, so the type can be used without it.
+#[derive(PartialEq, Clone)]
+pub struct StructType {
+    pub name : String,
+    pub size : usize,
+    pub alignment : usize
+}
+
+// Types are shown as Struct(Header) in messages.
+impl fmt::Debug for StructType {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "{}", self.name);
+    }
 }
 
 impl VariableType {
@@ -214,10 +514,76 @@ impl VariableType {
             VariableType::U16 => 2,
             VariableType::U32 => 4,
             VariableType::U64 => 8,
-            VariableType::Void => 0
+            VariableType::Void => 0,
+            VariableType::Pointer(_) => 8,
+            VariableType::Array(element_type, length) => element_type.get_variable_size() * length,
+            VariableType::Struct(struct_type) => struct_type.size
         }
     }
 
+    // Name used in the source.
+    pub fn name(&self) -> String {
+        let name = match self {
+            VariableType::I8 => "i8",
+            VariableType::I16 => "i16",
+            VariableType::I32 => "i32",
+            VariableType::I64 => "i64",
+            VariableType::U8 => "u8",
+            VariableType::U16 => "u16",
+            VariableType::U32 => "u32",
+            VariableType::U64 => "u64",
+            VariableType::Void => "void",
+            VariableType::Pointer(pointee) => {
+                return format!("*{}", pointee.name());
+            },
+            VariableType::Array(element_type, length) => {
+                return format!("{}[{}]", element_type.name(), length);
+            },
+            VariableType::Struct(struct_type) => {
+                return struct_type.name.clone();
+            }
+        };
+
+        return String::from(name);
+    }
+
+    // Natural alignment, every value is placed at an offset that is a multiple of it.
+    pub fn get_variable_alignment(&self) -> usize {
+        return match self {
+            VariableType::Void => 1,
+            VariableType::Array(element_type, _) => element_type.get_variable_alignment(),
+            VariableType::Struct(struct_type) => struct_type.alignment,
+            _ => self.get_variable_size()
+        };
+    }
+
+    pub fn is_signed(&self) -> bool {
+        return matches!(self, VariableType::I8 | VariableType::I16 | VariableType::I32 | VariableType::I64);
+    }
+
+    pub fn is_integer(&self) -> bool {
+        return !matches!(self, VariableType::Void | VariableType::Pointer(_) | VariableType::Array(_, _) | VariableType::Struct(_));
+    }
+
+    pub fn is_pointer(&self) -> bool {
+        return matches!(self, VariableType::Pointer(_));
+    }
+
+    pub fn is_array(&self) -> bool {
+        return matches!(self, VariableType::Array(_, _));
+    }
+
+    pub fn is_struct(&self) -> bool {
+        return matches!(self, VariableType::Struct(_));
+    }
+
+    // Type of the value the pointer points to.
+    pub fn pointee(&self) -> Option<&VariableType> {
+        return match self {
+            VariableType::Pointer(pointee) => Some(pointee),
+            _ => None
+        };
+    }
 }
 
 // Code gen specific Structs
@@ -229,7 +595,62 @@ pub struct CgStatement {
 #[derive(Debug, PartialEq, Clone)]
 pub enum CgStatementType {
     VariableInitialization(CgVariableInitialization),
-    BuiltInFunction(CgBuiltInFunctions)
+    BuiltInFunction(CgBuiltInFunctions),
+    ChildStackFrame(usize),
+    Compare(CgCompare),
+    Break(CgLoopJump),
+    Continue(CgLoopJump),
+    Return(CgReturn),
+    // Assignments to stack variables and stack args use VariableInitialization.
+    RegisterAssignment(CgRegisterAssignment),
+    Store(CgStore)
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct CgStore {
+    pub pointer : CgExpression,
+    pub value : CgExpression,
+    // Type of the value written.
+    pub variable_type : VariableType
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct CgRegisterAssignment {
+    pub value : CgExpression,
+    pub register : String
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct CgCompare {
+    pub left : CgExpression,
+    pub right : CgExpression,
+    pub variable_type : VariableType,
+    // Stack frame of the compare, its children are the arms.
+    pub stack_frame : usize
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct CgLoopJump {
+    pub loop_stack_frame : usize,
+    // Memory of the frames between the jump and the loop that has to be freed before jumping.
+    pub unwind_bytes : usize
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct CgReturn {
+    pub value : Option<CgExpression>,
+    pub variable_type : VariableType,
+    pub return_location : Option<CgReturnLocation>,
+    // Memory of the frames inside the function that has to be freed before returning.
+    pub unwind_bytes : usize,
+    pub function_stack_frame : usize
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum CgReturnLocation {
+    Register(String),
+    // Offset of the return slot from sp at the return statement.
+    Stack(usize)
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -241,18 +662,116 @@ pub enum CgBuiltInFunctions {
 #[derive(Debug, PartialEq, Clone)]
 pub struct CgBranchLinked {
     pub function_name : String,
-    pub args : Vec<CgExpression>
+    pub args : Vec<CgExpression>,
+    // Arg registers of the calling function the callee may overwrite, kept on the stack during
+    // the call.
+    pub saved_registers : Vec<String>
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum CgExpression {
     Identifier(CgIdentifiers),
-    Literal(Literal)
+    Literal(Literal),
+    BinaryOperation(CgBinaryOperation),
+    UnaryOperation(CgUnaryOperation),
+    Cast(CgCast),
+    // Offset of the variable from sp, evaluates to its address.
+    Address(usize),
+    // Qualified name of a global, evaluates to its address.
+    GlobalAddress(String),
+    // Index of a string in ProgramData.strings, evaluates to the address of its first byte.
+    StringAddress(usize),
+    ElementAddress(CgElementAddress),
+    FieldAddress(CgFieldAddress),
+    Load(CgLoad),
+    // Only used as the whole value of a statement, calls clobber the expression registers.
+    BranchLinked(CgBranchLinked)
+}
+
+impl CgExpression {
+    // Number of registers needed to evaluate the expression, left operands are kept in a register
+    // while the right one is evaluated.
+    pub fn registers_needed(&self) -> usize {
+        return match self {
+            CgExpression::BinaryOperation(binary_operation) => binary_operation.left.registers_needed().max(binary_operation.right.registers_needed() + 1),
+            CgExpression::UnaryOperation(unary_operation) => unary_operation.operand.registers_needed(),
+            CgExpression::Cast(cast) => cast.value.registers_needed(),
+            CgExpression::Load(load) => load.pointer.registers_needed(),
+            CgExpression::ElementAddress(element_address) => match &element_address.pointer {
+                Some(pointer) => element_address.index.registers_needed().max(pointer.registers_needed() + 1),
+                None => element_address.index.registers_needed()
+            },
+            CgExpression::FieldAddress(field_address) => field_address.pointer.registers_needed(),
+            _ => 1
+        };
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct CgBinaryOperation {
+    pub operator : BinaryOperators,
+    pub left : Box<CgExpression>,
+    pub right : Box<CgExpression>,
+    // Type of the operands, decides between signed and unsigned instructions. Adding to or
+    // subtracting from a pointer moves it by whole pointees.
+    pub variable_type : VariableType
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct CgCast {
+    pub value : Box<CgExpression>,
+    // Type the value is evaluated as.
+    pub from : VariableType,
+    pub to : VariableType
+}
+
+// Address of the element of an array at a computed index.
+#[derive(Debug, PartialEq, Clone)]
+pub struct CgElementAddress {
+    // Offset of the first element from sp.
+    pub offset : usize,
+    pub index : Box<CgExpression>,
+    pub element_size : usize,
+    // Number of elements when the index is checked before it is used.
+    pub bounds_check : Option<usize>,
+    // Qualified name of a global array, the elements start at its address instead of sp + offset.
+    pub global : Option<String>,
+    // Pointer to the struct holding the array, the elements start at pointer + offset instead.
+    pub pointer : Option<Box<CgExpression>>
+}
+
+// Address of a field of the struct the pointer points to.
+#[derive(Debug, PartialEq, Clone)]
+pub struct CgFieldAddress {
+    pub pointer : Box<CgExpression>,
+    pub offset : usize
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct CgLoad {
+    pub pointer : Box<CgExpression>,
+    // Type of the value read.
+    pub variable_type : VariableType
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct CgUnaryOperation {
+    pub operator : UnaryOperators,
+    pub operand : Box<CgExpression>,
+    pub variable_type : VariableType
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum CgIdentifiers {
     StackVariableData(StackVariableData),
+    // Function arg passed in a register.
+    RegisterData(RegisterData)
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct RegisterData {
+    pub register : String,
+    pub variable_type : VariableType
 }
 
 #[derive(Debug, PartialEq, Clone)]
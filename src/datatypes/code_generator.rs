@@ -1,6 +1,4 @@
-use std::fmt::format;
-
-use crate::datatypes::{assembly_instructions::asm::*, ast_statements::{AstIdentifiers, CgBuiltInFunctions, CgExpression, CgIdentifiers, CgStatement, CgStatementType, Literal, MemoryLocationsAst, VariableType}, general_functions::align_memory, program_data::{ProgramData, StackVariableRef}, stack_frame::StackFrame};
+use crate::datatypes::{assembly_instructions::asm::*, ast_statements::{CgBranchLinked, CgBuiltInFunctions, CgCompare, CgReturnLocation, CompareArmCondition, GlobalSection, ScopeKind, CgExpression, CgIdentifiers, CgStatement, CgStatementType, Literal, MemoryLocationsAst, VariableType}, general_functions::align_memory, program_data::ProgramData, stack_frame::StackFrame};
 
 pub struct CodeGenerator<'a> {
     program_data: &'a mut ProgramData,
@@ -12,7 +10,7 @@ impl<'a> CodeGenerator<'a> {
         return Self{program_data, stack_ptr: 0};
     }
 
-    pub fn generate_statement(&mut self, statement : &CgStatement, stack_frame : usize) -> String {
+    pub fn generate_statement(&mut self, statement : &CgStatement) -> String {
         match statement.statement_type.clone() {
             CgStatementType::VariableInitialization(var_init) => {
                 return self.init_var(var_init.stack_offset, var_init.variable_type.clone(), var_init.init_value);
@@ -20,70 +18,292 @@ impl<'a> CodeGenerator<'a> {
             CgStatementType::BuiltInFunction(built_in_function) => {
                 match built_in_function {
                     CgBuiltInFunctions::BranchLinked(branch_linked) => {
-                        let mut result = String::new();
+                        return self.generate_branch_linked(&branch_linked, None);
+                    },
+                    CgBuiltInFunctions::Assembly(assembly_code) => {
+                        return assembly_code;
+                    }
+                }
+            },
+            CgStatementType::ChildStackFrame(child_stack_frame) => {
+                return self.generate_child_stack_frame(child_stack_frame);
+            },
+            CgStatementType::Compare(compare) => {
+                return self.generate_compare(&compare);
+            },
+            CgStatementType::Break(loop_jump) => {
+                let mut result = String::new();
 
-                        let function_args = self.program_data.functions.get(&branch_linked.function_name).unwrap().args.clone();
-                        let function_stack_args_mem_allocated = self.program_data.functions.get(&branch_linked.function_name).unwrap().stack_mem_allocated;
+                if loop_jump.unwind_bytes != 0 {
+                    result.push_str(&deallocate_stack_memory(loop_jump.unwind_bytes));
+                }
 
-                        if function_stack_args_mem_allocated != 0 {
-                            result.push_str(&allocate_stack_memory(function_stack_args_mem_allocated));
-                        }
+                result.push_str(&branch(&self.loop_label(loop_jump.loop_stack_frame, "end")));
 
-                        for i in 0..function_args.len() {
-                            let arg_provided = branch_linked.args.get(i).unwrap();
-                            let arg_expecting = function_args.get(i).unwrap();
-
-                            match arg_expecting.memory_location.clone() {
-                                MemoryLocationsAst::Register(register) => {
-                                    match arg_provided {
-                                        CgExpression::Literal(Literal::Number(num)) => {
-                                            result.push_str(&mov_num_to_reg(&register, num.clone()));
-                                        },
-                                        CgExpression::Identifier(CgIdentifiers::StackVariableData(stack_var_data)) => {
-                                            result.push_str(&variable_to_reg(&register, function_stack_args_mem_allocated + stack_var_data.offset, stack_var_data.variable_type.clone()));
-                                        },
-                                        _ => todo!()
-                                    }
-                                },
-                                MemoryLocationsAst::Stack(stack_arg_offset) => {
-                                    match arg_provided {
-                                        CgExpression::Literal(Literal::Number(num)) => {
-                                            let var_size = arg_expecting.arg_var_type.get_variable_size();
-
-                                            result.push_str(&store_literal_to_stack(arg_expecting.arg_var_type.clone(), num.clone(), function_stack_args_mem_allocated - stack_arg_offset - var_size));
-                                        },
-                                        CgExpression::Identifier(CgIdentifiers::StackVariableData(stack_var_data)) => {
-                                            let mut stack_var_data_clone = stack_var_data.clone();
-
-                                            let var_size = stack_var_data_clone.variable_type.get_variable_size();
-
-                                            stack_var_data_clone.offset += function_stack_args_mem_allocated;
-
-                                            result.push_str(&self.init_var(function_stack_args_mem_allocated - stack_arg_offset - var_size, stack_var_data_clone.variable_type.clone(), CgExpression::Identifier(CgIdentifiers::StackVariableData(stack_var_data_clone))));
-                                        },
-                                        _ => unreachable!()
-                                    }
-                                }
-                                _ => todo!()
-                            }
-                        }
+                return result;
+            },
+            CgStatementType::Continue(loop_jump) => {
+                let mut result = String::new();
 
-                        result.push_str(&jump_to_function(&branch_linked.function_name));
+                if loop_jump.unwind_bytes != 0 {
+                    result.push_str(&deallocate_stack_memory(loop_jump.unwind_bytes));
+                }
 
-                        if function_stack_args_mem_allocated != 0 {
-                            result.push_str(&deallocate_stack_memory(function_stack_args_mem_allocated));
-                        }
+                result.push_str(&branch(&self.loop_label(loop_jump.loop_stack_frame, "start")));
 
-                        return result;
-                    },
-                    CgBuiltInFunctions::Assembly(assembly_code) => {
-                        return assembly_code;
+                return result;
+            },
+            CgStatementType::Return(cg_return) => {
+                let mut result = String::new();
+
+                if let Some(value) = cg_return.value {
+                    let value_reg = expression_reg_num(0);
+
+                    result.push_str(&self.generate_expression(&value, 0, 0));
+
+                    match cg_return.return_location.unwrap() {
+                        CgReturnLocation::Register(register) => {
+                            result.push_str(&mov_reg_to_reg(&register, &format!("x{}", value_reg)));
+                        },
+                        CgReturnLocation::Stack(offset) => {
+                            result.push_str(&store_reg_to_stack(&reg_for_type(value_reg, cg_return.variable_type.clone(), false), offset, cg_return.variable_type));
+                        }
                     }
                 }
+
+                if cg_return.unwind_bytes != 0 {
+                    result.push_str(&deallocate_stack_memory(cg_return.unwind_bytes));
+                }
+
+                result.push_str(&self.return_stack_frame(cg_return.function_stack_frame));
+
+                return result;
+            },
+            CgStatementType::RegisterAssignment(register_assignment) => {
+                let mut result = self.generate_expression(&register_assignment.value, 0, 0);
+
+                result.push_str(&mov_reg_to_reg(&register_assignment.register, &format!("x{}", expression_reg_num(0))));
+
+                return result;
+            },
+            CgStatementType::Store(store) => {
+                let mut result = self.generate_expression(&store.pointer, 0, 0);
+
+                result.push_str(&self.generate_expression(&store.value, 1, 0));
+                result.push_str(&store_to_pointer(expression_reg_num(1), expression_reg_num(0), store.variable_type));
+
+                return result;
             }
         };
     }
 
+    // Calls the function, when result_depth is given the returned value is moved into the
+    // expression register for that depth.
+    pub fn generate_branch_linked(&mut self, branch_linked : &CgBranchLinked, result_depth : Option<usize>) -> String {
+        let mut result = String::new();
+
+        let function = self.program_data.functions.get(&branch_linked.function_name).unwrap().clone();
+        let function_stack_args_mem_allocated = function.stack_mem_allocated;
+
+        // Saved registers sit above the args, variables are that much further away from sp while
+        // the args are evaluated.
+        let saved_registers_mem = align_memory(branch_linked.saved_registers.len() * 8, 16);
+        let stack_adjust = saved_registers_mem + function_stack_args_mem_allocated;
+
+        if saved_registers_mem != 0 {
+            result.push_str(&allocate_stack_memory(saved_registers_mem));
+
+            for (i, register) in branch_linked.saved_registers.iter().enumerate() {
+                result.push_str(&store_reg_to_stack(register, i * 8, VariableType::U64));
+            }
+        }
+
+        if function_stack_args_mem_allocated != 0 {
+            result.push_str(&allocate_stack_memory(function_stack_args_mem_allocated));
+        }
+
+        for (arg_provided, arg_expecting) in branch_linked.args.iter().zip(function.args.iter()) {
+            let MemoryLocationsAst::Stack(stack_arg_offset) = arg_expecting.memory_location else {
+                continue;
+            };
+
+            let var_size = arg_expecting.arg_var_type.get_variable_size();
+
+            match arg_provided {
+                CgExpression::Literal(Literal::Number(num)) => {
+                    result.push_str(&store_literal_to_stack(arg_expecting.arg_var_type.clone(), num.clone(), function_stack_args_mem_allocated - stack_arg_offset - var_size));
+                },
+                CgExpression::Identifier(CgIdentifiers::StackVariableData(stack_var_data)) => {
+                    let mut stack_var_data_clone = stack_var_data.clone();
+
+                    stack_var_data_clone.offset += stack_adjust;
+
+                    result.push_str(&self.init_var(function_stack_args_mem_allocated - stack_arg_offset - var_size, stack_var_data_clone.variable_type.clone(), CgExpression::Identifier(CgIdentifiers::StackVariableData(stack_var_data_clone))));
+                },
+                _ => {
+                    result.push_str(&self.generate_expression(arg_provided, 0, stack_adjust));
+                    result.push_str(&store_reg_to_stack(&reg_for_type(expression_reg_num(0), arg_expecting.arg_var_type.clone(), false), function_stack_args_mem_allocated - stack_arg_offset - var_size, arg_expecting.arg_var_type.clone()));
+                }
+            }
+        }
+
+        // Register args are evaluated before any of them is moved, an arg can read the register
+        // another arg is passed in.
+        let mut evaluated_args : Vec<(String, usize)> = Vec::new();
+
+        for (arg_provided, arg_expecting) in branch_linked.args.iter().zip(function.args.iter()) {
+            let MemoryLocationsAst::Register(register) = arg_expecting.memory_location.clone() else {
+                continue;
+            };
+
+            if let CgExpression::Literal(_) = arg_provided {
+                continue;
+            }
+
+            let depth = evaluated_args.len();
+
+            result.push_str(&self.generate_expression(arg_provided, depth, stack_adjust));
+
+            evaluated_args.push((register, depth));
+        }
+
+        for (register, depth) in evaluated_args.iter() {
+            result.push_str(&mov_reg_to_reg(register, &format!("x{}", expression_reg_num(*depth))));
+        }
+
+        for (arg_provided, arg_expecting) in branch_linked.args.iter().zip(function.args.iter()) {
+            if let (CgExpression::Literal(Literal::Number(num)), MemoryLocationsAst::Register(register)) = (arg_provided, arg_expecting.memory_location.clone()) {
+                result.push_str(&mov_num_to_reg(&register, *num));
+            }
+        }
+
+        result.push_str(&jump_to_function(&self.function_symbol(&branch_linked.function_name)));
+
+        if let Some(depth) = result_depth {
+            let result_reg = expression_reg_num(depth);
+
+            match function.return_location.clone().unwrap() {
+                MemoryLocationsAst::Register(register) => {
+                    result.push_str(&mov_reg_to_reg(&format!("x{}", result_reg), &register));
+                    result.push_str(&extend_reg_for_type(result_reg, function.return_type.clone()));
+                },
+                // The return slot is freed together with the args.
+                MemoryLocationsAst::Stack(stack_offset) => {
+                    let var_size = function.return_type.get_variable_size();
+
+                    result.push_str(&variable_to_reg(&reg_for_type(result_reg, function.return_type.clone(), true), function_stack_args_mem_allocated - stack_offset - var_size, function.return_type.clone()));
+                }
+            }
+        }
+
+        if function_stack_args_mem_allocated != 0 {
+            result.push_str(&deallocate_stack_memory(function_stack_args_mem_allocated));
+        }
+
+        if saved_registers_mem != 0 {
+            for (i, register) in branch_linked.saved_registers.iter().enumerate() {
+                result.push_str(&variable_to_reg(register, i * 8, VariableType::U64));
+            }
+
+            result.push_str(&deallocate_stack_memory(saved_registers_mem));
+        }
+
+        return result;
+    }
+
+    // Extern and exported functions are linked by the name they were declared with, without their
+    // module.
+    pub fn function_symbol(&self, function_name : &str) -> String {
+        let function = self.program_data.functions.get(function_name).unwrap();
+
+        if function.external || function.exported {
+            return self.program_data.target.symbol_name(function_name.rsplit("::").next().unwrap());
+        }
+
+        return self.program_data.target.symbol_name(function_name);
+    }
+
+    // Frames inside a function are emitted inline, they only move sp for their own variables.
+    pub fn generate_child_stack_frame(&mut self, stack_frame : usize) -> String {
+        let mut result = String::new();
+
+        let stack_mem_allocated = self.get_stack_frame_by_index(stack_frame).stack_mem_allocated;
+        let is_loop = self.get_stack_frame_by_index(stack_frame).kind == ScopeKind::Loop;
+
+        if stack_mem_allocated != 0 {
+            result.push_str(&allocate_stack_memory(stack_mem_allocated));
+        }
+
+        // The allocation stays outside of the loop, continue jumps back to the start with the
+        // loop's own memory still allocated.
+        if is_loop {
+            result.push_str(&label(&self.loop_label(stack_frame, "start")));
+        }
+
+        for statement in self.get_stack_frame_by_index(stack_frame).cg_statements.clone().iter() {
+            result.push_str(&self.generate_statement(statement));
+        }
+
+        if is_loop {
+            result.push_str(&branch(&self.loop_label(stack_frame, "start")));
+            result.push_str(&label(&self.loop_label(stack_frame, "end")));
+        }
+
+        if stack_mem_allocated != 0 {
+            result.push_str(&deallocate_stack_memory(stack_mem_allocated));
+        }
+
+        return result;
+    }
+
+    // Compares both values once and branches to the first arm whose condition holds, or to the
+    // else arm when none does.
+    pub fn generate_compare(&mut self, compare : &CgCompare) -> String {
+        let mut result = String::new();
+
+        let left_reg = expression_reg_num(0);
+        let right_reg = expression_reg_num(1);
+
+        result.push_str(&self.generate_expression(&compare.left, 0, 0));
+        result.push_str(&self.generate_expression(&compare.right, 1, 0));
+        result.push_str(&format!("cmp x{}, x{}\n", left_reg, right_reg));
+
+        let arms = self.get_stack_frame_by_index(compare.stack_frame).children.clone();
+        let end_label = self.program_data.target.local_label(&format!("compare_{}_end", compare.stack_frame));
+
+        let mut else_label = end_label.clone();
+
+        for arm in arms.iter() {
+            let arm_label = self.program_data.target.local_label(&format!("compare_arm_{}", arm));
+
+            match self.get_stack_frame_by_index(*arm).kind.clone() {
+                ScopeKind::CompareArm(CompareArmCondition::Condition(operator)) => {
+                    result.push_str(&branch_if(&condition_code(&operator, compare.variable_type.is_signed()), &arm_label));
+                },
+                ScopeKind::CompareArm(CompareArmCondition::Else) => {
+                    else_label = arm_label;
+                },
+                _ => unreachable!()
+            }
+        }
+
+        result.push_str(&branch(&else_label));
+
+        for arm in arms.iter() {
+            result.push_str(&label(&self.program_data.target.local_label(&format!("compare_arm_{}", arm))));
+            result.push_str(&self.generate_child_stack_frame(*arm));
+            result.push_str(&branch(&end_label));
+        }
+
+        result.push_str(&label(&end_label));
+
+        return result;
+    }
+
+    pub fn loop_label(&self, stack_frame : usize, position : &str) -> String {
+        return self.program_data.target.local_label(&format!("loop_{}_{}", stack_frame, position));
+    }
+
     pub fn init_var(&mut self, target_offset : usize, variable_type : VariableType, expression : CgExpression) -> String {
         match (variable_type.clone(), expression.clone()) {
             (
@@ -92,25 +312,154 @@ impl<'a> CodeGenerator<'a> {
             ) => {
                 return String::from(store_literal_to_stack(variable_type, num, target_offset));
             },
+            // Structs don't fit a register, they are copied in pieces.
             (
-                _,
+                VariableType::Struct(struct_type),
                 CgExpression::Identifier(CgIdentifiers::StackVariableData(stack_var_data))
             ) => {
+                return copy_stack_memory(stack_var_data.offset, target_offset, struct_type.size);
+            },
+            // Copied with a single load and store, values of another type are extended by
+            // generate_expression first.
+            (
+                _,
+                CgExpression::Identifier(CgIdentifiers::StackVariableData(stack_var_data))
+            ) if stack_var_data.variable_type == variable_type => {
                 return String::from(format!("{}{}", variable_to_reg(&temp_reg_for_type(variable_type.clone(), true), stack_var_data.offset, variable_type.clone()), store_reg_to_stack(&temp_reg_for_type(variable_type.clone(), false), target_offset, variable_type)));
+            },
+            _ => {
+                return format!("{}{}", self.generate_expression(&expression, 0, 0), store_reg_to_stack(&reg_for_type(expression_reg_num(0), variable_type.clone(), false), target_offset, variable_type));
             }
+        }
+    }
+
+    // Evaluates the expression into the expression register for depth. stack_adjust is added to
+    // variable offsets when sp was moved since they were computed.
+    pub fn generate_expression(&mut self, expression : &CgExpression, depth : usize, stack_adjust : usize) -> String {
+        let reg_num = expression_reg_num(depth);
+
+        match expression {
+            CgExpression::Literal(Literal::Number(num)) => {
+                return mov_num_to_reg(&format!("x{}", reg_num), *num);
+            },
+            CgExpression::Identifier(CgIdentifiers::StackVariableData(stack_var_data)) => {
+                return variable_to_reg(&reg_for_type(reg_num, stack_var_data.variable_type.clone(), true), stack_var_data.offset + stack_adjust, stack_var_data.variable_type.clone());
+            },
+            // Only the low bits of an arg register are defined by the caller.
+            CgExpression::Identifier(CgIdentifiers::RegisterData(register_data)) => {
+                return format!("{}{}", mov_reg_to_reg(&format!("x{}", reg_num), &register_data.register), extend_reg_for_type(reg_num, register_data.variable_type.clone()));
+            },
+            CgExpression::BranchLinked(branch_linked) => {
+                return self.generate_branch_linked(branch_linked, Some(depth));
+            },
+            CgExpression::Address(offset) => {
+                return stack_address_to_reg(reg_num, offset + stack_adjust);
+            },
+            CgExpression::ElementAddress(element_address) => {
+                let mut result = self.generate_expression(&element_address.index, depth, stack_adjust);
+
+                if let Some(length) = element_address.bounds_check {
+                    result.push_str(&index_bounds_check(reg_num, length));
+                }
+
+                match (&element_address.global, &element_address.pointer) {
+                    (Some(global_name), _) => {
+                        result.push_str(&self.program_data.target.global_address(SCRATCH_REGISTER, &self.program_data.target.symbol_name(global_name)));
+                        result.push_str(&element_address_to_reg(reg_num, SCRATCH_REGISTER, element_address.offset, element_address.element_size));
+                    },
+                    // The index stays in the register for depth while the pointer is evaluated.
+                    (_, Some(pointer)) => {
+                        result.push_str(&self.generate_expression(pointer, depth + 1, stack_adjust));
+                        result.push_str(&element_address_to_reg(reg_num, &format!("x{}", expression_reg_num(depth + 1)), element_address.offset, element_address.element_size));
+                    },
+                    (None, None) => result.push_str(&element_address_to_reg(reg_num, "sp", element_address.offset + stack_adjust, element_address.element_size))
+                }
+
+                return result;
+            },
+            CgExpression::GlobalAddress(global_name) => {
+                return self.program_data.target.global_address(&format!("x{}", reg_num), &self.program_data.target.symbol_name(global_name));
+            },
+            CgExpression::StringAddress(index) => {
+                return self.program_data.target.global_address(&format!("x{}", reg_num), &self.program_data.target.string_label(*index));
+            },
+            CgExpression::FieldAddress(field_address) => {
+                let mut result = self.generate_expression(&field_address.pointer, depth, stack_adjust);
+
+                result.push_str(&add_offset_to_reg(reg_num, field_address.offset));
+
+                return result;
+            },
+            CgExpression::Load(load) => {
+                let mut result = self.generate_expression(&load.pointer, depth, stack_adjust);
+
+                result.push_str(&load_from_pointer(reg_num, load.variable_type.clone()));
+
+                return result;
+            },
+            CgExpression::Cast(cast) => {
+                // A variable that isn't wider than the result is loaded as the result type, the
+                // low bytes are at its address.
+                if let CgExpression::Identifier(CgIdentifiers::StackVariableData(stack_var_data)) = cast.value.as_ref() {
+                    if cast.to.get_variable_size() <= cast.from.get_variable_size() && stack_var_data.variable_type == cast.from {
+                        return variable_to_reg(&reg_for_type(reg_num, cast.to.clone(), true), stack_var_data.offset + stack_adjust, cast.to.clone());
+                    }
+                }
+
+                let mut result = self.generate_expression(&cast.value, depth, stack_adjust);
+
+                result.push_str(&cast_reg_to_type(reg_num, cast.from.clone(), cast.to.clone()));
+
+                return result;
+            },
+            CgExpression::BinaryOperation(binary_operation) => {
+                let mut result = self.generate_expression(&binary_operation.left, depth, stack_adjust);
+
+                result.push_str(&self.generate_expression(&binary_operation.right, depth + 1, stack_adjust));
+                result.push_str(&binary_operation_to_reg(&binary_operation.operator, reg_num, expression_reg_num(depth + 1), binary_operation.variable_type.clone()));
+
+                return result;
+            },
+            CgExpression::UnaryOperation(unary_operation) => {
+                let mut result = self.generate_expression(&unary_operation.operand, depth, stack_adjust);
+
+                result.push_str(&unary_operation_to_reg(&unary_operation.operator, reg_num, unary_operation.variable_type.clone()));
+
+                return result;
+            },
             _ => unreachable!()
         }
     }
 
     pub fn initialize_stack_frame(&mut self, stack_frame : usize) -> String {
-        let mem = self.get_stack_frame_by_index(stack_frame).stack_mem_allocated.clone();
+        let stack_frame_borrow = self.get_stack_frame_by_index(stack_frame);
+        let mem = stack_frame_borrow.stack_mem_allocated;
+
+        if !stack_frame_borrow.exported {
+            return create_stack_frame(mem);
+        }
 
-        return create_stack_frame(mem);
+        let mut result = create_exported_stack_frame(mem);
+
+        // C leaves the upper bits of args smaller than 64 bits undefined.
+        let function = self.program_data.functions.get(&stack_frame_borrow.function).unwrap();
+
+        for arg in function.args.iter() {
+            if let MemoryLocationsAst::Register(register) = &arg.memory_location {
+                result.push_str(&extend_reg_for_type(register[1..].parse().unwrap(), arg.arg_var_type.clone()));
+            }
+        }
+
+        return result;
     }
 
     pub fn return_stack_frame(&mut self, stack_frame : usize) -> String {
         let stack_frame_borrow = self.get_stack_frame_by_index(stack_frame);
 
+        if stack_frame_borrow.exported {
+            return destroy_exported_stack_frame(stack_frame_borrow.stack_mem_allocated);
+        }
+
         return destroy_stack_frame(stack_frame_borrow.stack_mem_allocated);
     }
 
@@ -119,19 +468,26 @@ impl<'a> CodeGenerator<'a> {
 
         result.push_str(&self.initialize_stack_frame(stack_frame));
 
-        for statement in self.get_stack_frame_by_index(stack_frame).cg_statements.clone().iter() {
-            let asm_code = self.generate_statement(statement, stack_frame);
+        let cg_statements = self.get_stack_frame_by_index(stack_frame).cg_statements.clone();
+
+        for statement in cg_statements.iter() {
+            let asm_code = self.generate_statement(statement);
 
             result.push_str(&asm_code);
         }
 
-        result.push_str(&self.return_stack_frame(stack_frame));
+        // A trailing return already destroyed the frame.
+        if !matches!(cg_statements.last().map(|statement| &statement.statement_type), Some(CgStatementType::Return(_))) {
+            result.push_str(&self.return_stack_frame(stack_frame));
+        }
 
         return result;
     }
 
+    // Children are emitted inline by their ChildStackFrame statements while the parent is generated,
+    // so processing the function's frame also covers all of its children.
     pub fn process_stack_frame_and_children(&mut self, stack_frame_index : usize) -> String {
-        let compiled_code = self.traverse_stack_frame_children(stack_frame_index);
+        let compiled_code = self.process_stack_frame(stack_frame_index);
 
         return compiled_code;
     }
@@ -139,32 +495,75 @@ impl<'a> CodeGenerator<'a> {
     pub fn process_all_functions(&mut self) -> String {
         let mut result = String::new();
 
-        for (function_name, stack_frame) in self.program_data.functions.clone() {
-            let function_start = format!("_{}:\n", function_name);
+        for function_name in self.program_data.function_order.clone() {
+            let function = self.program_data.functions.get(&function_name).unwrap();
+            let first_stack_frame = function.first_stack_frame;
+            let symbol = self.function_symbol(&function_name);
+
+            if function.exported {
+                result.push_str(&global_symbol(&symbol));
+            }
+
+            let function_start = format!("{}:\n", symbol);
             result.push_str(&function_start);
 
-            result.push_str(&self.process_stack_frame_and_children(stack_frame.first_stack_frame.clone()));
+            result.push_str(&self.process_stack_frame_and_children(first_stack_frame));
         }
 
         return result;
     }
 
-    pub fn traverse_stack_frame_children(&mut self, stack_frame_index : usize) -> String {
+    // Globals are grouped by section, each one aligned to its type. Elements without a value are
+    // zero and globals in .bss only reserve their size. String literals follow the consts.
+    pub fn process_all_globals(&mut self) -> String {
         let mut result = String::new();
 
-        let children = self.get_stack_frame_by_index(stack_frame_index).children.clone();
+        for section in [GlobalSection::Data, GlobalSection::ReadOnly, GlobalSection::Bss] {
+            let names : Vec<&String> = self.program_data.global_order.iter().filter(|name| self.program_data.globals.get(*name).unwrap().section == section).collect();
 
-        let compiled_code = self.process_stack_frame(stack_frame_index);
+            let strings = if section == GlobalSection::ReadOnly { self.program_data.strings.clone() } else { Vec::new() };
+
+            if names.is_empty() && strings.is_empty() {
+                continue;
+            }
 
-        result.push_str(&compiled_code);
+            result.push_str(&format!("{}\n", self.program_data.target.section_directive(&section)));
 
-        for child in children.iter() {
-            let child_compiled = self.traverse_stack_frame_children(child.clone());
+            for name in names {
+                let global = self.program_data.globals.get(name).unwrap();
+                let size = global.variable_type.get_variable_size();
 
-            result.push_str(&child_compiled);
+                result.push_str(&format!(".p2align {}\n{}:\n", global.variable_type.get_variable_alignment().trailing_zeros(), self.program_data.target.symbol_name(name)));
+
+                let element_size = match &global.variable_type {
+                    VariableType::Array(element_type, _) => element_type.get_variable_size(),
+                    variable_type => variable_type.get_variable_size()
+                };
+
+                let directive = match element_size {
+                    1 => ".byte",
+                    2 => ".2byte",
+                    4 => ".4byte",
+                    _ => ".8byte"
+                };
+
+                for value in global.values.iter() {
+                    result.push_str(&format!("{} {}\n", directive, value));
+                }
+
+                let remaining = size - global.values.len() * element_size;
+
+                if remaining > 0 {
+                    result.push_str(&format!(".zero {}\n", remaining));
+                }
+            }
+
+            for (index, string) in strings.iter().enumerate() {
+                result.push_str(&format!("{}:\n{}", self.program_data.target.string_label(index), string_data(string)));
+            }
         }
 
-        return compiled_code;
+        return result;
     }
 
     pub fn get_stack_frame_by_index(&self, index : usize) -> &'_ StackFrame {
@@ -0,0 +1,231 @@
+use crate::datatypes::{ast_statements::Statement, source_map::SourceFile, token::Token};
+
+// Diagnostics are collected by every phase inside ProgramData and rendered once the phase is done.
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Severity {
+    Error,
+    Warning
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum DiagnosticCode {
+    // Tokenizer
+    UnterminatedString,
+    UnterminatedComment,
+    ImportNotFound,
+    FileNotFound,
+    // Parser
+    SyntaxError,
+    UnexpectedToken,
+    InvalidType,
+    DuplicateStruct,
+    // Scope analysis
+    DuplicateFunction,
+    DuplicateVariable,
+    StatementOutsideFunction,
+    BreakOutsideLoop,
+    InvalidCompareArm,
+    UnclosedScope,
+    MissingReturn,
+    InvalidImport,
+    // Semantic analysis
+    UnknownFunction,
+    UnknownVariable,
+    TypeMismatch,
+    ArgCountMismatch,
+    InvalidExpression,
+    InvalidBuiltInArgs,
+    InvalidReturn,
+    LiteralOutOfRange,
+    IndexOutOfBounds
+}
+
+impl DiagnosticCode {
+    pub fn as_str(&self) -> &'static str {
+        return match self {
+            DiagnosticCode::UnterminatedString => "E0101",
+            DiagnosticCode::UnterminatedComment => "E0102",
+            DiagnosticCode::ImportNotFound => "E0103",
+            DiagnosticCode::FileNotFound => "E0104",
+            DiagnosticCode::SyntaxError => "E0201",
+            DiagnosticCode::UnexpectedToken => "E0202",
+            DiagnosticCode::InvalidType => "E0203",
+            DiagnosticCode::DuplicateStruct => "E0204",
+            DiagnosticCode::DuplicateFunction => "E0301",
+            DiagnosticCode::DuplicateVariable => "E0302",
+            DiagnosticCode::StatementOutsideFunction => "E0303",
+            DiagnosticCode::BreakOutsideLoop => "E0304",
+            DiagnosticCode::InvalidCompareArm => "E0305",
+            DiagnosticCode::UnclosedScope => "E0306",
+            DiagnosticCode::MissingReturn => "W0307",
+            DiagnosticCode::InvalidImport => "E0308",
+            DiagnosticCode::UnknownFunction => "E0401",
+            DiagnosticCode::UnknownVariable => "E0402",
+            DiagnosticCode::TypeMismatch => "E0403",
+            DiagnosticCode::ArgCountMismatch => "E0404",
+            DiagnosticCode::InvalidExpression => "E0405",
+            DiagnosticCode::InvalidBuiltInArgs => "E0406",
+            DiagnosticCode::InvalidReturn => "E0407",
+            DiagnosticCode::LiteralOutOfRange => "E0408",
+            DiagnosticCode::IndexOutOfBounds => "E0409"
+        };
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Span {
+    // Index of the file in ProgramData::files.
+    pub file: usize,
+    pub line: usize,
+    pub col: usize,
+    pub start_pos: usize,
+    pub end_pos: usize
+}
+
+impl Span {
+    // The whole file instead of a place in it, rendered without a line.
+    pub fn whole_file(file : usize) -> Self {
+        return Self { file, line: 0, col: 0, start_pos: 0, end_pos: 0 };
+    }
+
+    pub fn from_token(token : &Token) -> Self {
+        return Self { file: token.file, line: token.line, col: token.col, start_pos: token.start_pos, end_pos: token.end_pos };
+    }
+
+    pub fn from_statement(statement : &Statement) -> Self {
+        return Self { file: statement.file, line: statement.line, col: statement.col, start_pos: statement.start_pos, end_pos: statement.end_pos };
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Label {
+    pub span: Span,
+    pub message: String
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: DiagnosticCode,
+    pub message: String,
+    pub primary: Label,
+    pub secondary: Vec<Label>,
+    pub notes: Vec<String>,
+    pub help: Option<String>
+}
+
+impl Diagnostic {
+    pub fn error(code : DiagnosticCode, message : &str, span : Span) -> Self {
+        return Self { severity: Severity::Error, code, message: String::from(message), primary: Label { span, message: String::new() }, secondary: Vec::new(), notes: Vec::new(), help: None };
+    }
+
+    pub fn warning(code : DiagnosticCode, message : &str, span : Span) -> Self {
+        return Self { severity: Severity::Warning, ..Self::error(code, message, span) };
+    }
+
+    pub fn with_label(mut self, label : &str) -> Self {
+        self.primary.message = String::from(label);
+
+        return self;
+    }
+
+    pub fn with_secondary(mut self, span : Span, label : &str) -> Self {
+        self.secondary.push(Label { span, message: String::from(label) });
+
+        return self;
+    }
+
+    pub fn with_note(mut self, note : &str) -> Self {
+        self.notes.push(String::from(note));
+
+        return self;
+    }
+
+    pub fn with_help(mut self, help : &str) -> Self {
+        self.help = Some(String::from(help));
+
+        return self;
+    }
+
+    // Renders the diagnostic with the offending source lines underlined, for example:
+    //
+    // error[E0402]: Variable not found: foo
+    //  --> main.byte:3:5
+    //   |
+    // 3 |     i32 x = foo;
+    //   |     ^^^^^^^^^^^^
+    //
+    // Labels in another file than the primary one get their own ::: file:line:col header.
+    pub fn render(&self, files : &[SourceFile]) -> String {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning"
+        };
+
+        let gutter_width = self.secondary.iter().map(|label| label.span.line).chain([self.primary.span.line]).max().unwrap().to_string().len();
+        let empty_gutter = format!("{} |", " ".repeat(gutter_width));
+
+        let mut result = format!("{}[{}]: {}\n", severity, self.code.as_str(), self.message);
+
+        let primary_file = files.get(self.primary.span.file).unwrap();
+
+        if self.primary.span.line == 0 {
+            result.push_str(&format!("{}--> {}\n", " ".repeat(gutter_width), primary_file.name));
+
+            return result;
+        }
+
+        result.push_str(&format!("{}--> {}:{}:{}\n", " ".repeat(gutter_width), primary_file.name, self.primary.span.line, self.primary.span.col));
+        result.push_str(&format!("{}\n", empty_gutter));
+        result.push_str(&render_label(&self.primary, '^', &primary_file.source, gutter_width));
+
+        for label in self.secondary.iter() {
+            let label_file = files.get(label.span.file).unwrap();
+
+            if label.span.file != self.primary.span.file {
+                result.push_str(&format!("{}::: {}:{}:{}\n", " ".repeat(gutter_width), label_file.name, label.span.line, label.span.col));
+            }
+
+            result.push_str(&format!("{}\n", empty_gutter));
+            result.push_str(&render_label(label, '-', &label_file.source, gutter_width));
+        }
+
+        if !self.notes.is_empty() || self.help.is_some() {
+            result.push_str(&format!("{}\n", empty_gutter));
+        }
+
+        for note in self.notes.iter() {
+            result.push_str(&format!("{} = note: {}\n", " ".repeat(gutter_width), note));
+        }
+
+        if let Some(help) = &self.help {
+            result.push_str(&format!("{} = help: {}\n", " ".repeat(gutter_width), help));
+        }
+
+        return result;
+    }
+}
+
+fn render_label(label : &Label, marker : char, source : &str, gutter_width : usize) -> String {
+    let line_text = source.lines().nth(label.span.line.saturating_sub(1)).unwrap_or("");
+
+    let start_col = label.span.col.max(1);
+    let line_chars = line_text.chars().count();
+
+    // Spans covering several lines are only underlined until the end of the first one.
+    let span_len = label.span.end_pos.saturating_sub(label.span.start_pos).max(1);
+    let marker_len = span_len.min((line_chars + 1).saturating_sub(start_col)).max(1);
+
+    let mut result = format!("{:>width$} | {}\n", label.span.line, line_text, width = gutter_width);
+
+    result.push_str(&format!("{} | {}{}", " ".repeat(gutter_width), " ".repeat(start_col - 1), marker.to_string().repeat(marker_len)));
+
+    if !label.message.is_empty() {
+        result.push_str(&format!(" {}", label.message));
+    }
+
+    result.push('\n');
+
+    return result;
+}
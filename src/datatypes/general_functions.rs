@@ -1,3 +1,13 @@
 pub fn align_memory(mem : usize, alignment : usize) -> usize {
     return (mem + (alignment - 1)) & !(alignment - 1);
 }
+
+// Places size bytes after the memory already allocated at the next multiple of alignment and
+// returns their offset.
+pub fn allocate_memory(mem_allocated : &mut usize, size : usize, alignment : usize) -> usize {
+    let offset = align_memory(*mem_allocated, alignment);
+
+    *mem_allocated = offset + size;
+
+    return offset;
+}
@@ -10,3 +10,7 @@ pub mod tokenizer;
 pub mod program_data;
 pub mod assembly_instructions;
 pub mod general_functions;
+pub mod target;
+pub mod diagnostic;
+pub mod source_map;
+pub mod type_checker;
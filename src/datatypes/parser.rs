@@ -1,8 +1,11 @@
-use std::panic;
+use std::{collections::HashMap, panic};
 
-use crate::datatypes::ast_statements::{BranchLinkedAst, BuiltInFunctionsAst, Expression, Format, Function, FunctionArg, FunctionDeclaration, Literal, MemoryLocationsAst, Statement, Statements, VariableDeclaration, VariableType};
-use crate::datatypes::general_functions::align_memory;
+use crate::datatypes::ast_statements::{BinaryOperation, BinaryOperators, BranchLinkedAst, BuiltInFunctionsAst, Cast, CompareArmCondition, CompareAst, ElementAssignment, Expression, FieldAssignment, FieldAst, Format, Function, FunctionArg, FunctionDeclaration, GlobalDeclaration, IndexAst, Literal, MemoryLocationsAst, ScopeKind, Statement, Statements, StoreAst, StructDefinition, StructField, UnaryOperation, UnaryOperators, VariableAssignment, VariableDeclaration, VariableType};
+use crate::datatypes::assembly_instructions::asm::is_location_register;
+use crate::datatypes::diagnostic::{Diagnostic, DiagnosticCode, Span};
+use crate::datatypes::general_functions::{align_memory, allocate_memory};
 use crate::datatypes::program_data::ProgramData;
+use crate::datatypes::target::Target;
 use crate::datatypes::token::{BuiltInFunctions, Identifiers, Keywords, MemoryLocations, Operators, Punctuations, Token, TokenType};
 
 macro_rules! expect_token_with_err {
@@ -16,7 +19,10 @@ macro_rules! expect_token_with_err {
 
 macro_rules! throw_err {
     ($self:expr, $error:expr) => {
-        $self.handle_error($error);
+        throw_err!($self, DiagnosticCode::SyntaxError, $error);
+    };
+    ($self:expr, $code:expr, $error:expr) => {
+        $self.handle_error($code, $error);
 
         return None;
     };
@@ -25,23 +31,41 @@ macro_rules! throw_err {
 pub struct Parser<'a> {
     program_data: &'a mut ProgramData,
     position: usize,
+    // Position of the struct keyword of every declared struct.
+    struct_positions: HashMap<String, usize>,
+    // Structs being laid out, a struct found here again contains itself.
+    structs_in_progress: Vec<String>,
+    failed_structs: Vec<String>
 }
 
 impl<'a> Parser<'a> {
     pub fn new(program_data: &'a mut ProgramData) -> Self {
-        return Self{program_data, position: 0};
+        return Self{program_data, position: 0, struct_positions: HashMap::new(), structs_in_progress: Vec::new(), failed_structs: Vec::new()};
     }
 
     pub fn parse_all(&mut self) -> () {
+        // Structs are laid out before anything else is parsed, so they can be used in any file
+        // and before their declaration.
+        self.layout_all_structs();
+
+        self.position = 0;
+
         loop {
             match self.parse_next() {
                 Some(statement) => {
                     self.program_data.statements.push(statement.clone());
 
-                    print!("{:?}", statement);
+                    if self.program_data.verbose {
+                        print!("{:?}", statement);
+                    }
 
+                    // Every file ends with an EOF, only the last one ends the program.
                     if statement.statement_type == Statements::EOF {
-                        break;
+                        if self.position + 1 >= self.program_data.tokens.len() {
+                            break;
+                        }
+
+                        self.advance_position();
                     }
                 },
                 None => {
@@ -51,6 +75,316 @@ impl<'a> Parser<'a> {
         }
     }
 
+    pub fn layout_all_structs(&mut self) -> () {
+        let mut names : Vec<String> = Vec::new();
+
+        for position in 0..self.program_data.tokens.len() {
+            if self.program_data.tokens.get(position).unwrap().kind != TokenType::Keyword(Keywords::Struct) {
+                continue;
+            }
+
+            // Declarations without a name are reported when they are parsed.
+            let Some(TokenType::Identifiers(Identifiers::Identifier(name))) = self.program_data.tokens.get(position + 1).map(|token| token.kind.clone()) else {
+                continue;
+            };
+
+            if let Some(first_position) = self.struct_positions.get(&name) {
+                let diagnostic = Diagnostic::error(DiagnosticCode::DuplicateStruct, &format!("Duplicate struct: {}", name), Span::from_token(self.program_data.tokens.get(position + 1).unwrap()))
+                    .with_label("redefined here")
+                    .with_secondary(Span::from_token(self.program_data.tokens.get(first_position + 1).unwrap()), "first defined here");
+
+                self.program_data.diagnostics.push(diagnostic);
+
+                continue;
+            }
+
+            self.struct_positions.insert(name.clone(), position);
+            names.push(name);
+        }
+
+        for name in names.iter() {
+            self.layout_struct(name);
+        }
+    }
+
+    // Type of the struct, laid out the first time it is used. None if its declaration has errors.
+    pub fn layout_struct(&mut self, name : &str) -> Option<VariableType> {
+        if let Some(struct_definition) = self.program_data.structs.get(name) {
+            return Some(struct_definition.variable_type());
+        }
+
+        if self.failed_structs.iter().any(|failed| failed == name) {
+            return None;
+        }
+
+        if self.structs_in_progress.iter().any(|in_progress| in_progress == name) {
+            throw_err!(self, DiagnosticCode::InvalidType, &format!("Struct {} contains itself, its size can't be known", name));
+        }
+
+        let saved_position = self.position;
+
+        self.position = *self.struct_positions.get(name).unwrap();
+        self.structs_in_progress.push(String::from(name));
+
+        let struct_definition = self.parse_struct_declaration();
+
+        self.structs_in_progress.pop();
+        self.position = saved_position;
+
+        let Some(struct_definition) = struct_definition else {
+            self.failed_structs.push(String::from(name));
+
+            return None;
+        };
+
+        let variable_type = struct_definition.variable_type();
+
+        self.program_data.struct_order.push(String::from(name));
+        self.program_data.structs.insert(String::from(name), struct_definition);
+
+        return Some(variable_type);
+    }
+
+    // Parses struct Name { type field; ... } starting at the struct keyword. Fields are placed in
+    // declaration order at their natural alignment, or back to back if the struct is packed.
+    pub fn parse_struct_declaration(&mut self) -> Option<StructDefinition> {
+        let packed = self.position > 0 && self.program_data.tokens.get(self.position - 1).unwrap().kind == TokenType::Keyword(Keywords::Packed);
+
+        self.advance_position();
+
+        let name_token = self.current_token();
+
+        let TokenType::Identifiers(Identifiers::Identifier(name)) = name_token.kind.clone() else {
+            throw_err!(self, "Expected the name of the struct");
+        };
+
+        self.advance_position();
+
+        expect_token_with_err!(TokenType::Punctuation(Punctuations::OpenBraces), self);
+
+        let mut fields : Vec<StructField> = Vec::new();
+        let mut size = 0;
+        let mut alignment = 1;
+
+        while TokenType::Punctuation(Punctuations::ClosedBraces) != self.current_token().kind {
+            let kind = self.current_token().kind;
+
+            if !self.starts_type(&kind) {
+                throw_err!(self, DiagnosticCode::InvalidType, "Expected the type of a field");
+            }
+
+            let Some(variable_type) = self.parse_variable_type() else {
+                return None;
+            };
+
+            if variable_type == VariableType::Void {
+                throw_err!(self, DiagnosticCode::InvalidType, "Fields can't be void");
+            }
+
+            let TokenType::Identifiers(Identifiers::Identifier(field_name)) = self.current_token().kind else {
+                throw_err!(self, "Expected the name of the field");
+            };
+
+            if fields.iter().any(|field| field.name == field_name) {
+                throw_err!(self, DiagnosticCode::DuplicateVariable, &format!("Duplicate field {} in struct {}", field_name, name));
+            }
+
+            self.advance_position();
+
+            expect_token_with_err!(TokenType::Punctuation(Punctuations::Semicolon), self);
+
+            let field_alignment = if packed { 1 } else { variable_type.get_variable_alignment() };
+            let offset = allocate_memory(&mut size, variable_type.get_variable_size(), field_alignment);
+
+            alignment = alignment.max(field_alignment);
+
+            fields.push(StructField{name: field_name, variable_type, offset});
+        }
+
+        if fields.is_empty() {
+            throw_err!(self, DiagnosticCode::InvalidType, &format!("Struct {} needs at least one field", name));
+        }
+
+        self.advance_position();
+
+        // The size is rounded up to the alignment so every element of an array of them is aligned.
+        return Some(StructDefinition{name, fields, size: align_memory(size, alignment), alignment, packed, span: Span::from_token(&name_token)});
+    }
+
+    // Parses the rest of static type name = value; or const type name = value;. Values are checked
+    // to be numbers by scope analysis.
+    pub fn parse_global_declaration(&mut self, first_token : &Token, constant : bool) -> Option<Statement> {
+        self.advance_position();
+
+        let kind = self.current_token().kind;
+
+        if !self.starts_type(&kind) {
+            throw_err!(self, DiagnosticCode::InvalidType, "Expected the type of the global");
+        }
+
+        let Some(variable_type) = self.parse_variable_type() else {
+            return None;
+        };
+
+        if variable_type == VariableType::Void {
+            throw_err!(self, DiagnosticCode::InvalidType, "Can't declare global as void");
+        }
+
+        let TokenType::Identifiers(Identifiers::Identifier(name)) = self.current_token().kind else {
+            throw_err!(self, "Expected the name of the global");
+        };
+
+        self.advance_position();
+
+        let values = if TokenType::Operator(Operators::Assignment) == self.current_token().kind {
+            self.advance_position();
+
+            let mut values : Vec<Expression> = Vec::new();
+
+            if TokenType::Punctuation(Punctuations::OpenBraces) == self.current_token().kind {
+                self.advance_position();
+
+                while TokenType::Punctuation(Punctuations::ClosedBraces) != self.current_token().kind {
+                    let Some(value) = self.parse_expression(0) else {
+                        return None;
+                    };
+
+                    values.push(value);
+
+                    if TokenType::Punctuation(Punctuations::Comma) == self.current_token().kind {
+                        self.advance_position();
+                    } else if TokenType::Punctuation(Punctuations::ClosedBraces) != self.current_token().kind {
+                        throw_err!(self, "Expected ',' or '}' between the elements");
+                    }
+                }
+
+                self.advance_position();
+            } else {
+                let Some(value) = self.parse_expression(0) else {
+                    return None;
+                };
+
+                values.push(value);
+            }
+
+            Some(values)
+        } else {
+            None
+        };
+
+        let end_pos = self.current_token().end_pos;
+
+        expect_token_with_err!(TokenType::Punctuation(Punctuations::Semicolon), self);
+
+        return Some(Statement::new(first_token, end_pos, Statements::GlobalDeclaration(GlobalDeclaration{name, variable_type, values, constant})));
+    }
+
+    // Struct declarations were laid out before parsing, their tokens are only skipped.
+    pub fn skip_struct_declaration(&mut self, first_token : &Token) -> Option<Statement> {
+        self.advance_position();
+
+        let TokenType::Identifiers(Identifiers::Identifier(name)) = self.current_token().kind else {
+            throw_err!(self, "Expected the name of the struct");
+        };
+
+        let mut depth = 0;
+
+        loop {
+            match self.current_token().kind {
+                TokenType::Punctuation(Punctuations::OpenBraces) => depth += 1,
+                TokenType::Punctuation(Punctuations::ClosedBraces) => {
+                    depth -= 1;
+
+                    if depth == 0 {
+                        break;
+                    }
+                },
+                TokenType::EOF => {
+                    throw_err!(self, DiagnosticCode::UnexpectedToken, &format!("Struct {} is missing its closing brace", name));
+                },
+                _ => {}
+            }
+
+            self.advance_position();
+        }
+
+        let end_pos = self.current_token().end_pos;
+
+        self.advance_position();
+
+        return Some(Statement::new(first_token, end_pos, Statements::StructDeclaration(name)));
+    }
+
+    pub fn is_struct_name(&self, name : &str) -> bool {
+        return self.struct_positions.contains_key(name);
+    }
+
+    // Types start with a built in type, a * or the name of a struct.
+    pub fn starts_type(&self, kind : &TokenType) -> bool {
+        return match kind {
+            TokenType::Keyword(Keywords::VariableType(_)) | TokenType::Operator(Operators::Star) => true,
+            TokenType::Identifiers(Identifiers::Identifier(name)) => self.is_struct_name(name),
+            _ => false
+        };
+    }
+
+    // Parses name.field.field, reading fields of structs.
+    pub fn parse_field(&mut self) -> Option<FieldAst> {
+        let TokenType::Identifiers(Identifiers::Identifier(name)) = self.current_token().kind else {
+            throw_err!(self, "Expected identifier");
+        };
+
+        self.advance_position();
+
+        let mut fields : Vec<String> = Vec::new();
+
+        while TokenType::Punctuation(Punctuations::Dot) == self.current_token().kind {
+            self.advance_position();
+
+            let TokenType::Identifiers(Identifiers::Identifier(field)) = self.current_token().kind else {
+                throw_err!(self, "Expected the name of a field after .");
+            };
+
+            self.advance_position();
+
+            fields.push(field);
+        }
+
+        return Some(FieldAst{name, fields});
+    }
+
+    // Parses [index] starting at the open square bracket.
+    pub fn parse_index(&mut self) -> Option<Expression> {
+        self.advance_position();
+
+        let Some(index) = self.parse_expression(0) else {
+            return None;
+        };
+
+        expect_token_with_err!(TokenType::Punctuation(Punctuations::ClosedSquareBracket), self);
+
+        return Some(index);
+    }
+
+    // Parses the rest of array[index] = value; starting at the open square bracket.
+    pub fn parse_element_assignment(&mut self, first_token : &Token, array : FieldAst) -> Option<Statement> {
+        let Some(index) = self.parse_index() else {
+            return None;
+        };
+
+        expect_token_with_err!(TokenType::Operator(Operators::Assignment), self);
+
+        let Some(value) = self.parse_expression(0) else {
+            return None;
+        };
+
+        let end_pos = self.current_token().end_pos;
+
+        expect_token_with_err!(TokenType::Punctuation(Punctuations::Semicolon), self);
+
+        return Some(Statement::new(first_token, end_pos, Statements::ElementAssignment(ElementAssignment{array, index, value})));
+    }
+
     pub fn parse_format_built_in_function(&mut self, first_token : &Token) -> Option<Statement> {
         self.advance_position();
 
@@ -96,7 +430,7 @@ impl<'a> Parser<'a> {
                     break;
                 },
                 _ => {
-                    throw_err!(self, "");
+                    throw_err!(self, "Expected ',' or ')' in format");
                 }
             }
         }
@@ -104,7 +438,20 @@ impl<'a> Parser<'a> {
         return Some(Statement::new(first_token, self.current_token().end_pos, Statements::Expression(Expression::BuiltInFunction(BuiltInFunctionsAst::Format(Format{string: string_literal, args_provided: args})))));
     }
 
-    pub fn parse_function_declaration(&mut self, first_token : &Token, func_return_type : VariableType) -> Option<Statement> {
+    // Extern functions end with a semicolon instead of a body. The args of extern and exported
+    // functions are placed by the C calling convention.
+    pub fn parse_function_declaration(&mut self, first_token : &Token, func_return_type : VariableType, packed : bool, external : bool, exported : bool) -> Option<Statement> {
+        let c_abi = external || exported;
+        let (c_abi_name, c_abi_title) = if external { ("extern", "Extern") } else { ("exported", "Exported") };
+
+        if func_return_type.is_array() {
+            throw_err!(self, DiagnosticCode::InvalidType, "Arrays can't be returned from functions");
+        }
+
+        if func_return_type.is_struct() {
+            throw_err!(self, DiagnosticCode::InvalidType, "Structs can't be returned from functions, fill one through a pointer arg instead");
+        }
+
         self.advance_position();
 
         let func_name_tkn = self.current_token();
@@ -125,57 +472,69 @@ impl<'a> Parser<'a> {
         let mut args : Vec<FunctionArg> = Vec::new();
 
         let mut stack_mem_allocated = 0;
+        // Register, name and name token of every arg passed in a register.
+        let mut register_args : Vec<(String, String, Token)> = Vec::new();
 
         loop {
             match self.current_token().kind.clone() {
                 TokenType::Punctuation(Punctuations::ClosedParenthesis) => break,
-                TokenType::Keyword(Keywords::VariableType(var_type)) => {
-                    self.advance_position();
+                kind if self.starts_type(&kind) => {
+                    let Some(var_type) = self.parse_variable_type() else {
+                        return None;
+                    };
+
+                    if var_type.is_array() {
+                        throw_err!(self, DiagnosticCode::InvalidType, "Arrays can't be passed to functions, pass a pointer to the first element with addr");
+                    }
 
-                    let TokenType::Identifiers(Identifiers::Identifier(arg_name)) = self.current_token().kind else {
+                    let arg_token = self.current_token();
+
+                    let TokenType::Identifiers(Identifiers::Identifier(arg_name)) = arg_token.kind.clone() else {
                         throw_err!(self, "Unknown token in function arg");
                     };
 
                     self.advance_position();
 
-                    if TokenType::Punctuation(Punctuations::Colon) != self.current_token().kind {
-                        args.push(FunctionArg { arg_var_type: var_type.clone(), arg_name, memory_location: MemoryLocationsAst::Stack(stack_mem_allocated) });
-                        stack_mem_allocated += var_type.get_variable_size();
-                        self.advance_position();
-                        continue;
+                    if c_abi && var_type.is_struct() {
+                        throw_err!(self, DiagnosticCode::InvalidType, &format!("Structs can't be passed to {} functions, pass a pointer instead", c_abi_name));
                     }
 
-                    self.advance_position();
+                    let memory_location = if TokenType::Punctuation(Punctuations::Colon) == self.current_token().kind {
+                        if c_abi {
+                            throw_err!(self, DiagnosticCode::InvalidType, &format!("Arg {} of {} function {} can't have a location, it follows the C calling convention", arg_name, c_abi_name, func_name));
+                        }
 
-                    expect_token_with_err!(TokenType::Punctuation(Punctuations::OpenSquareBracket), self);
+                        self.advance_position();
 
-                    let memory_location : MemoryLocationsAst = match self.current_token().kind {
-                        TokenType::MemoryLocation(MemoryLocations::Stack) => {
-                            self.advance_position();
-                            stack_mem_allocated += var_type.get_variable_size();
-                            MemoryLocationsAst::Stack(stack_mem_allocated - var_type.get_variable_size())
-                        },
-                        TokenType::MemoryLocation(MemoryLocations::Register) => {
-                            self.advance_position();
+                        let Some(memory_location) = self.parse_memory_location(&var_type, &mut stack_mem_allocated, packed) else {
+                            return None;
+                        };
 
-                            expect_token_with_err!(TokenType::Punctuation(Punctuations::OpenParenthesis), self);
+                        if var_type.is_struct() && matches!(memory_location, MemoryLocationsAst::Register(_)) {
+                            throw_err!(self, DiagnosticCode::InvalidType, "Structs are passed on the stack, use [stack]");
+                        }
 
-                            let TokenType::Identifiers(Identifiers::Identifier(arg_register)) = self.current_token().kind else {
-                                throw_err!(self, "");
-                            };
+                        // The return value may share a register with an arg, two args can't.
+                        if let MemoryLocationsAst::Register(register) = &memory_location {
+                            if let Some((_, first_name, first_token)) = register_args.iter().find(|(used, _, _)| used == register) {
+                                let diagnostic = Diagnostic::error(DiagnosticCode::InvalidType, &format!("Args {} and {} of {} are both passed in {}", first_name, arg_name, func_name, register), Span::from_token(&arg_token))
+                                    .with_label("passed in the same register here")
+                                    .with_secondary(Span::from_token(first_token), "first passed here");
 
-                            self.advance_position();
+                                self.program_data.diagnostics.push(diagnostic);
+                                self.skip_until_semicolon();
 
-                            expect_token_with_err!(TokenType::Punctuation(Punctuations::ClosedParenthesis), self);
+                                return None;
+                            }
 
-                            MemoryLocationsAst::Register(arg_register)
-                        },
-                        _ => {
-                            throw_err!(self, "");
+                            register_args.push((register.clone(), arg_name.clone(), arg_token.clone()));
                         }
+
+                        memory_location
+                    } else {
+                        // Args without a location are passed on the stack.
+                        MemoryLocationsAst::Stack(Self::allocate_stack_slot(&var_type, &mut stack_mem_allocated, packed))
                     };
-                    
-                    expect_token_with_err!(TokenType::Punctuation(Punctuations::ClosedSquareBracket), self);
 
                     match self.current_token().kind {
                         TokenType::Punctuation(Punctuations::Comma) => {
@@ -197,67 +556,280 @@ impl<'a> Parser<'a> {
 
         self.advance_position();
 
+        // Return values go to x0 unless another location is given with -> [location].
+        let return_location = if TokenType::Punctuation(Punctuations::Arrow) == self.current_token().kind {
+            if c_abi {
+                throw_err!(self, DiagnosticCode::InvalidType, &format!("{} function {} can't have a return location, it returns in x0", c_abi_title, func_name));
+            }
+
+            if func_return_type == VariableType::Void {
+                throw_err!(self, DiagnosticCode::InvalidType, "Void functions can't have a return location");
+            }
+
+            self.advance_position();
+
+            // The entry point passes the value main returns to exit in a register.
+            if func_name == "main" && func_name_tkn.file == 0 && self.peek_token(1).kind == TokenType::MemoryLocation(MemoryLocations::Stack) {
+                throw_err!(self, DiagnosticCode::InvalidType, "main can't return on the stack, the entry point reads its value from a register");
+            }
+
+            let Some(memory_location) = self.parse_memory_location(&func_return_type, &mut stack_mem_allocated, packed) else {
+                return None;
+            };
+
+            Some(memory_location)
+        } else if func_return_type != VariableType::Void {
+            Some(MemoryLocationsAst::Register(String::from("x0")))
+        } else {
+            None
+        };
+
+        if external {
+            if TokenType::Punctuation(Punctuations::OpenBraces) == self.current_token().kind {
+                throw_err!(self, DiagnosticCode::SyntaxError, &format!("Extern function {} can't have a body, end its declaration with ';'", func_name));
+            }
+
+            let end_pos = self.current_token().end_pos;
+
+            expect_token_with_err!(TokenType::Punctuation(Punctuations::Semicolon), self);
+
+            let args_stack_mem_allocated = self.place_c_args(&mut args);
+
+            return Some(Statement::new(first_token, end_pos, Statements::FunctionDeclaration(FunctionDeclaration{
+                args,
+                name : func_name,
+                return_type: func_return_type,
+                return_location,
+                args_stack_mem_allocated,
+                packed,
+                external,
+                exported
+            })));
+        }
+
         expect_token_with_err!(TokenType::Punctuation(Punctuations::OpenBraces), self);
 
+        let args_stack_mem_allocated = if exported {
+            self.place_c_args(&mut args)
+        } else {
+            align_memory(stack_mem_allocated, 16)
+        };
+
         return Some(Statement::new(first_token, self.current_token().end_pos, Statements::FunctionDeclaration(FunctionDeclaration{
             args,
             name : func_name,
             return_type: func_return_type,
-            args_stack_mem_allocated: align_memory(stack_mem_allocated, 16)
+            return_location,
+            args_stack_mem_allocated,
+            packed,
+            external,
+            exported
         })));
     }
 
-    pub fn parse_variable_declaration(&mut self, first_token : &Token, var_type : VariableType, var_name : &String) -> Option<Statement> {
-        if var_type == VariableType::Void {
-            throw_err!(self, "Can't declare variable as void");
-        }
+    // AAPCS64 passes the first 8 args in x0 to x7 and the rest on the stack in declaration order,
+    // starting at sp. Linux gives every stack arg 8 bytes, Apple places them at their natural
+    // alignment. Returns the stack memory the caller allocates for them.
+    pub fn place_c_args(&self, args : &mut Vec<FunctionArg>) -> usize {
+        let mut stack_positions : Vec<(usize, usize)> = Vec::new();
+        let mut stack_mem_allocated = 0;
 
-        let end_pos;
+        for arg in args.iter().skip(8) {
+            let size = arg.arg_var_type.get_variable_size();
 
-        self.advance_position();
+            let position = match self.program_data.target {
+                Target::Aarch64Macos => allocate_memory(&mut stack_mem_allocated, size, arg.arg_var_type.get_variable_alignment()),
+                Target::Aarch64Linux => allocate_memory(&mut stack_mem_allocated, 8, 8)
+            };
 
-        let value : Option<Expression> = match self.current_token().kind {
-            TokenType::Operator(operator) => {
-                if operator == Operators::Assignment {
-                    self.advance_position();
+            stack_positions.push((position, size));
+        }
 
-                    let value = self.current_token();
+        let args_stack_mem_allocated = align_memory(stack_mem_allocated, 16);
 
-                    let option_value : Option<Expression> = match value.kind {
-                        TokenType::Literal(identifier) => {
-                            match identifier {
-                                Literal::String(string_val) => Some(Expression::Literal(Literal::String(string_val))),
-                                Literal::Number(num_val) => Some(Expression::Literal(Literal::Number(num_val))),
-                                _ => None
-                            }
-                        },
-                        TokenType::Identifiers(Identifiers::Identifier(identifier)) => Some(Expression::Identifier(Identifiers::Identifier(identifier))),
-                        _ => None
-                    };
+        // Stack locations count from the end of the args memory.
+        for (i, arg) in args.iter_mut().enumerate() {
+            arg.memory_location = if i < 8 {
+                MemoryLocationsAst::Register(format!("x{}", i))
+            } else {
+                let (position, size) = stack_positions[i - 8];
 
-                    if option_value.is_none() {
-                        throw_err!(self, "Please Provide a valid value");
-                    }
+                MemoryLocationsAst::Stack(args_stack_mem_allocated - position - size)
+            };
+        }
 
-                    let value = option_value.unwrap();
+        return args_stack_mem_allocated;
+    }
 
-                    self.advance_position();
+    // Parses a type like u8, *u8 or u8[64], every * in front adds a pointer around the type after
+    // it and [length] makes an array of the type before it.
+    pub fn parse_variable_type(&mut self) -> Option<VariableType> {
+        let mut pointer_depth = 0;
 
-                    let semicolon_token = self.current_token();
+        while TokenType::Operator(Operators::Star) == self.current_token().kind {
+            pointer_depth += 1;
 
-                    expect_token_with_err!(TokenType::Punctuation(Punctuations::Semicolon), self);
+            self.advance_position();
+        }
 
-                    end_pos = semicolon_token.end_pos;
-        
-                    Some(value)
-                } else {
-                    throw_err!(self, "Invalid identifier found");
-                }
-            },
-           TokenType::Punctuation(Punctuations::Semicolon) => {
-                end_pos = self.current_token().end_pos;
+        let mut var_type = match self.current_token().kind {
+            TokenType::Keyword(Keywords::VariableType(var_type)) => var_type,
+            TokenType::Identifiers(Identifiers::Identifier(name)) if self.is_struct_name(&name) => {
+                let Some(struct_type) = self.layout_struct(&name) else {
+                    // Reported when the struct was laid out.
+                    self.skip_until_semicolon();
 
-                self.advance_position();
+                    return None;
+                };
+
+                struct_type
+            },
+            _ => {
+                throw_err!(self, DiagnosticCode::InvalidType, "Expected a type after *");
+            }
+        };
+
+        if pointer_depth != 0 && var_type == VariableType::Void {
+            throw_err!(self, DiagnosticCode::InvalidType, "Pointers to void aren't supported, use *u8 for raw bytes");
+        }
+
+        self.advance_position();
+
+        for _ in 0..pointer_depth {
+            var_type = VariableType::Pointer(Box::new(var_type));
+        }
+
+        if TokenType::Punctuation(Punctuations::OpenSquareBracket) == self.current_token().kind {
+            self.advance_position();
+
+            let TokenType::Literal(Literal::Number(length)) = self.current_token().kind else {
+                throw_err!(self, DiagnosticCode::InvalidType, "Expected the number of elements of the array");
+            };
+
+            if length <= 0 {
+                throw_err!(self, DiagnosticCode::InvalidType, "Arrays must have at least one element");
+            }
+
+            if var_type == VariableType::Void {
+                throw_err!(self, DiagnosticCode::InvalidType, "Arrays of void aren't supported");
+            }
+
+            self.advance_position();
+
+            expect_token_with_err!(TokenType::Punctuation(Punctuations::ClosedSquareBracket), self);
+
+            if TokenType::Punctuation(Punctuations::OpenSquareBracket) == self.current_token().kind {
+                throw_err!(self, DiagnosticCode::InvalidType, "Arrays of arrays aren't supported");
+            }
+
+            var_type = VariableType::Array(Box::new(var_type), length as usize);
+        }
+
+        return Some(var_type);
+    }
+
+    // Function or variable declaration after its type.
+    pub fn parse_typed_statement(&mut self, first_token : &Token, var_type : VariableType) -> Option<Statement> {
+        match self.current_token().kind {
+            TokenType::Punctuation(Punctuations::Colon) => {
+                return self.parse_function_declaration(first_token, var_type, false, false, false);
+            },
+            TokenType::Identifiers(Identifiers::Identifier(var_name)) => {
+                return self.parse_variable_declaration(first_token, var_type, &var_name);
+            },
+            TokenType::Punctuation(_) => {
+                throw_err!(self, "Unknown Punctuation");
+            },
+            _ => {
+                throw_err!(self, "Syntax Error");
+            }
+        }
+    }
+
+    // Stack args and return slots are placed at their natural alignment unless the function is packed.
+    pub fn allocate_stack_slot(var_type : &VariableType, stack_mem_allocated : &mut usize, packed : bool) -> usize {
+        let alignment = if packed { 1 } else { var_type.get_variable_alignment() };
+
+        return allocate_memory(stack_mem_allocated, var_type.get_variable_size(), alignment);
+    }
+
+    // Parses [stack] or [reg(register)], stack locations are placed after the stack memory already
+    // allocated.
+    pub fn parse_memory_location(&mut self, var_type : &VariableType, stack_mem_allocated : &mut usize, packed : bool) -> Option<MemoryLocationsAst> {
+        expect_token_with_err!(TokenType::Punctuation(Punctuations::OpenSquareBracket), self);
+
+        let memory_location : MemoryLocationsAst = match self.current_token().kind {
+            TokenType::MemoryLocation(MemoryLocations::Stack) => {
+                self.advance_position();
+                MemoryLocationsAst::Stack(Self::allocate_stack_slot(var_type, stack_mem_allocated, packed))
+            },
+            TokenType::MemoryLocation(MemoryLocations::Register) => {
+                self.advance_position();
+
+                expect_token_with_err!(TokenType::Punctuation(Punctuations::OpenParenthesis), self);
+
+                let TokenType::Identifiers(Identifiers::Identifier(register)) = self.current_token().kind else {
+                    throw_err!(self, "Expected register name");
+                };
+
+                if !is_location_register(&register) {
+                    throw_err!(self, DiagnosticCode::InvalidType, &format!("Register {} can't be used as a location, use x0 to x8 or x19 to x28", register));
+                }
+
+                self.advance_position();
+
+                expect_token_with_err!(TokenType::Punctuation(Punctuations::ClosedParenthesis), self);
+
+                MemoryLocationsAst::Register(register)
+            },
+            _ => {
+                throw_err!(self, "Expected stack or reg as memory location");
+            }
+        };
+
+        expect_token_with_err!(TokenType::Punctuation(Punctuations::ClosedSquareBracket), self);
+
+        return Some(memory_location);
+    }
+
+    pub fn parse_variable_declaration(&mut self, first_token : &Token, var_type : VariableType, var_name : &String) -> Option<Statement> {
+        if var_type == VariableType::Void {
+            throw_err!(self, DiagnosticCode::InvalidType, "Can't declare variable as void");
+        }
+
+        let end_pos;
+
+        self.advance_position();
+
+        let value : Option<Expression> = match self.current_token().kind {
+            TokenType::Operator(operator) => {
+                if operator == Operators::Assignment {
+                    // Arrays of bytes can be filled with a string.
+                    if var_type.is_array() && !matches!(self.peek_token(1).kind, TokenType::Literal(Literal::String(_))) {
+                        throw_err!(self, DiagnosticCode::InvalidType, "Arrays on the stack can only be initialized with a string, assign their elements instead");
+                    }
+
+                    self.advance_position();
+
+                    let Some(value) = self.parse_expression(0) else {
+                        return None;
+                    };
+
+                    let semicolon_token = self.current_token();
+
+                    expect_token_with_err!(TokenType::Punctuation(Punctuations::Semicolon), self);
+
+                    end_pos = semicolon_token.end_pos;
+        
+                    Some(value)
+                } else {
+                    throw_err!(self, "Invalid identifier found");
+                }
+            },
+           TokenType::Punctuation(Punctuations::Semicolon) => {
+                end_pos = self.current_token().end_pos;
+
+                self.advance_position();
 
                 None
             },
@@ -291,6 +863,27 @@ impl<'a> Parser<'a> {
 
                 self.advance_position();
 
+                // Elements of arrays are given with a constant index.
+                let index = if TokenType::Punctuation(Punctuations::OpenSquareBracket) == self.current_token().kind {
+                    self.advance_position();
+
+                    let TokenType::Literal(Literal::Number(index)) = self.current_token().kind else {
+                        throw_err!(self, DiagnosticCode::InvalidBuiltInArgs, "stack_offset only takes a number as index");
+                    };
+
+                    if index < 0 {
+                        throw_err!(self, DiagnosticCode::InvalidBuiltInArgs, "Array indices can't be negative");
+                    }
+
+                    self.advance_position();
+
+                    expect_token_with_err!(TokenType::Punctuation(Punctuations::ClosedSquareBracket), self);
+
+                    Some(index as usize)
+                } else {
+                    None
+                };
+
                 if TokenType::Punctuation(Punctuations::ClosedParenthesis) != self.current_token().kind {
                     throw_err!(self, "Expected closed parenthesis");
                 };
@@ -299,7 +892,61 @@ impl<'a> Parser<'a> {
 
                 self.advance_position();
 
-                return Some(Statement::new(&token, end_pos, Statements::Expression(Expression::BuiltInFunction(BuiltInFunctionsAst::StackOffset(var_name)))));
+                return Some(Statement::new(&token, end_pos, Statements::Expression(Expression::BuiltInFunction(BuiltInFunctionsAst::StackOffset(var_name, index)))));
+            },
+            TokenType::BuiltInFunctions(BuiltInFunctions::SizeOf) => {
+                self.advance_position();
+
+                expect_token_with_err!(TokenType::Punctuation(Punctuations::OpenParenthesis), self);
+
+                let name = match self.current_token().kind {
+                    TokenType::Identifiers(Identifiers::Identifier(name)) => name,
+                    TokenType::Literal(Literal::String(string)) => {
+                        self.advance_position();
+
+                        let end_pos = self.current_token().end_pos;
+
+                        expect_token_with_err!(TokenType::Punctuation(Punctuations::ClosedParenthesis), self);
+
+                        return Some(Statement::new(&token, end_pos, Statements::Expression(Expression::BuiltInFunction(BuiltInFunctionsAst::SizeOfString(string)))));
+                    },
+                    _ => {
+                        throw_err!(self, "Expected a variable inside size_of");
+                    }
+                };
+
+                self.advance_position();
+
+                let end_pos = self.current_token().end_pos;
+
+                expect_token_with_err!(TokenType::Punctuation(Punctuations::ClosedParenthesis), self);
+
+                return Some(Statement::new(&token, end_pos, Statements::Expression(Expression::BuiltInFunction(BuiltInFunctionsAst::SizeOf(name)))));
+            },
+            TokenType::BuiltInFunctions(BuiltInFunctions::OffsetOf) => {
+                self.advance_position();
+
+                expect_token_with_err!(TokenType::Punctuation(Punctuations::OpenParenthesis), self);
+
+                let TokenType::Identifiers(Identifiers::Identifier(struct_name)) = self.current_token().kind else {
+                    throw_err!(self, "Expected a struct inside offset_of");
+                };
+
+                self.advance_position();
+
+                expect_token_with_err!(TokenType::Punctuation(Punctuations::Comma), self);
+
+                let TokenType::Identifiers(Identifiers::Identifier(field_name)) = self.current_token().kind else {
+                    throw_err!(self, "Expected a field inside offset_of");
+                };
+
+                self.advance_position();
+
+                let end_pos = self.current_token().end_pos;
+
+                expect_token_with_err!(TokenType::Punctuation(Punctuations::ClosedParenthesis), self);
+
+                return Some(Statement::new(&token, end_pos, Statements::Expression(Expression::BuiltInFunction(BuiltInFunctionsAst::OffsetOf(struct_name, field_name)))));
             },
             TokenType::BuiltInFunctions(BuiltInFunctions::Assembly) => {
                 self.advance_position();
@@ -338,85 +985,281 @@ impl<'a> Parser<'a> {
 
                 return Some(Statement::new(&token, token.end_pos, Statements::StackFramePop))
             },
+            TokenType::Punctuation(Punctuations::OpenBraces) => {
+                self.advance_position();
+
+                return Some(Statement::new(&token, token.end_pos, Statements::OpenScope(ScopeKind::Block)))
+            },
             TokenType::Keyword(keyword) => {
                 match keyword {
-                    Keywords::VariableType(var_type) => {
+                    Keywords::VariableType(_) => {
+                        let Some(var_type) = self.parse_variable_type() else {
+                            return None;
+                        };
+
+                        return self.parse_typed_statement(&token, var_type);
+                    },
+                    Keywords::Packed => {
                         self.advance_position();
 
-                        match self.current_token().kind {
-                            TokenType::Punctuation(punctuation) => {
-                                match punctuation {
-                                    Punctuations::Colon => {
-                                        return self.parse_function_declaration(&token, var_type);
-                                        // Handle func decl
-                                    },
-                                    _ => {
-                                        throw_err!(self, "Unknown Punctuation");
-                                    }
-                                }
-                            },
-                            TokenType::Identifiers(identifier) => {
-                                match identifier {
-                                    Identifiers::Identifier(var_name) => {
-                                        return self.parse_variable_declaration(&token, var_type, &var_name);
-                                    },
-                                    _ => {
-                                        throw_err!(self, "Unknown Identifier");
-                                    }
-                                }
-                            }
-                            _ => {
-                                throw_err!(self, "Syntax Error");
-                            }
+                        if TokenType::Keyword(Keywords::Struct) == self.current_token().kind {
+                            return self.skip_struct_declaration(&token);
+                        }
+
+                        let kind = self.current_token().kind;
+
+                        if !self.starts_type(&kind) {
+                            throw_err!(self, "Expected a function or struct declaration after packed");
                         }
+
+                        let Some(var_type) = self.parse_variable_type() else {
+                            return None;
+                        };
+
+                        if TokenType::Punctuation(Punctuations::Colon) != self.current_token().kind {
+                            throw_err!(self, "Only functions and structs can be packed");
+                        }
+
+                        return self.parse_function_declaration(&token, var_type, true, false, false);
+                    },
+                    Keywords::Break | Keywords::Continue => {
+                        self.advance_position();
+
+                        let end_pos = self.current_token().end_pos;
+
+                        expect_token_with_err!(TokenType::Punctuation(Punctuations::Semicolon), self);
+
+                        let statement_type = if keyword == Keywords::Break { Statements::Break } else { Statements::Continue };
+
+                        return Some(Statement::new(&token, end_pos, statement_type));
+                    },
+                    Keywords::Return => {
+                        self.advance_position();
+
+                        let value = if TokenType::Punctuation(Punctuations::Semicolon) == self.current_token().kind {
+                            None
+                        } else {
+                            let Some(value) = self.parse_expression(0) else {
+                                return None;
+                            };
+
+                            Some(value)
+                        };
+
+                        let end_pos = self.current_token().end_pos;
+
+                        expect_token_with_err!(TokenType::Punctuation(Punctuations::Semicolon), self);
+
+                        return Some(Statement::new(&token, end_pos, Statements::Return(value)));
+                    },
+                    Keywords::Import => {
+                        self.advance_position();
+
+                        let TokenType::Literal(Literal::String(path)) = self.current_token().kind else {
+                            throw_err!(self, "Expected path of the imported file");
+                        };
+
+                        self.advance_position();
+
+                        let end_pos = self.current_token().end_pos;
+
+                        expect_token_with_err!(TokenType::Punctuation(Punctuations::Semicolon), self);
+
+                        return Some(Statement::new(&token, end_pos, Statements::Import(path)));
+                    },
+                    Keywords::As => {
+                        throw_err!(self, DiagnosticCode::UnexpectedToken, "as can only follow a value");
+                    },
+                    Keywords::Struct => {
+                        return self.skip_struct_declaration(&token);
+                    },
+                    Keywords::Static | Keywords::Const => {
+                        return self.parse_global_declaration(&token, keyword == Keywords::Const);
+                    },
+                    Keywords::Extern => {
+                        self.advance_position();
+
+                        let kind = self.current_token().kind;
+
+                        if !self.starts_type(&kind) {
+                            throw_err!(self, "Expected a function declaration after extern");
+                        }
+
+                        let Some(var_type) = self.parse_variable_type() else {
+                            return None;
+                        };
+
+                        if TokenType::Punctuation(Punctuations::Colon) != self.current_token().kind {
+                            throw_err!(self, "Only functions can be extern");
+                        }
+
+                        return self.parse_function_declaration(&token, var_type, false, true, false);
+                    },
+                    Keywords::Export => {
+                        self.advance_position();
+
+                        let kind = self.current_token().kind;
+
+                        if !self.starts_type(&kind) {
+                            throw_err!(self, "Expected a function declaration after export");
+                        }
+
+                        let Some(var_type) = self.parse_variable_type() else {
+                            return None;
+                        };
+
+                        if TokenType::Punctuation(Punctuations::Colon) != self.current_token().kind {
+                            throw_err!(self, "Only functions can be exported");
+                        }
+
+                        return self.parse_function_declaration(&token, var_type, false, false, true);
                     }
                 }
             },
-            TokenType::BuiltInFunctions(BuiltInFunctions::BranchLinked) => {
+            // Declarations with a pointer type, expressions never start with *.
+            TokenType::Operator(Operators::Star) => {
+                let Some(var_type) = self.parse_variable_type() else {
+                    return None;
+                };
+
+                return self.parse_typed_statement(&token, var_type);
+            },
+            TokenType::BuiltInFunctions(BuiltInFunctions::Store) => {
                 self.advance_position();
 
                 expect_token_with_err!(TokenType::Punctuation(Punctuations::OpenParenthesis), self);
 
-                let TokenType::Identifiers(Identifiers::Identifier(identifier)) = self.current_token().kind else {
-                    throw_err!(self, "Expected function");
+                let Some(pointer) = self.parse_expression(0) else {
+                    return None;
                 };
 
+                expect_token_with_err!(TokenType::Punctuation(Punctuations::Comma), self);
+
+                let Some(value) = self.parse_expression(0) else {
+                    return None;
+                };
+
+                expect_token_with_err!(TokenType::Punctuation(Punctuations::ClosedParenthesis), self);
+
+                let end_pos = self.current_token().end_pos;
+
+                expect_token_with_err!(TokenType::Punctuation(Punctuations::Semicolon), self);
+
+                return Some(Statement::new(&token, end_pos, Statements::Expression(Expression::BuiltInFunction(BuiltInFunctionsAst::Store(StoreAst{pointer: Box::new(pointer), value: Box::new(value)})))));
+            },
+            TokenType::BuiltInFunctions(BuiltInFunctions::Loop) => {
                 self.advance_position();
 
-                let mut args : Vec<Expression> = Vec::new();
+                let end_pos = self.current_token().end_pos;
 
-                loop {
-                    match self.current_token().kind {
-                        TokenType::Punctuation(Punctuations::ClosedParenthesis) => {
-                            self.advance_position();
-                            break;
-                        },
-                        TokenType::Punctuation(Punctuations::Comma) => {
-                            self.advance_position();
+                expect_token_with_err!(TokenType::Punctuation(Punctuations::OpenBraces), self);
 
-                            match self.current_token().kind {
-                                TokenType::Literal(literal) => args.push(Expression::Literal(literal)),
-                                TokenType::Identifiers(identifier) => args.push(Expression::Identifier(identifier)),
-                                _ => {
-                                    throw_err!(self, "");
-                                }
-                            }
+                return Some(Statement::new(&token, end_pos, Statements::OpenScope(ScopeKind::Loop)));
+            },
+            TokenType::BuiltInFunctions(BuiltInFunctions::Compare) => {
+                self.advance_position();
 
-                            self.advance_position();
+                expect_token_with_err!(TokenType::Punctuation(Punctuations::OpenParenthesis), self);
 
-                            continue;
-                        },
-                        _ => {
-                            throw_err!(self, "Syntax Error");
-                        }
-                    }
+                let Some(left) = self.parse_expression(0) else {
+                    return None;
+                };
+
+                expect_token_with_err!(TokenType::Punctuation(Punctuations::Comma), self);
+
+                let Some(right) = self.parse_expression(0) else {
+                    return None;
+                };
+
+                expect_token_with_err!(TokenType::Punctuation(Punctuations::ClosedParenthesis), self);
+
+                let end_pos = self.current_token().end_pos;
+
+                expect_token_with_err!(TokenType::Punctuation(Punctuations::OpenBraces), self);
+
+                return Some(Statement::new(&token, end_pos, Statements::OpenScope(ScopeKind::Compare(CompareAst{left, right}))));
+            },
+            // Declarations with a struct type.
+            TokenType::Identifiers(Identifiers::Identifier(identifier)) if self.is_struct_name(&identifier) => {
+                let Some(var_type) = self.parse_variable_type() else {
+                    return None;
+                };
+
+                return self.parse_typed_statement(&token, var_type);
+            },
+            TokenType::Identifiers(Identifiers::Identifier(_)) if self.peek_token(1).kind == TokenType::Punctuation(Punctuations::Dot) => {
+                let Some(field) = self.parse_field() else {
+                    return None;
+                };
+
+                if TokenType::Punctuation(Punctuations::OpenSquareBracket) == self.current_token().kind {
+                    return self.parse_element_assignment(&token, field);
                 }
 
+                expect_token_with_err!(TokenType::Operator(Operators::Assignment), self);
+
+                let Some(value) = self.parse_expression(0) else {
+                    return None;
+                };
+
                 let end_pos = self.current_token().end_pos;
 
                 expect_token_with_err!(TokenType::Punctuation(Punctuations::Semicolon), self);
 
-                return Some(Statement::new(&token, end_pos, Statements::Expression(Expression::BuiltInFunction(BuiltInFunctionsAst::BranchLinked(BranchLinkedAst{args, function_name: identifier})))));
+                return Some(Statement::new(&token, end_pos, Statements::FieldAssignment(FieldAssignment{field, value})));
+            },
+            TokenType::Identifiers(Identifiers::Identifier(identifier)) if self.peek_token(1).kind == TokenType::Operator(Operators::Assignment) => {
+                self.advance_position();
+                self.advance_position();
+
+                let Some(value) = self.parse_expression(0) else {
+                    return None;
+                };
+
+                let end_pos = self.current_token().end_pos;
+
+                expect_token_with_err!(TokenType::Punctuation(Punctuations::Semicolon), self);
+
+                return Some(Statement::new(&token, end_pos, Statements::Assignment(VariableAssignment{name: identifier, value})));
+            },
+            TokenType::Identifiers(Identifiers::Identifier(identifier)) if self.peek_token(1).kind == TokenType::Punctuation(Punctuations::OpenSquareBracket) => {
+                self.advance_position();
+
+                return self.parse_element_assignment(&token, FieldAst{name: identifier, fields: Vec::new()});
+            },
+            // Arms of compare, like eq { ... }
+            TokenType::Identifiers(Identifiers::Identifier(identifier)) if self.peek_token(1).kind == TokenType::Punctuation(Punctuations::OpenBraces) => {
+                let condition = match &identifier as &str {
+                    "eq" => CompareArmCondition::Condition(BinaryOperators::Equal),
+                    "ne" => CompareArmCondition::Condition(BinaryOperators::NotEqual),
+                    "lt" => CompareArmCondition::Condition(BinaryOperators::Less),
+                    "le" => CompareArmCondition::Condition(BinaryOperators::LessEqual),
+                    "gt" => CompareArmCondition::Condition(BinaryOperators::Greater),
+                    "ge" => CompareArmCondition::Condition(BinaryOperators::GreaterEqual),
+                    "else" => CompareArmCondition::Else,
+                    _ => {
+                        throw_err!(self, "Unknown compare arm, expected eq, ne, lt, le, gt, ge or else");
+                    }
+                };
+
+                self.advance_position();
+
+                let end_pos = self.current_token().end_pos;
+
+                self.advance_position();
+
+                return Some(Statement::new(&token, end_pos, Statements::OpenScope(ScopeKind::CompareArm(condition))));
+            },
+            TokenType::BuiltInFunctions(BuiltInFunctions::BranchLinked) => {
+                let Some(branch_linked) = self.parse_branch_linked() else {
+                    return None;
+                };
+
+                let end_pos = self.current_token().end_pos;
+
+                expect_token_with_err!(TokenType::Punctuation(Punctuations::Semicolon), self);
+
+                return Some(Statement::new(&token, end_pos, Statements::Expression(Expression::BuiltInFunction(BuiltInFunctionsAst::BranchLinked(branch_linked)))));
             }
             _ => {
                 throw_err!(self, "Syntax Error");
@@ -424,8 +1267,256 @@ impl<'a> Parser<'a> {
         };
     }
 
-    pub fn handle_error(&mut self, error : &str) -> () {
-        self.program_data.errors.push(String::from(error));
+    // Parses bl(function, args...) without the semicolon, so calls can be used as values.
+    pub fn parse_branch_linked(&mut self) -> Option<BranchLinkedAst> {
+        self.advance_position();
+
+        expect_token_with_err!(TokenType::Punctuation(Punctuations::OpenParenthesis), self);
+
+        let TokenType::Identifiers(Identifiers::Identifier(identifier)) = self.current_token().kind else {
+            throw_err!(self, "Expected function");
+        };
+
+        self.advance_position();
+
+        let mut args : Vec<Expression> = Vec::new();
+
+        loop {
+            match self.current_token().kind {
+                TokenType::Punctuation(Punctuations::ClosedParenthesis) => {
+                    self.advance_position();
+                    break;
+                },
+                TokenType::Punctuation(Punctuations::Comma) => {
+                    self.advance_position();
+
+                    let Some(arg) = self.parse_expression(0) else {
+                        return None;
+                    };
+
+                    args.push(arg);
+
+                    continue;
+                },
+                _ => {
+                    throw_err!(self, "Syntax Error");
+                }
+            }
+        }
+
+        return Some(BranchLinkedAst{args, function_name: identifier});
+    }
+
+    // Precedence climbing, operators that bind tighter have a higher precedence.
+    pub fn parse_expression(&mut self, min_precedence : u8) -> Option<Expression> {
+        let Some(mut left) = self.parse_cast_expression() else {
+            return None;
+        };
+
+        loop {
+            let TokenType::Operator(operator) = self.current_token().kind else {
+                break;
+            };
+
+            let Some((binary_operator, precedence)) = Self::binary_operator_precedence(&operator) else {
+                break;
+            };
+
+            if precedence < min_precedence {
+                break;
+            }
+
+            self.advance_position();
+
+            // All binary operators are left associative.
+            let Some(right) = self.parse_expression(precedence + 1) else {
+                return None;
+            };
+
+            left = Expression::BinaryOperation(BinaryOperation{operator: binary_operator, left: Box::new(left), right: Box::new(right)});
+        }
+
+        return Some(left);
+    }
+
+    // value as type, binds tighter than every binary operator and looser than - and ~.
+    pub fn parse_cast_expression(&mut self) -> Option<Expression> {
+        let Some(mut value) = self.parse_primary_expression() else {
+            return None;
+        };
+
+        while TokenType::Keyword(Keywords::As) == self.current_token().kind {
+            self.advance_position();
+
+            let kind = self.current_token().kind;
+
+            if !self.starts_type(&kind) {
+                throw_err!(self, DiagnosticCode::InvalidType, "Expected a type after as");
+            }
+
+            let Some(variable_type) = self.parse_variable_type() else {
+                return None;
+            };
+
+            value = Expression::Cast(Cast{value: Box::new(value), variable_type});
+        }
+
+        return Some(value);
+    }
+
+    pub fn parse_primary_expression(&mut self) -> Option<Expression> {
+        let token = self.current_token();
+
+        match token.kind {
+            TokenType::Literal(literal) => {
+                self.advance_position();
+
+                return Some(Expression::Literal(literal));
+            },
+            TokenType::Identifiers(Identifiers::Identifier(identifier)) if self.peek_token(1).kind == TokenType::Punctuation(Punctuations::OpenSquareBracket) => {
+                self.advance_position();
+
+                let Some(index) = self.parse_index() else {
+                    return None;
+                };
+
+                return Some(Expression::Index(IndexAst{array: FieldAst{name: identifier, fields: Vec::new()}, index: Box::new(index)}));
+            },
+            TokenType::Identifiers(_) if self.peek_token(1).kind == TokenType::Punctuation(Punctuations::Dot) => {
+                let Some(field) = self.parse_field() else {
+                    return None;
+                };
+
+                // Arrays inside a struct, like packet.tag[1].
+                if TokenType::Punctuation(Punctuations::OpenSquareBracket) == self.current_token().kind {
+                    let Some(index) = self.parse_index() else {
+                        return None;
+                    };
+
+                    return Some(Expression::Index(IndexAst{array: field, index: Box::new(index)}));
+                }
+
+                return Some(Expression::Field(field));
+            },
+            TokenType::Identifiers(identifier) => {
+                self.advance_position();
+
+                return Some(Expression::Identifier(identifier));
+            },
+            TokenType::Punctuation(Punctuations::OpenParenthesis) => {
+                self.advance_position();
+
+                let Some(expression) = self.parse_expression(0) else {
+                    return None;
+                };
+
+                expect_token_with_err!(TokenType::Punctuation(Punctuations::ClosedParenthesis), self);
+
+                return Some(expression);
+            },
+            TokenType::Operator(Operators::Minus) => {
+                self.advance_position();
+
+                // Negative number literals are written as a minus followed by the number.
+                if let TokenType::Literal(Literal::Number(num)) = self.current_token().kind {
+                    self.advance_position();
+
+                    return Some(Expression::Literal(Literal::Number(-num)));
+                }
+
+                let Some(operand) = self.parse_primary_expression() else {
+                    return None;
+                };
+
+                return Some(Expression::UnaryOperation(UnaryOperation{operator: UnaryOperators::Negate, operand: Box::new(operand)}));
+            },
+            TokenType::Operator(Operators::Tilde) => {
+                self.advance_position();
+
+                let Some(operand) = self.parse_primary_expression() else {
+                    return None;
+                };
+
+                return Some(Expression::UnaryOperation(UnaryOperation{operator: UnaryOperators::BitNot, operand: Box::new(operand)}));
+            },
+            TokenType::BuiltInFunctions(BuiltInFunctions::BranchLinked) => {
+                let Some(branch_linked) = self.parse_branch_linked() else {
+                    return None;
+                };
+
+                return Some(Expression::BuiltInFunction(BuiltInFunctionsAst::BranchLinked(branch_linked)));
+            },
+            TokenType::BuiltInFunctions(BuiltInFunctions::Address) => {
+                self.advance_position();
+
+                expect_token_with_err!(TokenType::Punctuation(Punctuations::OpenParenthesis), self);
+
+                let TokenType::Identifiers(Identifiers::Identifier(var_name)) = self.current_token().kind else {
+                    throw_err!(self, "Expected a variable inside addr");
+                };
+
+                self.advance_position();
+
+                expect_token_with_err!(TokenType::Punctuation(Punctuations::ClosedParenthesis), self);
+
+                return Some(Expression::BuiltInFunction(BuiltInFunctionsAst::Address(var_name)));
+            },
+            TokenType::BuiltInFunctions(BuiltInFunctions::Load) => {
+                self.advance_position();
+
+                expect_token_with_err!(TokenType::Punctuation(Punctuations::OpenParenthesis), self);
+
+                let Some(pointer) = self.parse_expression(0) else {
+                    return None;
+                };
+
+                expect_token_with_err!(TokenType::Punctuation(Punctuations::ClosedParenthesis), self);
+
+                return Some(Expression::BuiltInFunction(BuiltInFunctionsAst::Load(Box::new(pointer))));
+            },
+            TokenType::BuiltInFunctions(BuiltInFunctions::StackOffset) | TokenType::BuiltInFunctions(BuiltInFunctions::SizeOf) | TokenType::BuiltInFunctions(BuiltInFunctions::OffsetOf) => {
+                let Some(statement) = self.parse_next() else {
+                    return None;
+                };
+
+                let Statements::Expression(expression) = statement.statement_type else {
+                    throw_err!(self, "Expected expression");
+                };
+
+                return Some(expression);
+            },
+            _ => {
+                throw_err!(self, "Expected expression");
+            }
+        }
+    }
+
+    pub fn binary_operator_precedence(operator : &Operators) -> Option<(BinaryOperators, u8)> {
+        return match operator {
+            Operators::Star => Some((BinaryOperators::Multiply, 10)),
+            Operators::Slash => Some((BinaryOperators::Divide, 10)),
+            Operators::Percent => Some((BinaryOperators::Modulo, 10)),
+            Operators::Plus => Some((BinaryOperators::Add, 9)),
+            Operators::Minus => Some((BinaryOperators::Subtract, 9)),
+            Operators::ShiftLeft => Some((BinaryOperators::ShiftLeft, 8)),
+            Operators::ShiftRight => Some((BinaryOperators::ShiftRight, 8)),
+            Operators::Less => Some((BinaryOperators::Less, 7)),
+            Operators::LessEqual => Some((BinaryOperators::LessEqual, 7)),
+            Operators::Greater => Some((BinaryOperators::Greater, 7)),
+            Operators::GreaterEqual => Some((BinaryOperators::GreaterEqual, 7)),
+            Operators::Equal => Some((BinaryOperators::Equal, 6)),
+            Operators::NotEqual => Some((BinaryOperators::NotEqual, 6)),
+            Operators::Ampersand => Some((BinaryOperators::BitAnd, 5)),
+            Operators::Caret => Some((BinaryOperators::BitXor, 4)),
+            Operators::Pipe => Some((BinaryOperators::BitOr, 3)),
+            _ => None
+        };
+    }
+
+    pub fn handle_error(&mut self, code : DiagnosticCode, error : &str) -> () {
+        let span = Span::from_token(&self.current_token());
+
+        self.program_data.diagnostics.push(Diagnostic::error(code, error, span));
         self.skip_until_semicolon();
 
         return;
@@ -433,6 +1524,10 @@ impl<'a> Parser<'a> {
 
     pub fn skip_until_semicolon(&mut self) -> () {
         while self.current_token().kind != TokenType::Punctuation(Punctuations::Semicolon) {
+            if self.current_token().kind == TokenType::EOF {
+                return;
+            }
+
             self.advance_position();
         }
 
@@ -445,6 +1540,13 @@ impl<'a> Parser<'a> {
         self.position += 1;
     }
 
+    pub fn peek_token(&mut self, offset : usize) -> Token {
+        return match self.program_data.tokens.get(self.position + offset) {
+            Some(token) => token.clone(),
+            None => self.program_data.tokens.last().unwrap().clone()
+        };
+    }
+
     pub fn current_token(&mut self) -> Token {
         let tkn = self.program_data.tokens.get(self.position).unwrap().clone(); 
         
@@ -457,9 +1559,9 @@ impl<'a> Parser<'a> {
 
     pub fn expect_token(&mut self, token_type : TokenType) -> Result<(), ()> {
         if self.current_token().kind != token_type {
-            let err = format!("Current token type: {:?} Expected: {:?}", self.current_token().kind.clone(), token_type);
+            let err = format!("Expected {:?}, found {:?}", token_type, self.current_token().kind.clone());
 
-            self.handle_error(err.as_str());
+            self.handle_error(DiagnosticCode::UnexpectedToken, err.as_str());
 
             return Err(());
         }
@@ -1,6 +1,7 @@
 use std::collections::HashMap;
+use std::path::PathBuf;
 
-use crate::datatypes::{ast_statements::{Function, FunctionArg, MemoryLocationsAst, Statement}, stack_frame::{StackFrame, StackVariable}, token::Token};
+use crate::datatypes::{ast_statements::{Function, FunctionArg, GlobalVariable, MemoryLocationsAst, Statement, StructDefinition}, diagnostic::{Diagnostic, Severity}, source_map::SourceFile, stack_frame::{StackFrame, StackVariable}, target::Target, token::Token};
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct StackVariableRef {
@@ -14,26 +15,94 @@ pub struct FunctionStackArgRef {
     pub var: FunctionArg
 }
 
+pub fn qualified_function_name(module : &str, name : &str) -> String {
+    if module.is_empty() {
+        return String::from(name);
+    }
+
+    return format!("{}::{}", module, name);
+}
+
 #[derive(PartialEq, Clone, Debug)]
 pub struct ProgramData {
     pub stack_frames : Vec<StackFrame>,
     pub functions : HashMap<String, Function>,
+    // Qualified function names in declaration order, everything that walks the functions uses it
+    // so the output doesn't depend on the order of the map.
+    pub function_order : Vec<String>,
+    // Struct names are global, unlike functions they aren't namespaced by module.
+    pub structs : HashMap<String, StructDefinition>,
+    pub struct_order : Vec<String>,
+    // Globals by qualified name, namespaced like functions.
+    pub globals : HashMap<String, GlobalVariable>,
+    pub global_order : Vec<String>,
+    // Contents of the string literals used as values, placed in read only memory by index.
+    pub strings : Vec<String>,
     pub statements : Vec<Statement>,
-    pub source_code : String,
+    // Root file first, followed by the files it imports.
+    pub files : Vec<SourceFile>,
     pub tokens : Vec<Token>,
-    pub errors : Vec<String>
+    pub diagnostics : Vec<Diagnostic>,
+    pub target : Target,
+    // Phases print what they produce.
+    pub verbose : bool,
+    // Indices into arrays are checked at runtime, set by the debug profile.
+    pub bounds_checks : bool,
+    // Sources given in memory by path, imports look here before reading the filesystem.
+    pub memory_files : HashMap<PathBuf, String>
 }
 
 impl ProgramData {
     pub fn new() -> Self {
-        Self { stack_frames: Vec::new(), functions: HashMap::new(), source_code: String::new(), tokens: Vec::new(), statements: Vec::new(), errors: Vec::new() }
+        Self { stack_frames: Vec::new(), functions: HashMap::new(), function_order: Vec::new(), structs: HashMap::new(), struct_order: Vec::new(), globals: HashMap::new(), global_order: Vec::new(), strings: Vec::new(), files: Vec::new(), tokens: Vec::new(), statements: Vec::new(), diagnostics: Vec::new(), target: Target::host(), verbose: false, bounds_checks: false, memory_files: HashMap::new() }
+    }
+
+    // Index of the string in read only memory, equal strings share their bytes.
+    pub fn string_index(&mut self, string : &str) -> usize {
+        if let Some(index) = self.strings.iter().position(|existing| existing == string) {
+            return index;
+        }
+
+        self.strings.push(String::from(string));
+
+        return self.strings.len() - 1;
+    }
+
+    // Extern functions come from C libraries, which need the C library to be linked.
+    pub fn links_libc(&self) -> bool {
+        return self.functions.values().any(|function| function.external);
+    }
+
+    pub fn has_errors(&self) -> bool {
+        return self.diagnostics.iter().any(|diagnostic| diagnostic.severity == Severity::Error);
+    }
+
+    // Functions are namespaced with the module of the file declaring them, names without a
+    // namespace refer to the module of the calling function.
+    pub fn resolve_function_name(&self, stack_frame : usize, name : &str) -> String {
+        if name.contains("::") {
+            return String::from(name);
+        }
+
+        let caller = self.get_stack_frame_by_index(stack_frame).function.clone();
+        let caller_file = self.functions.get(&caller).unwrap().span.file;
+
+        return qualified_function_name(&self.files.get(caller_file).unwrap().module, name);
+    }
+
+    // Global called name as seen from the function of stack_frame, with its qualified name. Names
+    // without a namespace refer to the module of the function.
+    pub fn get_global_ref(&self, stack_frame : usize, name : &str) -> Option<(String, GlobalVariable)> {
+        let qualified_name = self.resolve_function_name(stack_frame, name);
+
+        return self.globals.get(&qualified_name).map(|global| (qualified_name, global.clone()));
     }
 
     pub fn get_stack_frame_by_index(&self, index : usize) -> &'_ StackFrame {
         return self.stack_frames.get(index).unwrap();
     }
 
-    pub fn get_stack_variable_ref(&mut self, stack_frame : usize, var_name : &str, offset : usize) -> Option<StackVariableRef> {
+    pub fn get_stack_variable_ref(&self, stack_frame : usize, var_name : &str, offset : usize) -> Option<StackVariableRef> {
         let stack_frame_ref = self.get_stack_frame_by_index(stack_frame);
 
         match stack_frame_ref.variables.get(var_name) {
@@ -44,8 +113,8 @@ impl ProgramData {
                 if stack_frame_ref.parent == usize::MAX {
                     return None;
                 } else {
-                    // Also count for x30 stored inside stack
-                    return self.get_stack_variable_ref(stack_frame_ref.parent, var_name, offset + 16 + stack_frame_ref.stack_mem_allocated);
+                    // Also count for registers stored inside stack
+                    return self.get_stack_variable_ref(stack_frame_ref.parent, var_name, offset + stack_frame_ref.saved_registers_size() + stack_frame_ref.stack_mem_allocated);
                 }
             }
         }
@@ -57,12 +126,50 @@ impl ProgramData {
         let func_mem_allocated = self.functions.get(&function_name).unwrap().stack_mem_allocated.clone();
         let func_arg = self.functions.get(&function_name).unwrap().args.iter().find(|arg| arg.arg_name == identifier);
 
+        let bytes = self.get_function_args_offset(stack_frame);
+
+        if let Some(func_arg_unwrapped) = func_arg {
+            if let MemoryLocationsAst::Stack(stack_offset) = func_arg_unwrapped.memory_location {
+                return Some(FunctionStackArgRef{local_offset: bytes + func_mem_allocated - stack_offset - func_arg_unwrapped.arg_var_type.get_variable_size(), var: func_arg_unwrapped.clone()});
+            }
+
+            return None;
+        } else {
+            return None;
+        }
+    }
+
+    pub fn get_function_register_arg(&self, stack_frame : usize, identifier : &str) -> Option<(String, FunctionArg)> {
+        let function_name = self.get_stack_frame_by_index(stack_frame).function.clone();
+
+        let func_arg = self.functions.get(&function_name).unwrap().args.iter().find(|arg| arg.arg_name == identifier)?;
+
+        if let MemoryLocationsAst::Register(register) = func_arg.memory_location.clone() {
+            return Some((register, func_arg.clone()));
+        }
+
+        return None;
+    }
+
+    // Offset of the stack return slot from sp inside stack_frame.
+    pub fn get_function_stack_return_offset(&self, stack_frame : usize) -> Option<usize> {
+        let function = self.functions.get(&self.get_stack_frame_by_index(stack_frame).function).unwrap();
+
+        if let Some(MemoryLocationsAst::Stack(stack_offset)) = function.return_location {
+            return Some(self.get_function_args_offset(stack_frame) + function.stack_mem_allocated - stack_offset - function.return_type.get_variable_size());
+        }
+
+        return None;
+    }
+
+    // Bytes between sp inside stack_frame and the stack args allocated by the caller.
+    pub fn get_function_args_offset(&self, stack_frame : usize) -> usize {
         let mut current_stack_frame = stack_frame;
         let mut bytes = 0;
 
         loop {
             let current_stack_frame_borrow = self.get_stack_frame_by_index(current_stack_frame);
-            bytes += current_stack_frame_borrow.stack_mem_allocated + 16;
+            bytes += current_stack_frame_borrow.stack_mem_allocated + current_stack_frame_borrow.saved_registers_size();
 
             if current_stack_frame_borrow.parent == usize::MAX {
                 break;
@@ -71,14 +178,6 @@ impl ProgramData {
             current_stack_frame = current_stack_frame_borrow.parent;
         }
 
-        if let Some(func_arg_unwrapped) = func_arg {
-            if let MemoryLocationsAst::Stack(stack_offset) = func_arg_unwrapped.memory_location {
-                return Some(FunctionStackArgRef{local_offset: bytes + func_mem_allocated - stack_offset - func_arg_unwrapped.arg_var_type.get_variable_size(), var: func_arg_unwrapped.clone()});
-            }
-
-            return None;
-        } else {
-            return None;
-        }
+        return bytes;
     }
 }
@@ -1,10 +1,11 @@
 use std::{collections::HashMap, panic};
 
-use crate::datatypes::{ast_statements::{BuiltInFunctionsAst, CgBranchLinked, CgBuiltInFunctions, CgStatement, CgStatementType, Expression, Function, Literal, Statement, Statements, VariableDeclaration, VariableType}, program_data::ProgramData, stack_frame::{StackFrame, StackVariable}};
+use crate::datatypes::{ast_statements::{BuiltInFunctionsAst, CompareArmCondition, ScopeKind, CgBranchLinked, CgBuiltInFunctions, CgStatement, CgStatementType, Expression, Function, GlobalDeclaration, GlobalSection, GlobalVariable, Literal, Statement, Statements, VariableDeclaration, VariableType}, diagnostic::{Diagnostic, DiagnosticCode, Span}, general_functions::{align_memory, allocate_memory}, program_data::{qualified_function_name, ProgramData}, stack_frame::{StackFrame, StackVariable}, type_checker};
 
 macro_rules! throw_err {
-    ($self:expr, $error:expr) => {
-        $self.throw_err($error);
+    ($self:expr, $diagnostic:expr) => {
+        $self.throw_err($diagnostic);
+        $self.advance_position();
 
         continue;
     };
@@ -24,53 +25,183 @@ impl<'a> ScopeAnalysis<'a> {
     pub fn process_all(&mut self) -> () {
         let mut current_function = String::new();
 
+        // Globals are known before any function is analysed, so they can be used above their
+        // declaration.
+        self.collect_globals();
+
         loop {
             let current_statement = self.current_statement().clone();
 
-            print!(" {:?} ", current_statement);
+            if self.program_data.verbose {
+                print!(" {:?} ", current_statement);
+            }
             
             if current_function.is_empty() {
                 if let Statements::FunctionDeclaration(func_declaration) = current_statement.statement_type.clone() {
-                    if self.program_data.functions.get(&func_declaration.name).is_some() {
-                        throw_err!(self, &format!("Duplicate function: {}", func_declaration.name));
+                    let module = self.program_data.files.get(current_statement.file).unwrap().module.clone();
+                    let function_name = qualified_function_name(&module, &func_declaration.name);
+
+                    if let Some(existing_function) = self.program_data.functions.get(&function_name) {
+                        let diagnostic = Diagnostic::error(DiagnosticCode::DuplicateFunction, &format!("Duplicate function: {}", function_name), Span::from_statement(&current_statement))
+                            .with_label("redefined here")
+                            .with_secondary(existing_function.span.clone(), "first defined here");
+
+                        // Keep analysing the body so errors inside it are reported too.
+                        self.throw_err(diagnostic);
+                    }
+
+                    // Exported and extern functions are linked without their module, two of them
+                    // can only share a name when both are extern.
+                    if func_declaration.exported || func_declaration.external {
+                        let same_symbol = self.program_data.functions.iter().find(|(name, function)| {
+                            return **name != function_name && (function.exported || function.external) && (function.exported || func_declaration.exported) && name.rsplit("::").next().unwrap() == func_declaration.name;
+                        });
+
+                        if let Some((other_name, other_function)) = same_symbol {
+                            let diagnostic = Diagnostic::error(DiagnosticCode::DuplicateFunction, &format!("Function {} has the same symbol {} as {}", function_name, func_declaration.name, other_name), Span::from_statement(&current_statement))
+                                .with_secondary(other_function.span.clone(), "first declared here");
+
+                            self.throw_err(diagnostic);
+                        }
+                    }
+
+                    // Functions and globals share the symbols of the object file.
+                    if let Some(global) = self.program_data.globals.get(&function_name) {
+                        let diagnostic = Diagnostic::error(DiagnosticCode::DuplicateFunction, &format!("Function {} has the same name as a global", function_name), Span::from_statement(&current_statement))
+                            .with_secondary(global.span.clone(), "global declared here");
+
+                        self.throw_err(diagnostic);
+                    }
+
+                    for arg in func_declaration.args.iter() {
+                        if let Some(global) = self.program_data.globals.get(&qualified_function_name(&module, &arg.arg_name)) {
+                            let diagnostic = Diagnostic::error(DiagnosticCode::DuplicateVariable, &format!("Arg {} of function {} collides with the global {}", arg.arg_name, function_name, arg.arg_name), Span::from_statement(&current_statement))
+                                .with_secondary(global.span.clone(), "global declared here");
+
+                            self.throw_err(diagnostic);
+                        }
+                    }
+
+                    // Extern functions only have a declaration, there is no body to analyse.
+                    if func_declaration.external {
+                        self.program_data.functions.entry(function_name.clone()).or_insert(Function{first_stack_frame: usize::MAX, args: func_declaration.args, return_type: func_declaration.return_type, return_location: func_declaration.return_location, stack_mem_allocated: func_declaration.args_stack_mem_allocated, packed: false, external: true, exported: false, span: Span::from_statement(&current_statement)});
+
+                        self.advance_position();
+
+                        continue;
                     }
 
                     let stack_frame_index = self.program_data.stack_frames.len();
 
-                    self.program_data.stack_frames.push(StackFrame::default(func_declaration.name.clone()));
+                    let mut function_stack_frame = StackFrame::default(function_name.clone());
+                    function_stack_frame.exported = func_declaration.exported;
+
+                    self.program_data.stack_frames.push(function_stack_frame);
+
+                    if !self.program_data.functions.contains_key(&function_name) {
+                        self.program_data.function_order.push(function_name.clone());
+                    }
 
-                    self.program_data.functions.insert(func_declaration.name.clone(), Function{first_stack_frame: stack_frame_index, args: func_declaration.args, return_type: func_declaration.return_type, stack_mem_allocated: func_declaration.args_stack_mem_allocated});
+                    self.program_data.functions.entry(function_name.clone()).or_insert(Function{first_stack_frame: stack_frame_index, args: func_declaration.args, return_type: func_declaration.return_type, return_location: func_declaration.return_location, stack_mem_allocated: func_declaration.args_stack_mem_allocated, packed: func_declaration.packed, external: false, exported: func_declaration.exported, span: Span::from_statement(&current_statement)});
 
                     self.scope_stack.push(stack_frame_index);
 
-                    current_function = func_declaration.name;
+                    current_function = function_name;
+
+                    continue;
+                } else if let Statements::Import(_) | Statements::StructDeclaration(_) | Statements::GlobalDeclaration(_) = current_statement.statement_type {
+                    self.advance_position();
 
                     continue;
                 } else if current_statement.statement_type == Statements::EOF {
-                    break;
+                    // Every file ends with an EOF, only the last one ends the program.
+                    if self.is_last_statement() {
+                        break;
+                    }
+
+                    self.advance_position();
+
+                    continue;
                 } else {
-                    throw_err!(self, &format!("Found statement outside function: Statement {:?}", current_statement));
+                    throw_err!(self, Diagnostic::error(DiagnosticCode::StatementOutsideFunction, "Found statement outside function", Span::from_statement(&current_statement))
+                        .with_help("only function, struct and global declarations are allowed at the top level"));
                 }
             }
 
+            let in_compare = matches!(self.get_current_stack_frame().kind, ScopeKind::Compare(_));
+
+            if in_compare && !matches!(current_statement.statement_type, Statements::OpenScope(_) | Statements::StackFramePop | Statements::EOF) {
+                throw_err!(self, Diagnostic::error(DiagnosticCode::InvalidCompareArm, "Only arms are allowed directly inside compare", Span::from_statement(&current_statement))
+                    .with_help("put the statement inside one of the eq, ne, lt, le, gt, ge or else arms"));
+            }
+
             match current_statement.statement_type.clone() {
+                Statements::OpenScope(scope_kind) => {
+                    let is_arm = matches!(scope_kind, ScopeKind::CompareArm(_));
+
+                    // The scope is still created on errors so the braces stay balanced.
+                    if is_arm && !in_compare {
+                        self.throw_err(Diagnostic::error(DiagnosticCode::InvalidCompareArm, "Compare arm outside of compare", Span::from_statement(&current_statement)));
+                    } else if !is_arm && in_compare {
+                        self.throw_err(Diagnostic::error(DiagnosticCode::InvalidCompareArm, "Only arms are allowed directly inside compare", Span::from_statement(&current_statement)));
+                    } else if scope_kind == ScopeKind::CompareArm(CompareArmCondition::Else) && self.current_compare_has_else() {
+                        self.throw_err(Diagnostic::error(DiagnosticCode::InvalidCompareArm, "Compare can only have one else arm", Span::from_statement(&current_statement)));
+                    }
+
+                    let child_statement = Statement{statement_type: Statements::ChildStackFrame(self.program_data.stack_frames.len()), ..current_statement.clone()};
+
+                    self.add_statement_to_current_stack_frame(child_statement);
+
+                    self.create_new_scope(scope_kind);
+                },
+                Statements::Break | Statements::Continue => {
+                    if self.find_enclosing_loop().is_none() {
+                        throw_err!(self, Diagnostic::error(DiagnosticCode::BreakOutsideLoop, "break and continue can only be used inside a loop", Span::from_statement(&current_statement)));
+                    }
+
+                    self.add_statement_to_current_stack_frame(current_statement);
+                },
                 Statements::Expression(Expression::BuiltInFunction(BuiltInFunctionsAst::BranchLinked(bl))) => {
                     self.add_statement_to_current_stack_frame(current_statement);
                 },
                 Statements::VariableDeclaration(var_declaration) => {
-                    self.add_var_to_stack_frame(&var_declaration);
+                    self.add_var_to_stack_frame(&var_declaration, Span::from_statement(&current_statement));
 
                     self.add_statement_to_current_stack_frame(current_statement);
                 },
                 Statements::StackFramePop => {
+                    let stack_frame_index = self.get_current_stack_frame_index();
+
                     self.pop_scope();
 
                     if self.scope_stack.len() == 0 {
+                        self.check_function_returns(&current_function, stack_frame_index, Span::from_statement(&current_statement));
+
                         current_function = String::default();
                     }
                 },
                 Statements::EOF => {
-                    break;
+                    self.throw_err(Diagnostic::error(DiagnosticCode::UnclosedScope, &format!("Function {} is missing its closing brace", current_function), Span::from_statement(&current_statement)));
+
+                    if self.is_last_statement() {
+                        break;
+                    }
+
+                    // The next file starts at the top level again.
+                    self.scope_stack.clear();
+                    current_function = String::default();
+                },
+                Statements::Import(_) => {
+                    self.throw_err(Diagnostic::error(DiagnosticCode::InvalidImport, "Imports are only allowed at the top level", Span::from_statement(&current_statement))
+                        .with_help("move the import above the function"));
+                },
+                Statements::StructDeclaration(name) => {
+                    self.throw_err(Diagnostic::error(DiagnosticCode::SyntaxError, &format!("Struct {} is declared inside a function", name), Span::from_statement(&current_statement))
+                        .with_help("structs are only declared at the top level, move it above the function"));
+                },
+                Statements::GlobalDeclaration(global) => {
+                    self.throw_err(Diagnostic::error(DiagnosticCode::SyntaxError, &format!("Global {} is declared inside a function", global.name), Span::from_statement(&current_statement))
+                        .with_help("globals are only declared at the top level, move it above the function"));
                 },
                 _ => {
                     self.add_statement_to_current_stack_frame(current_statement);
@@ -80,16 +211,139 @@ impl<'a> ScopeAnalysis<'a> {
             self.advance_position();
         }
 
-        print!("\n");
+        if self.program_data.verbose {
+            print!("\n");
+        }
+    }
+
+    pub fn collect_globals(&mut self) -> () {
+        for statement in self.program_data.statements.clone().iter() {
+            let Statements::GlobalDeclaration(global) = &statement.statement_type else {
+                continue;
+            };
+
+            let module = self.program_data.files.get(statement.file).unwrap().module.clone();
+            let name = qualified_function_name(&module, &global.name);
+            let span = Span::from_statement(statement);
+
+            if let Some(existing_global) = self.program_data.globals.get(&name) {
+                let diagnostic = Diagnostic::error(DiagnosticCode::DuplicateVariable, &format!("Duplicate global: {}", name), span)
+                    .with_label("redeclared here")
+                    .with_secondary(existing_global.span.clone(), "first declared here");
+
+                self.throw_err(diagnostic);
+
+                continue;
+            }
+
+            let Some(values) = self.global_values(global, &span) else {
+                continue;
+            };
+
+            let section = if global.constant {
+                GlobalSection::ReadOnly
+            } else if values.is_empty() {
+                GlobalSection::Bss
+            } else {
+                GlobalSection::Data
+            };
+
+            self.program_data.global_order.push(name.clone());
+            self.program_data.globals.insert(name, GlobalVariable{variable_type: global.variable_type.clone(), values, section, span});
+        }
+    }
+
+    // Values of the global known at compile time, empty when it has none. Numbers and arrays of
+    // numbers are the only globals with values, everything else starts zeroed.
+    pub fn global_values(&mut self, global : &GlobalDeclaration, span : &Span) -> Option<Vec<i64>> {
+        let Some(expressions) = &global.values else {
+            if global.constant {
+                self.throw_err(Diagnostic::error(DiagnosticCode::InvalidExpression, &format!("const {} needs a value", global.name), span.clone())
+                    .with_help("use static for globals that start zeroed"));
+
+                return None;
+            }
+
+            return Some(Vec::new());
+        };
+
+        let (element_type, length) = match &global.variable_type {
+            VariableType::Array(element_type, length) => (*element_type.clone(), *length),
+            variable_type => (variable_type.clone(), 1)
+        };
+
+        // Arrays of bytes can be filled with a string, the rest of the elements stay zero.
+        if let [Expression::Literal(Literal::String(string))] = expressions.as_slice() {
+            if !global.variable_type.is_array() || element_type.get_variable_size() != 1 || !element_type.is_integer() {
+                self.throw_err(Diagnostic::error(DiagnosticCode::TypeMismatch, &format!("A string can't be the value of global {} of type {:?}", global.name, global.variable_type), span.clone())
+                    .with_help("strings fill arrays of u8 or i8"));
+
+                return None;
+            }
+
+            if string.len() > length {
+                self.throw_err(Diagnostic::error(DiagnosticCode::TypeMismatch, &format!("String of {} bytes doesn't fit in {}, which has {} elements", string.len(), global.name, length), span.clone()));
+
+                return None;
+            }
+
+            return Some(string.bytes().map(|byte| if element_type.is_signed() { byte as i8 as i64 } else { byte as i64 }).collect());
+        }
+
+        if !element_type.is_integer() {
+            self.throw_err(Diagnostic::error(DiagnosticCode::TypeMismatch, &format!("Global {} of type {:?} can't be given a value", global.name, global.variable_type), span.clone())
+                .with_help("only numbers and arrays of numbers have values, leave the value out to start it zeroed"));
+
+            return None;
+        }
+
+        if expressions.len() > length {
+            let message = if global.variable_type.is_array() {
+                format!("{} values given to {}, which has {} elements", expressions.len(), global.name, length)
+            } else {
+                format!("{} values given to {}, which is not an array", expressions.len(), global.name)
+            };
+
+            self.throw_err(Diagnostic::error(DiagnosticCode::TypeMismatch, &message, span.clone()));
+
+            return None;
+        }
+
+        let mut values : Vec<i64> = Vec::new();
+
+        for expression in expressions.iter() {
+            let Expression::Literal(Literal::Number(num)) = expression else {
+                self.throw_err(Diagnostic::error(DiagnosticCode::InvalidExpression, &format!("Global {} can only be given numbers", global.name), span.clone())
+                    .with_help("values of globals are written into the program, they have to be known at compile time"));
+
+                return None;
+            };
+
+            if let Err(type_error) = type_checker::check_literal(*num as i128, &element_type) {
+                let mut diagnostic = Diagnostic::error(type_error.code, &format!("Invalid value for global {}: {}", global.name, type_error.message), span.clone());
+
+                if let Some(help) = type_error.help {
+                    diagnostic = diagnostic.with_help(&help);
+                }
+
+                self.throw_err(diagnostic);
+
+                return None;
+            }
+
+            values.push(*num);
+        }
+
+        return Some(values);
     }
 
-    pub fn create_new_scope(&mut self) -> () {
+    pub fn create_new_scope(&mut self, kind : ScopeKind) -> () {
         let new_frame_index = self.program_data.stack_frames.len();
 
         let parent = self.scope_stack.last().unwrap().clone();
         let function_name = self.get_stack_frame_by_index(parent.clone()).function.clone();
 
-        self.program_data.stack_frames.push(StackFrame::new(parent, function_name));
+        self.program_data.stack_frames.push(StackFrame::new(parent, function_name, kind));
 
         self.get_current_stack_frame().children.push(new_frame_index);
 
@@ -98,10 +352,8 @@ impl<'a> ScopeAnalysis<'a> {
         return;
     }
 
-    pub fn throw_err(&mut self, err : &str) -> () {
-        self.program_data.errors.push(String::from(err));
-
-        self.advance_position();
+    pub fn throw_err(&mut self, diagnostic : Diagnostic) -> () {
+        self.program_data.diagnostics.push(diagnostic);
 
         return;
     }
@@ -116,39 +368,120 @@ impl<'a> ScopeAnalysis<'a> {
         return self.program_data.stack_frames.get(stack_frame).unwrap();
     }
 
-    pub fn check_if_var_exists(&mut self, var : &VariableDeclaration) -> bool {
+    pub fn find_existing_var(&mut self, var : &VariableDeclaration) -> Option<StackVariable> {
         let mut current_stack_frame_index : usize = self.get_current_stack_frame_index();
 
         while current_stack_frame_index != usize::MAX {
             let stack_frame_borrow = self.get_stack_frame_by_index(current_stack_frame_index);
 
-            if let Some(_) = stack_frame_borrow.variables.get(&var.name) {
-                return true;
+            if let Some(existing_var) = stack_frame_borrow.variables.get(&var.name) {
+                return Some(existing_var.clone());
             }
 
             current_stack_frame_index = stack_frame_borrow.parent;
         }
 
-        return false;
+        return None;
     }
 
-    pub fn add_var_to_stack_frame(&mut self, var : &VariableDeclaration) -> () {
-        if self.check_if_var_exists(var) == true {
-            self.throw_err(&format!("Duplicate variable: {}", var.name));
+    pub fn add_var_to_stack_frame(&mut self, var : &VariableDeclaration, span : Span) -> () {
+        if let Some(existing_var) = self.find_existing_var(var) {
+            self.throw_err(Diagnostic::error(DiagnosticCode::DuplicateVariable, &format!("Duplicate variable: {}", var.name), span)
+                .with_label("redeclared here")
+                .with_secondary(existing_var.span, "previous declaration here"));
+
+            return;
+        }
+
+        let current_stack_frame_index = self.get_current_stack_frame_index();
+
+        if let Some((global_name, global)) = self.program_data.get_global_ref(current_stack_frame_index, &var.name) {
+            self.throw_err(Diagnostic::error(DiagnosticCode::DuplicateVariable, &format!("Variable {} collides with the global {}", var.name, global_name), span)
+                .with_label("declared here")
+                .with_secondary(global.span, "global declared here"));
 
             return;
         }
 
+        let function_name = self.get_current_stack_frame().function.clone();
+        let packed = self.program_data.functions.get(&function_name).is_some_and(|function| function.packed);
+
+        let alignment = if packed { 1 } else { var.variable_type.get_variable_alignment() };
+
         let current_stack_frame = self.get_current_stack_frame();
 
-        current_stack_frame.variables.insert(var.name.clone(), StackVariable{variable_type: var.variable_type.clone(), variable_size: var.variable_type.get_variable_size(), offset: current_stack_frame.stack_mem_allocated.clone()});
+        let offset = allocate_memory(&mut current_stack_frame.stack_mem_allocated, var.variable_type.get_variable_size(), alignment);
 
-        current_stack_frame.stack_mem_allocated += var.variable_type.get_variable_size();
+        current_stack_frame.variables.insert(var.name.clone(), StackVariable{variable_type: var.variable_type.clone(), variable_size: var.variable_type.get_variable_size(), offset, span});
 
         return;
     }
 
+    // Index of the closest loop frame around the current statement.
+    pub fn find_enclosing_loop(&mut self) -> Option<usize> {
+        for stack_frame in self.scope_stack.iter().rev() {
+            if self.program_data.stack_frames.get(*stack_frame).unwrap().kind == ScopeKind::Loop {
+                return Some(*stack_frame);
+            }
+        }
+
+        return None;
+    }
+
+    pub fn current_compare_has_else(&mut self) -> bool {
+        let children = self.get_current_stack_frame().children.clone();
+
+        return children.iter().any(|child| self.program_data.stack_frames.get(*child).unwrap().kind == ScopeKind::CompareArm(CompareArmCondition::Else));
+    }
+
+    // Warns when a function with a return type can reach its end without a return statement.
+    pub fn check_function_returns(&mut self, function_name : &str, stack_frame : usize, span : Span) -> () {
+        let function = self.program_data.functions.get(function_name).unwrap().clone();
+
+        // Duplicate functions keep the frame of the first definition.
+        if function.return_type == VariableType::Void || function.first_stack_frame != stack_frame {
+            return;
+        }
+
+        if !self.stack_frame_always_returns(stack_frame) {
+            self.throw_err(Diagnostic::warning(DiagnosticCode::MissingReturn, &format!("Function {} can reach its end without returning a value", function_name), span)
+                .with_secondary(function.span.clone(), "function declared here")
+                .with_help("add a return statement at the end of the function"));
+        }
+
+        return;
+    }
+
+    // A frame always returns when its last statement is a return, a block that always returns or
+    // a compare with an else arm where every arm returns.
+    pub fn stack_frame_always_returns(&mut self, stack_frame : usize) -> bool {
+        let last_statement = self.get_stack_frame_by_index(stack_frame).statements.last().map(|statement| statement.statement_type.clone());
+
+        return match last_statement {
+            Some(Statements::Return(_)) => true,
+            Some(Statements::ChildStackFrame(child)) => {
+                match self.get_stack_frame_by_index(child).kind.clone() {
+                    ScopeKind::Block => self.stack_frame_always_returns(child),
+                    ScopeKind::Compare(_) => {
+                        let arms = self.get_stack_frame_by_index(child).children.clone();
+
+                        let has_else = arms.iter().any(|arm| self.program_data.stack_frames.get(*arm).unwrap().kind == ScopeKind::CompareArm(CompareArmCondition::Else));
+
+                        has_else && arms.iter().all(|arm| self.stack_frame_always_returns(*arm))
+                    },
+                    _ => false
+                }
+            },
+            _ => false
+        };
+    }
+
     pub fn pop_scope(&mut self) -> () {
+        // The frame is complete, children already read the final size of their parents so it is
+        // aligned here instead of later.
+        let current_stack_frame = self.get_current_stack_frame();
+        current_stack_frame.stack_mem_allocated = align_memory(current_stack_frame.stack_mem_allocated, 16);
+
         self.scope_stack.pop();
 
         return;
@@ -168,6 +501,10 @@ impl<'a> ScopeAnalysis<'a> {
         return &self.program_data.statements.get(self.position).unwrap();
     }
 
+    pub fn is_last_statement(&self) -> bool {
+        return self.position + 1 >= self.program_data.statements.len();
+    }
+
     pub fn advance_position(&mut self) -> () {
         self.position += 1;
 
@@ -1,121 +1,412 @@
-use std::{collections::HashMap, panic};
-
-use crate::datatypes::{ast_statements::{AstIdentifiers, BuiltInFunctionsAst, CgBranchLinked, CgBuiltInFunctions, CgExpression, CgIdentifiers, CgStatement, CgStatementType, CgVariableInitialization, Expression, FunctionArg, Literal, StackVariableData, Statement, Statements, VariableType}, general_functions::align_memory, program_data::ProgramData, stack_frame::{StackFrame, StackVariable}, token::Identifiers};
+use crate::datatypes::{assembly_instructions::asm::{is_caller_saved, EXPRESSION_REGISTER_COUNT}, ast_statements::{BinaryOperation, BinaryOperators, BranchLinkedAst, BuiltInFunctionsAst, CgBinaryOperation, CgCast, CgElementAddress, CgFieldAddress, CgLoad, CgStore, CgRegisterAssignment, CgReturn, CgReturnLocation, MemoryLocationsAst, RegisterData, CgCompare, CgLoopJump, CgUnaryOperation, ScopeKind, CgBranchLinked, CgBuiltInFunctions, CgExpression, CgIdentifiers, CgStatement, CgStatementType, CgVariableInitialization, Expression, FieldAst, GlobalSection, Literal, StackVariableData, Statement, Statements, VariableType}, diagnostic::{Diagnostic, DiagnosticCode, Span}, program_data::ProgramData, stack_frame::{StackFrame, StackVariable}, token::Identifiers, type_checker::{self, TypeError}};
 
 macro_rules! throw_err {
-    ($self:expr, $error:expr) => {
-        $self.throw_err($error);
+    ($self:expr, $code:expr, $error:expr) => {
+        $self.throw_err($code, $error);
 
         return;
     };
 }
 
 pub struct SemanticAnaytis<'a> {
-    program_data : &'a mut ProgramData
+    program_data : &'a mut ProgramData,
+    // Span of the statement being processed, used for diagnostics.
+    current_span : Span
 }
 
 impl<'a> SemanticAnaytis<'a> {
     pub fn new(program_data : &'a mut ProgramData) -> Self {
         Self {
-            program_data
+            program_data,
+            current_span: Span { file: 0, line: 0, col: 0, start_pos: 0, end_pos: 0 }
         }
     }
 
     pub fn process_statement(&mut self, statement : &'_ Statement, stack_frame : usize) -> () {
+        self.current_span = Span::from_statement(statement);
+
         match statement.statement_type.clone() {
             Statements::VariableDeclaration(var_init) => {
+                if let (VariableType::Array(element_type, length), Some(Expression::Literal(Literal::String(string)))) = (&var_init.variable_type, &var_init.value) {
+                    let stack_offset = self.program_data.get_stack_variable_ref(stack_frame, &var_init.name, 0).unwrap().local_offset;
+
+                    self.string_to_stack_array(stack_frame, &var_init.name, string, element_type, *length, stack_offset);
+
+                    return;
+                }
+
                 if let Some(init_value) = var_init.value {
-                    let init_valid = match (var_init.variable_type.clone(), init_value.clone()) {
-                        (VariableType::I8  | VariableType::I16 | VariableType::I32 | VariableType::I64 | VariableType::U8 | VariableType::U16 | VariableType::U32 | VariableType::U64,
-                        Expression::Literal(Literal::Number(_))) => true,
-                        (_, Expression::Identifier(Identifiers::Identifier(identifier))) => {
-                            if let Some(var) = self.get_stack_variable(stack_frame, &identifier) {
-                                var.variable_type == var_init.variable_type
-                            } else if let Some(func_arg) = self.program_data.get_function_stack_arg_ref(stack_frame, &identifier) {
-                                func_arg.var.arg_var_type == var_init.variable_type
-                            } else {
-                                false
-                            }
-                        }
-                        _ => false
+                    let Some(cg_val) = self.value_to_cg(stack_frame, init_value, &var_init.variable_type, &format!("Invalid value for variable {} of type {:?}", var_init.name, var_init.variable_type)) else {
+                        return;
                     };
 
-                    if !init_valid {
-                        throw_err!(self, "Invalid var declaration");
+                    let var_being_init_ref = self.program_data.get_stack_variable_ref(stack_frame, &var_init.name, 0);
+
+                    self.add_cg_statement_to_stack_frame(stack_frame, CgStatement{statement_type: CgStatementType::VariableInitialization(CgVariableInitialization{init_value: cg_val, stack_offset: var_being_init_ref.unwrap().local_offset, variable_type: var_init.variable_type})});
+                };
+
+                return;
+            },
+            Statements::Assignment(assignment) => {
+                let type_error = |variable_type : &VariableType| format!("Invalid value assigned to {} of type {:?}", assignment.name, variable_type);
+
+                if let Some(var_ref) = self.program_data.get_stack_variable_ref(stack_frame, &assignment.name, 0) {
+                    let Some(cg_value) = self.value_to_cg(stack_frame, assignment.value, &var_ref.var.variable_type, &type_error(&var_ref.var.variable_type)) else {
+                        return;
+                    };
+
+                    self.add_cg_statement_to_stack_frame(stack_frame, CgStatement{statement_type: CgStatementType::VariableInitialization(CgVariableInitialization{init_value: cg_value, stack_offset: var_ref.local_offset, variable_type: var_ref.var.variable_type})});
+                } else if let Some(arg_ref) = self.program_data.get_function_stack_arg_ref(stack_frame, &assignment.name) {
+                    let Some(cg_value) = self.value_to_cg(stack_frame, assignment.value, &arg_ref.var.arg_var_type, &type_error(&arg_ref.var.arg_var_type)) else {
+                        return;
+                    };
+
+                    self.add_cg_statement_to_stack_frame(stack_frame, CgStatement{statement_type: CgStatementType::VariableInitialization(CgVariableInitialization{init_value: cg_value, stack_offset: arg_ref.local_offset, variable_type: arg_ref.var.arg_var_type})});
+                } else if let Some((register, function_arg)) = self.program_data.get_function_register_arg(stack_frame, &assignment.name) {
+                    let Some(cg_value) = self.value_to_cg(stack_frame, assignment.value, &function_arg.arg_var_type, &type_error(&function_arg.arg_var_type)) else {
+                        return;
+                    };
+
+                    self.add_cg_statement_to_stack_frame(stack_frame, CgStatement{statement_type: CgStatementType::RegisterAssignment(CgRegisterAssignment{value: cg_value, register})});
+                } else if let Some((global_name, global)) = self.program_data.get_global_ref(stack_frame, &assignment.name) {
+                    if !self.check_global_writable(&assignment.name, &global.section) {
+                        return;
                     }
 
-                    let cg_val = self.expression_to_cg(stack_frame, init_value);
+                    if global.variable_type.is_struct() {
+                        self.program_data.diagnostics.push(Diagnostic::error(DiagnosticCode::InvalidExpression, &format!("{} is a struct in a global and can't be written as a whole", assignment.name), self.current_span.clone())
+                            .with_help("assign its fields one by one"));
 
-                    let var_being_init_ref = self.program_data.get_stack_variable_ref(stack_frame, &var_init.name, 0);
+                        return;
+                    }
+
+                    // The address of the global is kept in a register while the value is
+                    // evaluated, so calls can't be used as the value.
+                    if let Err(error) = type_checker::check_expression(self.program_data, stack_frame, &assignment.value, &global.variable_type) {
+                        self.throw_type_error(&type_error(&global.variable_type), error);
 
-                    if let Some(cg_val_unwrapped) = cg_val {
-                        self.add_cg_statement_to_stack_frame(stack_frame, CgStatement{statement_type: CgStatementType::VariableInitialization(CgVariableInitialization{init_value: cg_val_unwrapped, stack_offset: var_being_init_ref.unwrap().local_offset, variable_type: var_init.variable_type})});
-                    } else {
                         return;
                     }
-                };
 
-                return;
+                    let Some(value) = self.expression_to_cg(stack_frame, assignment.value, &global.variable_type) else {
+                        return;
+                    };
+
+                    if value.registers_needed() + 1 > EXPRESSION_REGISTER_COUNT {
+                        throw_err!(self, DiagnosticCode::InvalidExpression, "Value is too complex, store it in a variable first");
+                    }
+
+                    self.add_cg_statement_to_stack_frame(stack_frame, CgStatement{statement_type: CgStatementType::Store(CgStore{pointer: CgExpression::GlobalAddress(global_name), value, variable_type: global.variable_type})});
+                } else {
+                    throw_err!(self, DiagnosticCode::UnknownVariable, &format!("Variable not found: {}", assignment.name));
+                }
             },
-            Statements::Expression(Expression::BuiltInFunction(func)) => {
-                match func {
-                    BuiltInFunctionsAst::BranchLinked(branch_linked) => {
-                        if self.program_data.functions.get(&branch_linked.function_name).is_none() {
-                            panic!("Branching to unknown function: {:?}", branch_linked.function_name);
+            Statements::ElementAssignment(element_assignment) => {
+                let path = element_assignment.array.path();
+
+                // Elements of an array in the struct a pointer points to aren't part of the global.
+                let through_pointer = type_checker::field_location(self.program_data, stack_frame, &element_assignment.array).is_ok_and(|location| location.through_pointer);
+
+                if let Some((_, global)) = self.program_data.get_global_ref(stack_frame, &element_assignment.array.name) {
+                    if !through_pointer && !self.check_global_writable(&element_assignment.array.name, &global.section) {
+                        return;
+                    }
+                }
+
+                let element_type = match type_checker::element_type(self.program_data, stack_frame, &element_assignment.array) {
+                    Ok(element_type) => element_type,
+                    Err(type_error) => {
+                        self.throw_type_error(&format!("Invalid assignment to {}", path), type_error);
+
+                        return;
+                    }
+                };
+
+                if let Err(type_error) = type_checker::check_index(self.program_data, stack_frame, &element_assignment.index, &element_type, &element_assignment.array) {
+                    self.throw_type_error(&format!("Invalid index into {}", path), type_error);
+
+                    return;
+                }
+
+                // The address of the element is kept in a register while the value is evaluated,
+                // so calls can't be used as the value.
+                if let Err(type_error) = type_checker::check_expression(self.program_data, stack_frame, &element_assignment.value, &element_type) {
+                    self.throw_type_error(&format!("Invalid value assigned to {} of type {:?}", path, element_type), type_error);
+
+                    return;
+                }
+
+                let Some(value) = self.expression_to_cg(stack_frame, element_assignment.value, &element_type) else {
+                    return;
+                };
+
+                let Some(element) = self.element_to_cg(stack_frame, &element_assignment.array, element_assignment.index) else {
+                    return;
+                };
+
+                match element {
+                    CgExpression::Identifier(CgIdentifiers::StackVariableData(stack_var_data)) => {
+                        if !self.check_registers_needed(&value) {
+                            return;
                         }
 
-                        let bl_function = self.program_data.functions.get(&branch_linked.function_name).unwrap().clone();
+                        self.add_cg_statement_to_stack_frame(stack_frame, CgStatement{statement_type: CgStatementType::VariableInitialization(CgVariableInitialization{init_value: value, stack_offset: stack_var_data.offset, variable_type: element_type})});
+                    },
+                    element_address => {
+                        if element_address.registers_needed().max(value.registers_needed() + 1) > EXPRESSION_REGISTER_COUNT {
+                            throw_err!(self, DiagnosticCode::InvalidExpression, "Index and value are too complex, store them in variables first");
+                        }
+
+                        self.add_cg_statement_to_stack_frame(stack_frame, CgStatement{statement_type: CgStatementType::Store(CgStore{pointer: element_address, value, variable_type: element_type})});
+                    }
+                }
+            },
+            Statements::FieldAssignment(field_assignment) => {
+                let path = field_assignment.field.path();
+
+                let location = match type_checker::field_location(self.program_data, stack_frame, &field_assignment.field) {
+                    Ok(location) => location,
+                    Err(type_error) => {
+                        self.throw_type_error(&format!("Invalid assignment to {}", path), type_error);
+
+                        return;
+                    }
+                };
+
+                if let Err(type_error) = type_checker::check_field_value(&location, &field_assignment.field) {
+                    self.throw_type_error(&format!("Invalid assignment to {}", path), type_error);
+
+                    return;
+                }
+
+                if location.global {
+                    let Some((_, global)) = self.program_data.get_global_ref(stack_frame, &field_assignment.field.name) else {
+                        return;
+                    };
+
+                    if !self.check_global_writable(&field_assignment.field.name, &global.section) {
+                        return;
+                    }
+                }
+
+                // Fields behind a pointer keep the address in a register while the value is
+                // evaluated, so calls can't be used as the value.
+                if let Err(type_error) = type_checker::check_expression(self.program_data, stack_frame, &field_assignment.value, &location.variable_type) {
+                    self.throw_type_error(&format!("Invalid value assigned to {} of type {:?}", path, location.variable_type), type_error);
+
+                    return;
+                }
+
+                let Some(value) = self.expression_to_cg(stack_frame, field_assignment.value, &location.variable_type) else {
+                    return;
+                };
 
-                        if branch_linked.args.len() != bl_function.args.len() {
-                            throw_err!(self, "Invalid arg length");
+                let Some(field) = self.field_to_cg(stack_frame, &field_assignment.field) else {
+                    return;
+                };
+
+                match field {
+                    CgExpression::Identifier(CgIdentifiers::StackVariableData(stack_var_data)) => {
+                        if !self.check_registers_needed(&value) {
+                            return;
                         }
 
-                        let mut cg_args : Vec<CgExpression> = Vec::new();
-
-                        let mut i = 0;
-                        while i < branch_linked.args.len() {
-                            let cg_expression = self.expression_to_cg(stack_frame, branch_linked.args.get(i).unwrap().clone());
-
-                            if let Some(cg_expression_unwrapped) = cg_expression {
-                                let valid : bool = match (cg_expression_unwrapped.clone(), bl_function.args.get(i).unwrap().arg_var_type.clone()) {
-                                    (
-                                        CgExpression::Literal(Literal::Number(_)),
-                                        VariableType::I8 | VariableType::I16 | VariableType::I32
-                                    ) => true,
-                                    (
-                                        CgExpression::Identifier(CgIdentifiers::StackVariableData(stack_var_data)),
-                                        _
-                                    ) => {
-                                        stack_var_data.variable_type == bl_function.args.get(i).unwrap().arg_var_type
-                                    },
-                                    _ => false
-                                };
-
-                                if !valid {
-                                    throw_err!(self, "Invalid args in bl");
-                                }
+                        self.add_cg_statement_to_stack_frame(stack_frame, CgStatement{statement_type: CgStatementType::VariableInitialization(CgVariableInitialization{init_value: value, stack_offset: stack_var_data.offset, variable_type: location.variable_type})});
+                    },
+                    field_address => {
+                        if field_address.registers_needed().max(value.registers_needed() + 1) > EXPRESSION_REGISTER_COUNT {
+                            throw_err!(self, DiagnosticCode::InvalidExpression, "Value is too complex, store it in a variable first");
+                        }
+
+                        self.add_cg_statement_to_stack_frame(stack_frame, CgStatement{statement_type: CgStatementType::Store(CgStore{pointer: field_address, value, variable_type: location.variable_type})});
+                    }
+                }
+            },
+            Statements::Return(value) => {
+                let function_name = self.get_stack_frame_by_index(stack_frame).function.clone();
+                let function = self.program_data.functions.get(&function_name).unwrap().clone();
+
+                let cg_value = match (value, function.return_type.clone()) {
+                    (None, VariableType::Void) => None,
+                    (Some(_), VariableType::Void) => {
+                        let diagnostic = Diagnostic::error(DiagnosticCode::InvalidReturn, &format!("Function {} doesn't return a value", function_name), self.current_span.clone())
+                            .with_secondary(function.span.clone(), "function declared as void here");
+
+                        self.program_data.diagnostics.push(diagnostic);
+
+                        return;
+                    },
+                    (None, return_type) => {
+                        let diagnostic = Diagnostic::error(DiagnosticCode::InvalidReturn, &format!("Function {} must return a value of type {:?}", function_name, return_type), self.current_span.clone())
+                            .with_secondary(function.span.clone(), "function declared here");
+
+                        self.program_data.diagnostics.push(diagnostic);
 
-                                cg_args.push(cg_expression_unwrapped);
+                        return;
+                    },
+                    (Some(value), return_type) => {
+                        let Some(cg_value) = self.value_to_cg(stack_frame, value, &return_type, &format!("Invalid return value for function {}, expected {:?}", function_name, return_type)) else {
+                            return;
+                        };
+
+                        Some(cg_value)
+                    }
+                };
+
+                let return_location = match function.return_location.clone() {
+                    Some(MemoryLocationsAst::Register(register)) => Some(CgReturnLocation::Register(register)),
+                    Some(MemoryLocationsAst::Stack(_)) => Some(CgReturnLocation::Stack(self.program_data.get_function_stack_return_offset(stack_frame).unwrap())),
+                    None => None
+                };
+
+                // Frames inside the function are freed here, the function frame by its epilogue.
+                let mut unwind_bytes = 0;
+                let mut current_stack_frame = stack_frame;
+
+                while current_stack_frame != function.first_stack_frame {
+                    let stack_frame_borrow = self.get_stack_frame_by_index(current_stack_frame);
+
+                    unwind_bytes += stack_frame_borrow.stack_mem_allocated + stack_frame_borrow.saved_registers_size();
+                    current_stack_frame = stack_frame_borrow.parent;
+                }
+
+                self.add_cg_statement_to_stack_frame(stack_frame, CgStatement{statement_type: CgStatementType::Return(CgReturn{value: cg_value, variable_type: function.return_type, return_location, unwind_bytes, function_stack_frame: function.first_stack_frame})});
+            },
+            Statements::ChildStackFrame(child_stack_frame) => {
+                match self.get_stack_frame_by_index(child_stack_frame).kind.clone() {
+                    ScopeKind::Compare(compare) => {
+                        let operand_type = match type_checker::comparison_operand_type(self.program_data, stack_frame, &BinaryOperation{operator: BinaryOperators::Equal, left: Box::new(compare.left.clone()), right: Box::new(compare.right.clone())}) {
+                            Ok(operand_type) => operand_type,
+                            Err(type_error) => {
+                                self.throw_type_error("Invalid values given to compare", type_error);
+
+                                return;
+                            }
+                        };
+
+                        for value in [&compare.left, &compare.right] {
+                            if let Err(type_error) = type_checker::check_expression(self.program_data, stack_frame, value, &operand_type) {
+                                self.throw_type_error(&format!("Values given to compare must fit {:?}", operand_type), type_error);
+
+                                return;
                             }
+                        }
+
+                        let Some(left) = self.expression_to_cg(stack_frame, compare.left, &operand_type) else {
+                            return;
+                        };
+
+                        let Some(right) = self.expression_to_cg(stack_frame, compare.right, &operand_type) else {
+                            return;
+                        };
 
-                            i += 1;
+                        // The right value is evaluated while the left one is kept in the first register.
+                        if left.registers_needed().max(right.registers_needed() + 1) > EXPRESSION_REGISTER_COUNT {
+                            throw_err!(self, DiagnosticCode::InvalidExpression, "Values given to compare are too complex, store them in variables first");
                         }
 
-                        self.add_cg_statement_to_stack_frame(stack_frame, CgStatement { statement_type: CgStatementType::BuiltInFunction(CgBuiltInFunctions::BranchLinked(CgBranchLinked{function_name: branch_linked.function_name, args: cg_args}))});
+                        self.add_cg_statement_to_stack_frame(stack_frame, CgStatement{statement_type: CgStatementType::Compare(CgCompare{left, right, variable_type: operand_type, stack_frame: child_stack_frame})});
+                    },
+                    _ => {
+                        self.add_cg_statement_to_stack_frame(stack_frame, CgStatement{statement_type: CgStatementType::ChildStackFrame(child_stack_frame)});
+                    }
+                }
+            },
+            Statements::Break | Statements::Continue => {
+                let mut unwind_bytes = 0;
+                let mut current_stack_frame = stack_frame;
+
+                // Scope analysis made sure there is a loop around.
+                while self.get_stack_frame_by_index(current_stack_frame).kind != ScopeKind::Loop {
+                    let stack_frame_borrow = self.get_stack_frame_by_index(current_stack_frame);
+
+                    unwind_bytes += stack_frame_borrow.stack_mem_allocated + stack_frame_borrow.saved_registers_size();
+                    current_stack_frame = stack_frame_borrow.parent;
+                }
+
+                let loop_jump = CgLoopJump{loop_stack_frame: current_stack_frame, unwind_bytes};
+
+                let statement_type = if statement.statement_type == Statements::Break {
+                    CgStatementType::Break(loop_jump)
+                } else {
+                    CgStatementType::Continue(loop_jump)
+                };
+
+                self.add_cg_statement_to_stack_frame(stack_frame, CgStatement{statement_type});
+            },
+            Statements::Expression(Expression::BuiltInFunction(func)) => {
+                match func {
+                    BuiltInFunctionsAst::BranchLinked(branch_linked) => {
+                        let Some(cg_branch_linked) = self.branch_linked_to_cg(stack_frame, &branch_linked) else {
+                            return;
+                        };
+
+                        self.add_cg_statement_to_stack_frame(stack_frame, CgStatement { statement_type: CgStatementType::BuiltInFunction(CgBuiltInFunctions::BranchLinked(cg_branch_linked))});
                     },
                     BuiltInFunctionsAst::Assembly(asm_expression) => {
                         let asm_code : String = match *asm_expression {
                             Expression::Literal(Literal::String(asm_code)) => asm_code,
                             Expression::BuiltInFunction(func) => {
-                                func.parse(self.program_data, stack_frame).unwrap().to_string()
+                                match func.parse(self.program_data, stack_frame) {
+                                    Ok(literal) => literal.to_string(),
+                                    Err(err) => {
+                                        throw_err!(self, DiagnosticCode::InvalidBuiltInArgs, &err);
+                                    }
+                                }
                             },
                             _ => {
-                                throw_err!(self, "Invalid arg given to asm func");
+                                throw_err!(self, DiagnosticCode::InvalidBuiltInArgs, "Invalid arg given to asm func");
                             }
                         };
 
                         self.add_cg_statement_to_stack_frame(stack_frame, CgStatement{ statement_type: CgStatementType::BuiltInFunction(CgBuiltInFunctions::Assembly(asm_code))});
-                    }
+                    },
+                    BuiltInFunctionsAst::Store(store) => {
+                        let pointer_type = match type_checker::load_pointer_type(self.program_data, stack_frame, &store.pointer) {
+                            Ok(pointer_type) => pointer_type,
+                            Err(type_error) => {
+                                self.throw_type_error("Invalid pointer given to store", type_error);
+
+                                return;
+                            }
+                        };
+
+                        let pointee = pointer_type.pointee().unwrap().clone();
+
+                        if pointee.is_struct() {
+                            self.program_data.diagnostics.push(Diagnostic::error(DiagnosticCode::InvalidExpression, &format!("{:?} can't be stored as a whole", pointee), self.current_span.clone())
+                                .with_help("store its fields one by one with pointer.field = value"));
+
+                            return;
+                        }
+
+                        if let Err(type_error) = type_checker::check_expression(self.program_data, stack_frame, &store.pointer, &pointer_type) {
+                            self.throw_type_error("Invalid pointer given to store", type_error);
+
+                            return;
+                        }
+
+                        if let Err(type_error) = type_checker::check_expression(self.program_data, stack_frame, &store.value, &pointee) {
+                            self.throw_type_error(&format!("Invalid value stored through {:?}", pointer_type), type_error);
+
+                            return;
+                        }
+
+                        let Some(value) = self.expression_to_cg(stack_frame, *store.value, &pointee) else {
+                            return;
+                        };
+
+                        let Some(pointer) = self.expression_to_cg(stack_frame, *store.pointer, &pointer_type) else {
+                            return;
+                        };
+
+                        // The value is evaluated while the pointer is kept in the first register.
+                        if pointer.registers_needed().max(value.registers_needed() + 1) > EXPRESSION_REGISTER_COUNT {
+                            throw_err!(self, DiagnosticCode::InvalidExpression, "Values given to store are too complex, store them in variables first");
+                        }
+
+                        self.add_cg_statement_to_stack_frame(stack_frame, CgStatement{statement_type: CgStatementType::Store(CgStore{pointer, value, variable_type: pointee})});
+                    },
                     _ => {}
                 }
             }
@@ -125,33 +416,402 @@ impl<'a> SemanticAnaytis<'a> {
         return;
     }
 
-    pub fn expression_to_cg(&mut self, stack_frame : usize, expression : Expression) -> Option<CgExpression> {
+    // Converts the whole value of a declaration, assignment or return, which unlike other
+    // expressions can be a function call.
+    pub fn value_to_cg(&mut self, stack_frame : usize, value : Expression, variable_type : &VariableType, type_error : &str) -> Option<CgExpression> {
+        if let Expression::BuiltInFunction(BuiltInFunctionsAst::BranchLinked(branch_linked)) = &value {
+            let cg_branch_linked = self.branch_linked_to_cg(stack_frame, branch_linked)?;
+
+            let function = self.program_data.functions.get(&cg_branch_linked.function_name).unwrap().clone();
+
+            if let Err(conversion_error) = type_checker::check_conversion(&function.return_type, variable_type) {
+                let message = if function.return_type == VariableType::Void {
+                    format!("Function {} doesn't return a value", branch_linked.function_name)
+                } else {
+                    format!("{}, but function {} returns {:?}", type_error, branch_linked.function_name, function.return_type)
+                };
+
+                let mut diagnostic = Diagnostic::error(DiagnosticCode::TypeMismatch, &message, self.current_span.clone())
+                    .with_secondary(function.span.clone(), "function declared here");
+
+                if let Some(help) = conversion_error.help {
+                    diagnostic = diagnostic.with_help(&help);
+                }
+
+                self.program_data.diagnostics.push(diagnostic);
+
+                return None;
+            }
+
+            return Some(CgExpression::BranchLinked(cg_branch_linked));
+        }
+
+        if let Err(err) = type_checker::check_expression(self.program_data, stack_frame, &value, variable_type) {
+            self.throw_type_error(type_error, err);
+
+            return None;
+        }
+
+        let cg_value = self.expression_to_cg(stack_frame, value, variable_type)?;
+
+        if !self.check_registers_needed(&cg_value) {
+            return None;
+        }
+
+        return Some(cg_value);
+    }
+
+    pub fn branch_linked_to_cg(&mut self, stack_frame : usize, branch_linked : &BranchLinkedAst) -> Option<CgBranchLinked> {
+        let function_name = self.program_data.resolve_function_name(stack_frame, &branch_linked.function_name);
+
+        if self.program_data.functions.get(&function_name).is_none() {
+            let mut diagnostic = Diagnostic::error(DiagnosticCode::UnknownFunction, &format!("Branching to unknown function: {}", branch_linked.function_name), self.current_span.clone());
+
+            // Point at functions with the same name in imported modules.
+            let suffix = format!("::{}", branch_linked.function_name);
+            let mut candidates : Vec<&String> = self.program_data.functions.keys().filter(|name| name.ends_with(&suffix)).collect();
+            candidates.sort();
+
+            if let Some(candidate) = candidates.first() {
+                diagnostic = diagnostic.with_help(&format!("use the module name, like {}", candidate));
+            }
+
+            self.program_data.diagnostics.push(diagnostic);
+
+            return None;
+        }
+
+        let branch_linked = &BranchLinkedAst{function_name, args: branch_linked.args.clone()};
+
+        let bl_function = self.program_data.functions.get(&branch_linked.function_name).unwrap().clone();
+
+        if branch_linked.args.len() != bl_function.args.len() {
+            let diagnostic = Diagnostic::error(DiagnosticCode::ArgCountMismatch, &format!("Function {} takes {} args but {} were given", branch_linked.function_name, bl_function.args.len(), branch_linked.args.len()), self.current_span.clone())
+                .with_secondary(bl_function.span.clone(), "function declared here");
+
+            self.program_data.diagnostics.push(diagnostic);
+
+            return None;
+        }
+
+        let mut cg_args : Vec<CgExpression> = Vec::new();
+
+        // Register args that need evaluating are kept in the expression registers until all of
+        // them are done, so reading one arg register doesn't see another arg already moved in.
+        let mut register_args_depth = 0;
+
+        let mut i = 0;
+        while i < branch_linked.args.len() {
+            let arg_expression = branch_linked.args.get(i).unwrap().clone();
+            let arg_type = bl_function.args.get(i).unwrap().arg_var_type.clone();
+
+            if let Err(err) = type_checker::check_expression(self.program_data, stack_frame, &arg_expression, &arg_type) {
+                let mut diagnostic = Diagnostic::error(err.code, &format!("Invalid arg {} given to {}, expected {:?}: {}", i + 1, branch_linked.function_name, arg_type, err.message), self.current_span.clone())
+                    .with_secondary(bl_function.span.clone(), "function declared here");
+
+                if let Some(help) = err.help {
+                    diagnostic = diagnostic.with_help(&help);
+                }
+
+                self.program_data.diagnostics.push(diagnostic);
+
+                return None;
+            }
+
+            let cg_expression = self.expression_to_cg(stack_frame, arg_expression, &arg_type)?;
+
+            if !self.check_registers_needed(&cg_expression) {
+                return None;
+            }
+
+            if let MemoryLocationsAst::Register(_) = bl_function.args.get(i).unwrap().memory_location {
+                if !matches!(cg_expression, CgExpression::Literal(_)) {
+                    if cg_expression.registers_needed() + register_args_depth > EXPRESSION_REGISTER_COUNT {
+                        self.throw_err(DiagnosticCode::InvalidExpression, &format!("Args given to {} are too complex, store them in variables first", branch_linked.function_name));
+
+                        return None;
+                    }
+
+                    register_args_depth += 1;
+                }
+            }
+
+            cg_args.push(cg_expression);
+
+            i += 1;
+        }
+
+        // C functions may overwrite every caller saved register, byte functions only the ones
+        // they use. Exported functions can't choose where their args are, so they keep them
+        // around every call.
+        let mut saved_registers : Vec<String> = Vec::new();
+
+        let caller = self.get_stack_frame_by_index(stack_frame).function.clone();
+        let caller_function = self.program_data.functions.get(&caller).unwrap();
+
+        if bl_function.external || caller_function.exported {
+            for arg in caller_function.args.iter() {
+                if let MemoryLocationsAst::Register(register) = &arg.memory_location {
+                    if is_caller_saved(register) {
+                        saved_registers.push(register.clone());
+                    }
+                }
+            }
+        }
+
+        return Some(CgBranchLinked{function_name: branch_linked.function_name.clone(), args: cg_args, saved_registers});
+    }
+
+    // variable_type is the type the expression is stored as, literals and operations take that type.
+    pub fn expression_to_cg(&mut self, stack_frame : usize, expression : Expression, variable_type : &VariableType) -> Option<CgExpression> {
         match expression {
+            Expression::Literal(Literal::String(string)) => return Some(CgExpression::StringAddress(self.program_data.string_index(&string))),
             Expression::Literal(literal) => return Some(CgExpression::Literal(literal)),
             Expression::Identifier(Identifiers::Identifier(identifier)) => {
                 if let Some(stack_var_ref) = self.program_data.get_stack_variable_ref(stack_frame, &identifier, 0) {
                     return Some(CgExpression::Identifier(CgIdentifiers::StackVariableData(StackVariableData{offset: stack_var_ref.local_offset, variable_type: stack_var_ref.var.variable_type})));
                 } else if let Some(function_arg_ref) = self.program_data.get_function_stack_arg_ref(stack_frame, &identifier) {
                     return Some(CgExpression::Identifier(CgIdentifiers::StackVariableData(StackVariableData{offset: function_arg_ref.local_offset, variable_type: function_arg_ref.var.arg_var_type})));
+                } else if let Some((register, function_arg)) = self.program_data.get_function_register_arg(stack_frame, &identifier) {
+                    return Some(CgExpression::Identifier(CgIdentifiers::RegisterData(RegisterData{register, variable_type: function_arg.arg_var_type})));
+                } else if let Some((global_name, global)) = self.program_data.get_global_ref(stack_frame, &identifier) {
+                    return Some(CgExpression::Load(CgLoad{pointer: Box::new(CgExpression::GlobalAddress(global_name)), variable_type: global.variable_type}));
                 }
 
-                self.throw_err("Variable not found");
+                self.throw_err(DiagnosticCode::UnknownVariable, &format!("Variable not found: {}", identifier));
+
+                return None;
+            },
+            Expression::BinaryOperation(binary_operation) => {
+                // Checked by type_checker::check_expression before the expression is converted.
+                let operand_type = if binary_operation.operator.is_comparison() {
+                    type_checker::comparison_operand_type(self.program_data, stack_frame, &binary_operation).unwrap_or(VariableType::I64)
+                } else {
+                    variable_type.clone()
+                };
+
+                // Pointers are moved by an i64 number of pointees.
+                let right_type = if operand_type.is_pointer() && !binary_operation.operator.is_comparison() {
+                    VariableType::I64
+                } else {
+                    operand_type.clone()
+                };
+
+                let left = self.expression_to_cg(stack_frame, *binary_operation.left, &operand_type)?;
+                let right = self.expression_to_cg(stack_frame, *binary_operation.right, &right_type)?;
+
+                return Some(CgExpression::BinaryOperation(CgBinaryOperation{operator: binary_operation.operator, left: Box::new(left), right: Box::new(right), variable_type: operand_type}));
+            },
+            Expression::UnaryOperation(unary_operation) => {
+                let operand = self.expression_to_cg(stack_frame, *unary_operation.operand, variable_type)?;
+
+                return Some(CgExpression::UnaryOperation(CgUnaryOperation{operator: unary_operation.operator, operand: Box::new(operand), variable_type: variable_type.clone()}));
+            },
+            Expression::Cast(cast) => {
+                let from = type_checker::cast_source_type(self.program_data, stack_frame, &cast);
+                let value = self.expression_to_cg(stack_frame, *cast.value, &from)?;
+
+                return Some(CgExpression::Cast(CgCast{value: Box::new(value), from, to: cast.variable_type}));
+            },
+            Expression::Index(index) => {
+                let element_type = type_checker::element_type(self.program_data, stack_frame, &index.array).ok()?;
+
+                return match self.element_to_cg(stack_frame, &index.array, *index.index)? {
+                    element_address @ (CgExpression::ElementAddress(_) | CgExpression::FieldAddress(_)) => Some(CgExpression::Load(CgLoad{pointer: Box::new(element_address), variable_type: element_type})),
+                    element => Some(element)
+                };
+            },
+            Expression::Field(field) => {
+                let location = type_checker::field_location(self.program_data, stack_frame, &field).ok()?;
+
+                return match self.field_to_cg(stack_frame, &field)? {
+                    CgExpression::FieldAddress(field_address) => Some(CgExpression::Load(CgLoad{pointer: Box::new(CgExpression::FieldAddress(field_address)), variable_type: location.variable_type})),
+                    field => Some(field)
+                };
+            },
+            Expression::BuiltInFunction(size_of @ (BuiltInFunctionsAst::SizeOf(_) | BuiltInFunctionsAst::SizeOfString(_) | BuiltInFunctionsAst::OffsetOf(_, _))) => {
+                return match size_of.parse(self.program_data, stack_frame) {
+                    Ok(literal) => Some(CgExpression::Literal(literal)),
+                    Err(err) => {
+                        self.throw_err(DiagnosticCode::InvalidBuiltInArgs, &err);
+
+                        None
+                    }
+                };
+            },
+            Expression::BuiltInFunction(BuiltInFunctionsAst::Address(identifier)) => {
+                // Checked by type_checker::address_type, register args have no address.
+                if let Some(stack_var_ref) = self.program_data.get_stack_variable_ref(stack_frame, &identifier, 0) {
+                    return Some(CgExpression::Address(stack_var_ref.local_offset));
+                } else if let Some(function_arg_ref) = self.program_data.get_function_stack_arg_ref(stack_frame, &identifier) {
+                    return Some(CgExpression::Address(function_arg_ref.local_offset));
+                } else if let Some((global_name, _)) = self.program_data.get_global_ref(stack_frame, &identifier) {
+                    return Some(CgExpression::GlobalAddress(global_name));
+                }
+
+                self.throw_err(DiagnosticCode::UnknownVariable, &format!("Variable not found: {}", identifier));
+
+                return None;
+            },
+            Expression::BuiltInFunction(BuiltInFunctionsAst::Load(pointer)) => {
+                let pointer_type = type_checker::infer_expression_type(self.program_data, stack_frame, &pointer)?;
+                let pointee = pointer_type.pointee()?.clone();
+
+                let pointer = self.expression_to_cg(stack_frame, *pointer, &pointer_type)?;
+
+                return Some(CgExpression::Load(CgLoad{pointer: Box::new(pointer), variable_type: pointee}));
+            },
+            Expression::BuiltInFunction(BuiltInFunctionsAst::BranchLinked(_)) => {
+                self.program_data.diagnostics.push(Diagnostic::error(DiagnosticCode::InvalidExpression, "Function calls can't be used inside other expressions", self.current_span.clone())
+                    .with_help("store the result in a variable first"));
 
                 return None;
             },
             _ => {
-                self.throw_err("Invalid Expression");
+                self.throw_err(DiagnosticCode::InvalidExpression, "Invalid Expression");
 
                 return None;
             }
         }
     }
 
-    pub fn process_stack_frame(&mut self, stack_frame : usize) -> () {
-        let stack_mem = self.get_stack_frame_by_index_mut(stack_frame).stack_mem_allocated.clone();
+    // Element of the array at index. Constant indices are resolved to the element itself, others to
+    // its address, which is checked against the length of the array in the debug profile.
+    pub fn element_to_cg(&mut self, stack_frame : usize, array : &FieldAst, index : Expression) -> Option<CgExpression> {
+        // Elements of global arrays and of arrays behind a pointer are always used through their
+        // address.
+        let (offset, global, pointer, array_type) = if array.fields.is_empty() {
+            match self.program_data.get_stack_variable_ref(stack_frame, &array.name, 0) {
+                Some(stack_var_ref) => (stack_var_ref.local_offset, None, None, stack_var_ref.var.variable_type),
+                None => {
+                    let (global_name, global) = self.program_data.get_global_ref(stack_frame, &array.name)?;
+
+                    (0, Some(global_name), None, global.variable_type)
+                }
+            }
+        } else {
+            match self.field_to_cg(stack_frame, array)? {
+                CgExpression::Identifier(CgIdentifiers::StackVariableData(stack_var_data)) => (stack_var_data.offset, None, None, stack_var_data.variable_type),
+                CgExpression::FieldAddress(field_address) => {
+                    let array_type = type_checker::field_location(self.program_data, stack_frame, array).ok()?.variable_type;
+
+                    match *field_address.pointer {
+                        CgExpression::GlobalAddress(global_name) => (field_address.offset, Some(global_name), None, array_type),
+                        pointer => (field_address.offset, None, Some(Box::new(pointer)), array_type)
+                    }
+                },
+                _ => return None
+            }
+        };
+
+        let VariableType::Array(element_type, length) = array_type else {
+            return None;
+        };
+
+        let element_size = element_type.get_variable_size();
+
+        if let Expression::Literal(Literal::Number(num)) = index {
+            let offset = offset + num as usize * element_size;
+
+            return match (global, pointer) {
+                (Some(global_name), _) => Some(CgExpression::FieldAddress(CgFieldAddress{pointer: Box::new(CgExpression::GlobalAddress(global_name)), offset})),
+                (_, Some(pointer)) => Some(CgExpression::FieldAddress(CgFieldAddress{pointer, offset})),
+                (None, None) => Some(CgExpression::Identifier(CgIdentifiers::StackVariableData(StackVariableData{offset, variable_type: *element_type})))
+            };
+        }
+
+        let index = self.expression_to_cg(stack_frame, index, &VariableType::I64)?;
+        let bounds_check = if self.program_data.bounds_checks { Some(length) } else { None };
+
+        return Some(CgExpression::ElementAddress(CgElementAddress{offset, index: Box::new(index), element_size, bounds_check, global, pointer}));
+    }
+
+    // Field of a struct. Fields of variables and stack args are resolved to the field itself,
+    // fields of the struct a pointer points to to their address.
+    pub fn field_to_cg(&mut self, stack_frame : usize, field : &FieldAst) -> Option<CgExpression> {
+        let location = type_checker::field_location(self.program_data, stack_frame, field).ok()?;
+
+        if location.through_pointer {
+            let pointer_type = type_checker::identifier_type(self.program_data, stack_frame, &field.name)?;
+            let pointer = self.expression_to_cg(stack_frame, Expression::Identifier(Identifiers::Identifier(field.name.clone())), &pointer_type)?;
+
+            return Some(CgExpression::FieldAddress(CgFieldAddress{pointer: Box::new(pointer), offset: location.offset}));
+        }
 
-        self.get_stack_frame_by_index_mut(stack_frame).stack_mem_allocated = align_memory(stack_mem, 16);
+        if location.global {
+            let (global_name, _) = self.program_data.get_global_ref(stack_frame, &field.name)?;
 
+            return Some(CgExpression::FieldAddress(CgFieldAddress{pointer: Box::new(CgExpression::GlobalAddress(global_name)), offset: location.offset}));
+        }
+
+        let local_offset = if let Some(stack_var_ref) = self.program_data.get_stack_variable_ref(stack_frame, &field.name, 0) {
+            stack_var_ref.local_offset
+        } else {
+            self.program_data.get_function_stack_arg_ref(stack_frame, &field.name)?.local_offset
+        };
+
+        return Some(CgExpression::Identifier(CgIdentifiers::StackVariableData(StackVariableData{offset: local_offset + location.offset, variable_type: location.variable_type})));
+    }
+
+    // Fills the array with the bytes of the string and zeroes the elements after them. The bytes
+    // are stored as numbers, 8 at a time.
+    pub fn string_to_stack_array(&mut self, stack_frame : usize, name : &str, string : &str, element_type : &VariableType, length : usize, stack_offset : usize) -> () {
+        if element_type.get_variable_size() != 1 || !element_type.is_integer() {
+            throw_err!(self, DiagnosticCode::TypeMismatch, &format!("A string can't fill {}, which is an array of {:?}", name, element_type));
+        }
+
+        if string.len() > length {
+            throw_err!(self, DiagnosticCode::TypeMismatch, &format!("String of {} bytes doesn't fit in {}, which has {} elements", string.len(), name, length));
+        }
+
+        let mut bytes = string.as_bytes().to_vec();
+        bytes.resize(length, 0);
+
+        let mut position = 0;
+
+        while position < length {
+            let (piece_size, piece_type) = match length - position {
+                8.. => (8, VariableType::U64),
+                4..=7 => (4, VariableType::U32),
+                2..=3 => (2, VariableType::U16),
+                _ => (1, VariableType::U8)
+            };
+
+            let mut piece : u64 = 0;
+
+            for (i, byte) in bytes[position..position + piece_size].iter().enumerate() {
+                piece |= (*byte as u64) << (i * 8);
+            }
+
+            self.add_cg_statement_to_stack_frame(stack_frame, CgStatement{statement_type: CgStatementType::VariableInitialization(CgVariableInitialization{init_value: CgExpression::Literal(Literal::Number(piece as i64)), stack_offset: stack_offset + position, variable_type: piece_type})});
+
+            position += piece_size;
+        }
+    }
+
+    // Globals declared with const are placed in read only memory.
+    pub fn check_global_writable(&mut self, name : &str, section : &GlobalSection) -> bool {
+        if *section == GlobalSection::ReadOnly {
+            self.program_data.diagnostics.push(Diagnostic::error(DiagnosticCode::InvalidExpression, &format!("{} is a const and can't be written", name), self.current_span.clone())
+                .with_help("declare it with static to change it"));
+
+            return false;
+        }
+
+        return true;
+    }
+
+    pub fn check_registers_needed(&mut self, expression : &CgExpression) -> bool {
+        if expression.registers_needed() > EXPRESSION_REGISTER_COUNT {
+            self.throw_err(DiagnosticCode::InvalidExpression, &format!("Expression needs more than {} registers, split it into multiple statements", EXPRESSION_REGISTER_COUNT));
+
+            return false;
+        }
+
+        return true;
+    }
+
+    pub fn process_stack_frame(&mut self, stack_frame : usize) -> () {
         for statement in self.get_stack_frame_by_index(stack_frame).statements.clone().iter() {
             self.process_statement(statement, stack_frame);
         }
@@ -164,8 +824,10 @@ impl<'a> SemanticAnaytis<'a> {
     }
 
     pub fn process_all_functions(&mut self) -> () {
-        for (function_name, function) in self.program_data.functions.clone().iter() {
-            self.process_stack_frame_and_children(function.first_stack_frame.clone());
+        for function_name in self.program_data.function_order.clone() {
+            let first_stack_frame = self.program_data.functions.get(&function_name).unwrap().first_stack_frame;
+
+            self.process_stack_frame_and_children(first_stack_frame);
         }
     }
 
@@ -197,8 +859,24 @@ impl<'a> SemanticAnaytis<'a> {
         self.process_stack_frame(stack_frame_index);
     }
 
-    pub fn throw_err(&mut self, err : &str) -> () {
-        self.program_data.errors.push(String::from(err));
+    // Errors about the operands are reported as they are, others with the value they were found in.
+    pub fn throw_type_error(&mut self, context : &str, type_error : TypeError) -> () {
+        let message = match type_error.code {
+            DiagnosticCode::UnknownVariable | DiagnosticCode::InvalidExpression => type_error.message,
+            _ => format!("{}: {}", context, type_error.message)
+        };
+
+        let mut diagnostic = Diagnostic::error(type_error.code, &message, self.current_span.clone());
+
+        if let Some(help) = type_error.help {
+            diagnostic = diagnostic.with_help(&help);
+        }
+
+        self.program_data.diagnostics.push(diagnostic);
+    }
+
+    pub fn throw_err(&mut self, code : DiagnosticCode, err : &str) -> () {
+        self.program_data.diagnostics.push(Diagnostic::error(code, err, self.current_span.clone()));
     }
 
     pub fn borrow_stack_variable_with_sf_index(&self, stack_frame : usize, variable_name : String) -> Option<&'_ StackVariable> {
@@ -0,0 +1,112 @@
+use std::path::{Path, PathBuf};
+
+use crate::datatypes::{ast_statements::Literal, diagnostic::{Diagnostic, DiagnosticCode, Span}, program_data::ProgramData, token::{Keywords, TokenType}, tokenizer::Tokenizer};
+
+// Every file that is part of the program. Tokens, statements and spans point into it with the
+// index of the file.
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct SourceFile {
+    pub path : PathBuf,
+    // Path shown in diagnostics.
+    pub name : String,
+    pub source : String,
+    // Functions declared in the file are namespaced with it, empty for the root file.
+    pub module : String
+}
+
+impl SourceFile {
+    pub fn new(path : PathBuf, name : String, source : String, module : String) -> Self {
+        return Self { path, name, source, module };
+    }
+}
+
+// Tokenizes the root file, which has to be the first file, and every file imported from it.
+// Each file ends with its own EOF token.
+pub fn tokenize_all_files(program_data : &mut ProgramData) -> () {
+    let mut file = 0;
+
+    while file < program_data.files.len() {
+        let first_token = program_data.tokens.len();
+
+        let mut tokenizer = Tokenizer::new(program_data, file);
+        tokenizer.tokenize_all();
+
+        let mut position = first_token;
+
+        while position + 1 < program_data.tokens.len() {
+            if program_data.tokens.get(position).unwrap().kind == TokenType::Keyword(Keywords::Import) {
+                let path_token = program_data.tokens.get(position + 1).unwrap().clone();
+
+                // The parser reports imports without a path.
+                if let TokenType::Literal(Literal::String(import_path)) = &path_token.kind {
+                    load_import(program_data, file, import_path, Span::from_token(&path_token));
+                }
+            }
+
+            position += 1;
+        }
+
+        file += 1;
+    }
+}
+
+// Adds the imported file to the source map unless it is already part of it.
+pub fn load_import(program_data : &mut ProgramData, importing_file : usize, import_path : &str, span : Span) -> () {
+    let path = resolve_import_path(program_data, importing_file, import_path);
+
+    if let Some(source) = program_data.memory_files.get(&path).cloned() {
+        if !program_data.files.iter().any(|file| file.path == path) {
+            let module = path.file_stem().unwrap().to_string_lossy().to_string();
+
+            program_data.files.push(SourceFile::new(path.clone(), path.display().to_string(), source, module));
+        }
+
+        return;
+    }
+
+    let Ok(canonical_path) = path.canonicalize() else {
+        let mut diagnostic = Diagnostic::error(DiagnosticCode::ImportNotFound, &format!("Imported file not found: {}", import_path), span)
+            .with_note(&format!("looked for {}", path.display()));
+
+        if import_path.starts_with("std::") {
+            diagnostic = diagnostic.with_help("the std-lib is searched next to the compiler and in the directories above it, set BYTE_STD_LIB to use another one");
+        }
+
+        program_data.diagnostics.push(diagnostic);
+
+        return;
+    };
+
+    if program_data.files.iter().any(|file| file.path == canonical_path) {
+        return;
+    }
+
+    let Ok(source) = std::fs::read_to_string(&canonical_path) else {
+        program_data.diagnostics.push(Diagnostic::error(DiagnosticCode::ImportNotFound, &format!("Failed to read imported file: {}", import_path), span));
+
+        return;
+    };
+
+    let module = canonical_path.file_stem().unwrap().to_string_lossy().to_string();
+
+    // Shown relative to the importing file like the root file is shown as given on the command line.
+    let name = if import_path.starts_with("std::") {
+        String::from(import_path)
+    } else {
+        Path::new(&program_data.files.get(importing_file).unwrap().name).parent().unwrap_or(Path::new("")).join(import_path).display().to_string()
+    };
+
+    program_data.files.push(SourceFile::new(canonical_path, name, source, module));
+}
+
+// std:: paths point into the std-lib of the target, other paths are relative to the importing file.
+pub fn resolve_import_path(program_data : &ProgramData, importing_file : usize, import_path : &str) -> PathBuf {
+    if let Some(std_path) = import_path.strip_prefix("std::") {
+        return program_data.target.std_lib_dir().join(std_path);
+    }
+
+    let importing_dir = program_data.files.get(importing_file).unwrap().path.parent().map(Path::to_path_buf).unwrap_or_default();
+
+    return importing_dir.join(import_path);
+}
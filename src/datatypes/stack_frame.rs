@@ -2,13 +2,14 @@ use std::collections::HashMap;
 use std::rc::Rc;
 use std::cell::RefCell;
 
-use crate::datatypes::ast_statements::{CgStatement, Statement, VariableType};
+use crate::datatypes::{ast_statements::{CgStatement, ScopeKind, Statement, VariableType}, diagnostic::Span};
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct StackVariable {
     pub variable_type : VariableType,
     pub variable_size : usize, 
-    pub offset : usize 
+    pub offset : usize,
+    pub span : Span
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -19,15 +20,28 @@ pub struct StackFrame {
     pub cg_statements : Vec<CgStatement>,
     pub children : Vec<usize>,
     pub parent : usize,
-    pub function : String
+    pub function : String,
+    pub kind : ScopeKind,
+    // The frame of an exported function, it also keeps the registers C expects to be preserved.
+    pub exported : bool
 }
 
 impl StackFrame {
-    pub fn new(parent : usize, function_name : String) -> Self {
-        return Self { variables: HashMap::new(), stack_mem_allocated: 0, statements: Vec::new(), cg_statements: Vec::new(), children: Vec::new(), parent: parent, function: function_name }
+    pub fn new(parent : usize, function_name : String, kind : ScopeKind) -> Self {
+        return Self { variables: HashMap::new(), stack_mem_allocated: 0, statements: Vec::new(), cg_statements: Vec::new(), children: Vec::new(), parent: parent, function: function_name, kind, exported: false }
     }
 
     pub fn default(function_name : String) -> Self {
-        Self { variables: HashMap::new(), stack_mem_allocated: 0, statements: Vec::new(), cg_statements: Vec::new(), children: Vec::new(), parent: usize::MAX, function: function_name  }
+        Self { variables: HashMap::new(), stack_mem_allocated: 0, statements: Vec::new(), cg_statements: Vec::new(), children: Vec::new(), parent: usize::MAX, function: function_name, kind: ScopeKind::Function, exported: false }
+    }
+
+    // Only the function frame stores x30, frames inside it just move sp. Exported functions also
+    // store x29 and x19 to x28.
+    pub fn saved_registers_size(&self) -> usize {
+        return match self.kind {
+            ScopeKind::Function if self.exported => 96,
+            ScopeKind::Function => 16,
+            _ => 0
+        };
     }
 }
@@ -0,0 +1,194 @@
+// Everything that differs between the platforms we can emit code for lives here, so the rest of
+// the compiler only has to ask the target instead of hardcoding Darwin conventions.
+
+use std::path::{Path, PathBuf};
+
+use crate::datatypes::ast_statements::GlobalSection;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Target {
+    Aarch64Macos,
+    Aarch64Linux
+}
+
+impl Target {
+    pub fn from_name(name : &str) -> Option<Target> {
+        return match name {
+            "aarch64-macos" | "arm64-macos" => Some(Target::Aarch64Macos),
+            "aarch64-linux" | "arm64-linux" => Some(Target::Aarch64Linux),
+            _ => None
+        };
+    }
+
+    // Target used when the user doesn't pass --target.
+    pub fn host() -> Target {
+        if cfg!(target_os = "linux") {
+            return Target::Aarch64Linux;
+        }
+
+        return Target::Aarch64Macos;
+    }
+
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Target::Aarch64Macos => "aarch64-macos",
+            Target::Aarch64Linux => "aarch64-linux"
+        };
+    }
+
+    // Mach-O prefixes C level symbols with an underscore, ELF doesn't. Namespaced names like
+    // module::name are mangled to module__name.
+    pub fn symbol_name(&self, name : &str) -> String {
+        let mangled_name = name.replace("::", "__");
+
+        return match self {
+            Target::Aarch64Macos => format!("_{}", mangled_name),
+            Target::Aarch64Linux => mangled_name
+        };
+    }
+
+    // Labels that stay local to the object file.
+    pub fn local_label(&self, name : &str) -> String {
+        return match self {
+            Target::Aarch64Macos => format!("L{}", name),
+            Target::Aarch64Linux => format!(".L{}", name)
+        };
+    }
+
+    // Loads the address of a symbol into the register, the page first and then the offset in it.
+    pub fn global_address(&self, register : &str, symbol : &str) -> String {
+        return match self {
+            Target::Aarch64Macos => format!("adrp {}, {}@PAGE\nadd {}, {}, {}@PAGEOFF\n", register, symbol, register, register, symbol),
+            Target::Aarch64Linux => format!("adrp {}, {}\nadd {}, {}, :lo12:{}\n", register, symbol, register, register, symbol)
+        };
+    }
+
+    // Directive that starts the section globals of this kind live in.
+    pub fn section_directive(&self, section : &GlobalSection) -> &'static str {
+        return match (self, section) {
+            (_, GlobalSection::Data) => ".data",
+            (Target::Aarch64Macos, GlobalSection::ReadOnly) => ".const",
+            (Target::Aarch64Linux, GlobalSection::ReadOnly) => ".section .rodata",
+            (_, GlobalSection::Bss) => ".bss"
+        };
+    }
+
+    // Label of a string literal in read only memory.
+    pub fn string_label(&self, index : usize) -> String {
+        return self.local_label(&format!("str{}", index));
+    }
+
+    pub fn entry_symbol(&self) -> String {
+        return match self {
+            Target::Aarch64Macos => self.symbol_name("main"),
+            Target::Aarch64Linux => String::from("_start")
+        };
+    }
+
+    // Code written at the top of the output assembly file. main_return_register is where main
+    // leaves its return value, used as the exit code. Programs linked with the C library exit
+    // through exit, so its buffers are flushed.
+    pub fn assembly_header(&self, main_return_register : Option<&str>, links_libc : bool) -> String {
+        return match self {
+            Target::Aarch64Macos => format!(".global {}\n.align 4\n.text\n", self.entry_symbol()),
+            // Linux has no runtime that calls main and handles its return, so _start calls it
+            // and exits through the syscall itself.
+            Target::Aarch64Linux => {
+                let exit_code = match main_return_register {
+                    Some("x0") => String::new(),
+                    Some(register) => format!("mov x0, {}\n", register),
+                    None => String::from("mov x0, #0\n")
+                };
+
+                let exit = if links_libc { format!("bl {}\n", self.symbol_name("exit")) } else { format!("mov x8, #{}\nsvc #0\n", self.exit_syscall_number()) };
+
+                format!(".global {}\n.align 4\n.text\n{}:\nbl {}\n{}{}", self.entry_symbol(), self.entry_symbol(), self.symbol_name("main"), exit_code, exit)
+            }
+        };
+    }
+
+    // Objects and archives are linked into another program, which brings its own entry point.
+    pub fn library_header(&self) -> String {
+        return String::from(".align 4\n.text\n");
+    }
+
+    // Code written at the end of every output assembly file. Linux linkers warn about an
+    // executable stack unless the object says it doesn't need one.
+    pub fn assembly_footer(&self) -> String {
+        return match self {
+            Target::Aarch64Macos => String::new(),
+            Target::Aarch64Linux => String::from(".section .note.GNU-stack,\"\",@progbits\n")
+        };
+    }
+
+    pub fn exit_syscall_number(&self) -> i64 {
+        return match self {
+            Target::Aarch64Macos => 1,
+            Target::Aarch64Linux => 93
+        };
+    }
+
+    // Directory std:: imports resolve to. BYTE_STD_LIB overrides it, otherwise it is the closest
+    // std-lib directory next to the compiler binary or above it, which finds both an installed
+    // std-lib and the one of the repository for binaries in target/. Without one std-lib in the
+    // working directory is tried.
+    pub fn std_lib_dir(&self) -> PathBuf {
+        let std_lib = match std::env::var("BYTE_STD_LIB") {
+            Ok(std_lib) => PathBuf::from(std_lib),
+            Err(_) => {
+                let current_exe = std::env::current_exe().unwrap_or_default();
+
+                current_exe.ancestors().skip(1).map(|dir| dir.join("std-lib")).find(|dir| dir.is_dir()).unwrap_or(PathBuf::from("std-lib"))
+            }
+        };
+
+        return std_lib.join(self.name());
+    }
+
+    // C compiler used to link programs with the C library, it knows where the library and its
+    // dynamic loader are. BYTE_CC overrides it.
+    pub fn c_compiler(&self) -> String {
+        if let Ok(c_compiler) = std::env::var("BYTE_CC") {
+            return c_compiler;
+        }
+
+        let prefix = self.toolchain_prefix();
+
+        if prefix.is_empty() {
+            return String::from("cc");
+        }
+
+        return format!("{}gcc", prefix);
+    }
+
+    // Prefix for as/ld. Empty when we can use the host tools directly.
+    pub fn toolchain_prefix(&self) -> String {
+        if let Ok(prefix) = std::env::var("BYTE_CROSS_PREFIX") {
+            return prefix;
+        }
+
+        return match self {
+            Target::Aarch64Linux if !(cfg!(target_os = "linux") && cfg!(target_arch = "aarch64")) => String::from("aarch64-linux-gnu-"),
+            _ => String::new()
+        };
+    }
+
+    // Program and args used to run the output binary, None when it runs natively. Binaries linked
+    // with the C library need the aarch64 loader and libraries, qemu finds them through
+    // QEMU_LD_PREFIX or else in the sysroot of the cross toolchain.
+    pub fn runner(&self) -> Option<Vec<String>> {
+        if *self != Target::Aarch64Linux || (cfg!(target_os = "linux") && cfg!(target_arch = "aarch64")) {
+            return None;
+        }
+
+        let mut runner = vec![String::from("qemu-aarch64")];
+        let sysroot = Path::new("/usr/aarch64-linux-gnu");
+
+        if std::env::var_os("QEMU_LD_PREFIX").is_none() && sysroot.is_dir() {
+            runner.push(String::from("-L"));
+            runner.push(sysroot.display().to_string());
+        }
+
+        return Some(runner);
+    }
+}
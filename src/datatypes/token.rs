@@ -5,6 +5,8 @@ use crate::datatypes::ast_statements::{Literal, VariableType};
 #[derive(Debug, PartialEq, Clone)]
 pub struct Token {
     pub kind: TokenType,
+    // Index of the file in ProgramData::files.
+    pub file: usize,
     pub line: usize,
     pub col: usize,
     pub start_pos: usize,
@@ -13,7 +15,18 @@ pub struct Token {
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Keywords {
-    VariableType(VariableType)
+    VariableType(VariableType),
+    Break,
+    Continue,
+    Return,
+    Import,
+    Packed,
+    As,
+    Struct,
+    Static,
+    Const,
+    Extern,
+    Export
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -47,7 +60,12 @@ pub enum BuiltInFunctions {
     Format,
     StackOffset,   
     Branch,
-    BranchLinked
+    BranchLinked,
+    Address,
+    Load,
+    Store,
+    SizeOf,
+    OffsetOf
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -60,10 +78,29 @@ pub enum Punctuations {
     OpenSquareBracket,
     ClosedSquareBracket,
     Comma,
-    Semicolon
+    Arrow,
+    Semicolon,
+    Dot
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Operators {
-    Assignment
+    Assignment,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Percent,
+    Ampersand,
+    Pipe,
+    Caret,
+    Tilde,
+    ShiftLeft,
+    ShiftRight,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual
 }
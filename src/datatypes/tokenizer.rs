@@ -1,8 +1,11 @@
-use crate::datatypes::{ast_statements::{Literal, VariableType}, program_data::ProgramData, token::{BuiltInFunctions, Identifiers, Keywords, MemoryLocations, Operators, Punctuations, Token, TokenType}};
+use crate::datatypes::{ast_statements::{Literal, VariableType}, diagnostic::{Diagnostic, DiagnosticCode, Span}, program_data::ProgramData, token::{BuiltInFunctions, Identifiers, Keywords, MemoryLocations, Operators, Punctuations, Token, TokenType}};
 
 // Tokenzer struct
 pub struct Tokenizer<'a> {
     program_data: &'a mut ProgramData,
+    // Index of the file in the source map and its contents.
+    file: usize,
+    source: String,
     position: usize,
     col: usize,
     line: usize,
@@ -10,8 +13,10 @@ pub struct Tokenizer<'a> {
 
 impl<'a> Tokenizer<'a> {
     // Initialize the tokenizer.
-    pub fn new(program_data: &'a mut ProgramData) -> Self {
-        Self {program_data, position: 0, col: 1, line: 1}
+    pub fn new(program_data: &'a mut ProgramData, file: usize) -> Self {
+        let source = program_data.files.get(file).unwrap().source.clone();
+
+        Self {program_data, file, source, position: 0, col: 1, line: 1}
     }
 
     pub fn tokenize_all(&mut self) -> () {
@@ -20,14 +25,19 @@ impl<'a> Tokenizer<'a> {
 
             match token {
                 Some(tkn) => {
-                    print!(" {:?} ", tkn);
+                    if self.program_data.verbose {
+                        print!(" {:?} ", tkn);
+                    }
 
                     let eof = tkn.kind == TokenType::EOF;
 
                     self.program_data.tokens.push(tkn);
 
                     if eof {
-                        print!("\n");
+                        if self.program_data.verbose {
+                            print!("\n");
+                        }
+
                         return;
                     }
                 },
@@ -39,24 +49,34 @@ impl<'a> Tokenizer<'a> {
     pub fn next_token(&mut self) -> Option<Token> {
         self.skip_whitespace();
 
-        if self.program_data.source_code.len() <= self.position {
-            return Some(Token{kind: TokenType::EOF, col: self.col, line: self.line, start_pos: self.position, end_pos: self.position});
+        if self.source.len() <= self.position {
+            return Some(Token{kind: TokenType::EOF, file: self.file, col: self.col, line: self.line, start_pos: self.position, end_pos: self.position});
         }
 
         let mut res = String::new();
 
         let start_pos = self.position;
+        let start_col = self.col;
+        let start_line = self.line;
 
         match self.current_char() {
-            '\n' | ';' | '(' | ')' | ',' | '[' | ']' | '{' | '}' => {
+            '\n' | ';' | '(' | ')' | ',' | '[' | ']' | '{' | '}' | '.' => {
                 res = String::from(self.current_char());
                 self.advance(1);
             },
             '/' => {
-                if self.char_at_offset(1) == '/' {
+                if self.position + 1 < self.source.len() && self.char_at_offset(1) == '/' {
                     self.advance(2);
                     
                     loop {
+                        if self.position + 1 >= self.source.len() {
+                            self.advance(self.source.len() - self.position);
+                            self.throw_err(Diagnostic::error(DiagnosticCode::UnterminatedComment, "Unterminated comment", Span { file: self.file, line: start_line, col: start_col, start_pos, end_pos: start_pos + 2 })
+                                .with_help("comments are closed with another '//'"));
+
+                            break;
+                        }
+
                         if self.current_char() == '/' && self.char_at_offset(1) == '/' {
                             self.advance(2);
 
@@ -68,21 +88,46 @@ impl<'a> Tokenizer<'a> {
 
                     return None;
                 }
-            }
+
+                res = String::from("/");
+                self.advance(1);
+            },
+            '+' | '-' | '*' | '%' | '&' | '|' | '^' | '~' | '<' | '>' | '=' | '!' => {
+                res = String::from(self.current_char());
+                self.advance(1);
+
+                // Two char operators.
+                if self.position < self.source.len() {
+                    let two_chars = format!("{}{}", res, self.current_char());
+
+                    if matches!(&two_chars as &str, "<<" | ">>" | "==" | "!=" | "<=" | ">=" | "->") {
+                        res = two_chars;
+                        self.advance(1);
+                    }
+                }
+            },
             '"' => {
                 let mut str = String::new();
 
                 self.advance(1);
 
-                while self.position < self.program_data.source_code.len() && self.current_char() != '"' {
+                while self.position < self.source.len() && self.current_char() != '"' {
                     match self.current_char() {
                         '"' => {
                             break;
                         },
                         '\\' => {
                             self.advance(1);
+
+                            if self.position >= self.source.len() {
+                                break;
+                            }
+
                             let new_char = match self.current_char() {
                                 'n' => '\n',
+                                't' => '\t',
+                                '0' => '\0',
+                                '"' => '"',
                                 '\\' => '\\',
                                 _ => ' '
                             };
@@ -98,25 +143,28 @@ impl<'a> Tokenizer<'a> {
                     }
                 };
 
-                self.advance(1);
+                if self.position >= self.source.len() {
+                    self.throw_err(Diagnostic::error(DiagnosticCode::UnterminatedString, "Unterminated string literal", Span { file: self.file, line: start_line, col: start_col, start_pos, end_pos: start_pos + 1 })
+                        .with_label("string starts here"));
+                } else {
+                    self.advance(1);
+                }
 
-                return Some(Token{kind: TokenType::Literal(Literal::String(str)), col: self.col, line: self.line, start_pos, end_pos: self.position});
+                return Some(Token{kind: TokenType::Literal(Literal::String(str)), file: self.file, col: start_col, line: start_line, start_pos, end_pos: self.position});
             },
             _ => {
-                while self.position < self.program_data.source_code.len() && self.current_char().is_whitespace() == false && matches!(self.current_char(), ';' | '(' | ')' | ',' | '[' | ']' | '{' | '}') == false {
+                while self.position < self.source.len() && self.current_char().is_whitespace() == false && matches!(self.current_char(), ';' | '(' | ')' | ',' | '[' | ']' | '{' | '}' | '.' | '+' | '-' | '*' | '/' | '%' | '&' | '|' | '^' | '~' | '<' | '>' | '=' | '!') == false {
                     res.push(self.current_char());
                     self.advance(1);
                 };
             }
         }
 
-        let token_default = Token{kind: TokenType::EOF, col: self.col, line: self.line, start_pos, end_pos: self.position};
+        let token_default = Token{kind: TokenType::EOF, file: self.file, col: start_col, line: start_line, start_pos, end_pos: self.position};
 
         match &res as &str {
-            "\n" => {
-                self.line += 1;
-                self.col = 1;
-            },
+            // Line and col are already updated by advance.
+            "\n" => {},
             "stack_offset" => {
                 return Some(Token{kind: TokenType::BuiltInFunctions(BuiltInFunctions::StackOffset), ..token_default});
             }
@@ -126,8 +174,33 @@ impl<'a> Tokenizer<'a> {
             ":" => {
                 return Some(Token{kind: TokenType::Punctuation(Punctuations::Colon), ..token_default});
             }
-            "=" => {
-                return Some(Token{kind: TokenType::Operator(Operators::Assignment), ..token_default});
+            "=" | "+" | "-" | "*" | "/" | "%" | "&" | "|" | "^" | "~" | "<<" | ">>" | "==" | "!=" | "<" | "<=" | ">" | ">=" => {
+                return Some(Token{kind: TokenType::Operator(
+                    match &res as &str {
+                        "=" => Operators::Assignment,
+                        "+" => Operators::Plus,
+                        "-" => Operators::Minus,
+                        "*" => Operators::Star,
+                        "/" => Operators::Slash,
+                        "%" => Operators::Percent,
+                        "&" => Operators::Ampersand,
+                        "|" => Operators::Pipe,
+                        "^" => Operators::Caret,
+                        "~" => Operators::Tilde,
+                        "<<" => Operators::ShiftLeft,
+                        ">>" => Operators::ShiftRight,
+                        "==" => Operators::Equal,
+                        "!=" => Operators::NotEqual,
+                        "<" => Operators::Less,
+                        "<=" => Operators::LessEqual,
+                        ">" => Operators::Greater,
+                        ">=" => Operators::GreaterEqual,
+                        _ => unreachable!()
+                    }
+                ), ..token_default});
+            },
+            "->" => {
+                return Some(Token{kind: TokenType::Punctuation(Punctuations::Arrow), ..token_default});
             },
             "{" => {
                 return Some(Token{kind: TokenType::Punctuation(Punctuations::OpenBraces), ..token_default});
@@ -150,6 +223,9 @@ impl<'a> Tokenizer<'a> {
             "," => {
                 return Some(Token{kind: TokenType::Punctuation(Punctuations::Comma), ..token_default});
             },
+            "." => {
+                return Some(Token{kind: TokenType::Punctuation(Punctuations::Dot), ..token_default});
+            },
             "bl" => {
                 return Some(Token{kind: TokenType::BuiltInFunctions(BuiltInFunctions::BranchLinked), ..token_default});
             },
@@ -159,6 +235,21 @@ impl<'a> Tokenizer<'a> {
             "reg"=> {
                 return Some(Token{kind: TokenType::MemoryLocation(MemoryLocations::Register), ..token_default});
             },
+            "size_of" => {
+                return Some(Token{kind: TokenType::BuiltInFunctions(BuiltInFunctions::SizeOf), ..token_default});
+            },
+            "offset_of" => {
+                return Some(Token{kind: TokenType::BuiltInFunctions(BuiltInFunctions::OffsetOf), ..token_default});
+            },
+            "addr" => {
+                return Some(Token{kind: TokenType::BuiltInFunctions(BuiltInFunctions::Address), ..token_default});
+            },
+            "load" => {
+                return Some(Token{kind: TokenType::BuiltInFunctions(BuiltInFunctions::Load), ..token_default});
+            },
+            "store" => {
+                return Some(Token{kind: TokenType::BuiltInFunctions(BuiltInFunctions::Store), ..token_default});
+            },
             "asm" => {
                 return Some(Token{kind: TokenType::BuiltInFunctions(BuiltInFunctions::Assembly), ..token_default});
             }
@@ -187,6 +278,39 @@ impl<'a> Tokenizer<'a> {
             "loop" => {
                 return Some(Token{kind: TokenType::BuiltInFunctions(BuiltInFunctions::Loop), ..token_default});
             },
+            "break" => {
+                return Some(Token{kind: TokenType::Keyword(Keywords::Break), ..token_default});
+            },
+            "continue" => {
+                return Some(Token{kind: TokenType::Keyword(Keywords::Continue), ..token_default});
+            },
+            "return" => {
+                return Some(Token{kind: TokenType::Keyword(Keywords::Return), ..token_default});
+            },
+            "import" => {
+                return Some(Token{kind: TokenType::Keyword(Keywords::Import), ..token_default});
+            },
+            "as" => {
+                return Some(Token{kind: TokenType::Keyword(Keywords::As), ..token_default});
+            },
+            "packed" => {
+                return Some(Token{kind: TokenType::Keyword(Keywords::Packed), ..token_default});
+            },
+            "struct" => {
+                return Some(Token{kind: TokenType::Keyword(Keywords::Struct), ..token_default});
+            },
+            "static" => {
+                return Some(Token{kind: TokenType::Keyword(Keywords::Static), ..token_default});
+            },
+            "const" => {
+                return Some(Token{kind: TokenType::Keyword(Keywords::Const), ..token_default});
+            },
+            "extern" => {
+                return Some(Token{kind: TokenType::Keyword(Keywords::Extern), ..token_default});
+            },
+            "export" => {
+                return Some(Token{kind: TokenType::Keyword(Keywords::Export), ..token_default});
+            },
             _ => {
                 match res.parse::<i64>() {
                     Ok(num) => {
@@ -224,9 +348,13 @@ impl<'a> Tokenizer<'a> {
         self.line = new_line;
     }
 
+    pub fn throw_err(&mut self, diagnostic : Diagnostic) -> () {
+        self.program_data.diagnostics.push(diagnostic);
+    }
+
     // Skips whitespace.
     pub fn skip_whitespace(&mut self) {
-        while self.position < self.program_data.source_code.len() && self.current_char().is_whitespace() && self.current_char() != '\n' {
+        while self.position < self.source.len() && self.current_char().is_whitespace() && self.current_char() != '\n' {
             self.col += 1;
             self.position += 1;
         }
@@ -234,10 +362,10 @@ impl<'a> Tokenizer<'a> {
 
     // Get current char of input.
     pub fn current_char(&self) -> char {
-        self.program_data.source_code[self.position..].chars().next().unwrap()
+        self.source[self.position..].chars().next().unwrap()
     }
 
     pub fn char_at_offset(&self, offset : i32) -> char {
-        self.program_data.source_code[((self.position as i32) + offset) as usize..].chars().next().unwrap()
+        self.source[((self.position as i32) + offset) as usize..].chars().next().unwrap()
     }
  }
@@ -0,0 +1,487 @@
+use crate::datatypes::{ast_statements::{BinaryOperation, BinaryOperators, BuiltInFunctionsAst, Cast, Expression, FieldAst, Literal, UnaryOperators, VariableType}, diagnostic::DiagnosticCode, program_data::ProgramData, token::Identifiers};
+
+// Rules for which values can be stored as which integer type, shared by declarations,
+// assignments, returns, call args and compares.
+//
+// A literal fits a type if its value is inside the range of the type. A value of one type can be
+// used as another type without a conversion only if the other type holds every value of it:
+//
+//   widening        i8 -> i16 -> i32 -> i64, u8 -> u16 -> u32 -> u64
+//   unsigned to     u8 -> i16, u16 -> i32, u32 -> i64, and anything wider
+//   signed
+//
+// Narrowing and every other sign change would change the value and are rejected, they have to be
+// written as a cast with value as type.
+//
+// Pointers are only used as pointers to the same type. Adding an integer to a pointer or
+// subtracting one from it moves the pointer by whole pointees. Pointers can be cast to other
+// pointers and to and from the 64 bit integers.
+//
+// Arrays are never used as a whole, only their elements are read and written.
+//
+// Structs are read and written through their fields. A struct variable can be copied as a whole
+// into another variable or stack arg of the same struct, but never converted to anything else.
+// Structs in globals and behind pointers are only used through their fields.
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct TypeError {
+    pub code : DiagnosticCode,
+    pub message : String,
+    pub help : Option<String>
+}
+
+impl TypeError {
+    pub fn new(code : DiagnosticCode, message : &str) -> Self {
+        return Self { code, message: String::from(message), help: None };
+    }
+
+    pub fn with_help(mut self, help : &str) -> Self {
+        self.help = Some(String::from(help));
+
+        return self;
+    }
+}
+
+// Smallest and largest value of the type. u64 values above i64::MAX can't be written as literals.
+pub fn type_range(variable_type : &VariableType) -> (i128, i128) {
+    return match variable_type {
+        VariableType::I8 => (i8::MIN as i128, i8::MAX as i128),
+        VariableType::I16 => (i16::MIN as i128, i16::MAX as i128),
+        VariableType::I32 => (i32::MIN as i128, i32::MAX as i128),
+        VariableType::I64 => (i64::MIN as i128, i64::MAX as i128),
+        VariableType::U8 => (0, u8::MAX as i128),
+        VariableType::U16 => (0, u16::MAX as i128),
+        VariableType::U32 => (0, u32::MAX as i128),
+        VariableType::U64 => (0, u64::MAX as i128),
+        VariableType::Void => (0, 0),
+        VariableType::Pointer(_) => (0, u64::MAX as i128),
+        VariableType::Array(_, _) | VariableType::Struct(_) => (0, 0)
+    };
+}
+
+pub fn check_literal(value : i128, variable_type : &VariableType) -> Result<(), TypeError> {
+    if *variable_type == VariableType::Void {
+        return Err(TypeError::new(DiagnosticCode::TypeMismatch, "A number can't be used as Void"));
+    }
+
+    if variable_type.is_pointer() {
+        return Err(TypeError::new(DiagnosticCode::TypeMismatch, &format!("A number can't be used as {:?}", variable_type))
+            .with_help(&format!("use `{} as {}` to use the number as an address", value, variable_type.name())));
+    }
+
+    let (min, max) = type_range(variable_type);
+
+    if value < min || value > max {
+        return Err(TypeError::new(DiagnosticCode::LiteralOutOfRange, &format!("literal {} is out of range for {:?}", value, variable_type))
+            .with_help(&format!("{:?} holds values from {} to {}", variable_type, min, max)));
+    }
+
+    return Ok(());
+}
+
+// Checks if a value of type from can be used where to is expected without changing it.
+pub fn check_conversion(from : &VariableType, to : &VariableType) -> Result<(), TypeError> {
+    if from == to {
+        return Ok(());
+    }
+
+    if from.is_struct() || to.is_struct() {
+        return Err(TypeError::new(DiagnosticCode::TypeMismatch, &format!("{:?} can't be used as {:?}", from, to)));
+    }
+
+    if *from == VariableType::Void || *to == VariableType::Void {
+        return Err(TypeError::new(DiagnosticCode::TypeMismatch, &format!("{:?} can't be used as {:?}", from, to)));
+    }
+
+    if from.is_pointer() || to.is_pointer() {
+        let reason = if from.is_pointer() && to.is_pointer() {
+            "the pointee types differ"
+        } else {
+            "pointers and numbers are only converted with as"
+        };
+
+        return Err(TypeError::new(DiagnosticCode::TypeMismatch, &format!("{:?} can't be used as {:?} because {}", from, to, reason))
+            .with_help(&format!("use `as {}` to convert it", to.name())));
+    }
+
+    let (from_min, from_max) = type_range(from);
+    let (to_min, to_max) = type_range(to);
+
+    if from_min >= to_min && from_max <= to_max {
+        return Ok(());
+    }
+
+    let reason = if from.is_signed() != to.is_signed() {
+        "the sign changes"
+    } else {
+        "it is narrower"
+    };
+
+    return Err(TypeError::new(DiagnosticCode::TypeMismatch, &format!("{:?} can't be used as {:?} because {}", from, to, reason))
+        .with_help(&format!("only types that hold every value of {:?} are converted implicitly, use `as {}` to convert it", from, to.name())));
+}
+
+// Checks if value as to is a valid cast for a value of type from.
+pub fn check_cast(from : &VariableType, to : &VariableType) -> Result<(), TypeError> {
+    if *to == VariableType::Void {
+        return Err(TypeError::new(DiagnosticCode::InvalidType, "Values can't be cast to void"));
+    }
+
+    if to.is_array() {
+        return Err(TypeError::new(DiagnosticCode::InvalidType, "Values can't be cast to arrays"));
+    }
+
+    if from.is_struct() || to.is_struct() {
+        return Err(TypeError::new(DiagnosticCode::InvalidType, &format!("{:?} can't be cast to {:?}, structs are never cast", from, to)));
+    }
+
+    // Addresses are 64 bits, smaller integers would lose part of them.
+    if from.is_pointer() != to.is_pointer() {
+        let integer_type = if from.is_pointer() { to } else { from };
+
+        if integer_type.get_variable_size() != 8 {
+            return Err(TypeError::new(DiagnosticCode::TypeMismatch, &format!("{:?} can't be cast to {:?}, pointers are only cast to and from u64 and i64", from, to))
+                .with_help("cast the value to u64 first"));
+        }
+    }
+
+    return Ok(());
+}
+
+// Type both values can be converted to, the wider one of the two.
+pub fn common_type(left : &VariableType, right : &VariableType) -> Option<VariableType> {
+    if check_conversion(left, right).is_ok() {
+        return Some(right.clone());
+    }
+
+    if check_conversion(right, left).is_ok() {
+        return Some(left.clone());
+    }
+
+    return None;
+}
+
+// Checks if the expression can be stored as variable_type. Operations are done in variable_type,
+// so every operand has to fit it.
+pub fn check_expression(program_data : &ProgramData, stack_frame : usize, expression : &Expression, variable_type : &VariableType) -> Result<(), TypeError> {
+    if variable_type.is_struct() && !matches!(expression, Expression::Identifier(_) | Expression::Field(_)) {
+        return Err(TypeError::new(DiagnosticCode::TypeMismatch, &format!("{:?} can only be copied from another variable of the struct", variable_type))
+            .with_help("assign its fields one by one instead"));
+    }
+
+    return match expression {
+        Expression::Literal(Literal::Number(num)) => check_literal(*num as i128, variable_type),
+        Expression::Literal(Literal::String(_)) => {
+            check_conversion(&string_type(), variable_type).map_err(|_| {
+                TypeError::new(DiagnosticCode::TypeMismatch, &format!("A string literal can't be used as {:?}", variable_type))
+                    .with_help("a string is a *u8 to its first byte, or fills a u8 array like u8[3] s = \"hi\";")
+            })
+        },
+        Expression::Identifier(Identifiers::Identifier(identifier)) => {
+            let Some(identifier_type) = identifier_type(program_data, stack_frame, identifier) else {
+                return Err(TypeError::new(DiagnosticCode::UnknownVariable, &format!("Variable not found: {}", identifier)));
+            };
+
+            if identifier_type.is_array() {
+                return Err(TypeError::new(DiagnosticCode::InvalidExpression, &format!("Array {} can't be used as a value", identifier))
+                    .with_help(&format!("read an element with {}[index] or get a pointer to the first one with addr({})", identifier, identifier)));
+            }
+
+            if identifier_type.is_struct() && is_global(program_data, stack_frame, identifier) {
+                return Err(TypeError::new(DiagnosticCode::InvalidExpression, &format!("{} is a struct in a global and can't be copied as a whole", identifier))
+                    .with_help("use its fields one by one"));
+            }
+
+            check_conversion(&identifier_type, variable_type)
+        },
+        Expression::Index(index) => {
+            let element_type = element_type(program_data, stack_frame, &index.array)?;
+
+            check_index(program_data, stack_frame, &index.index, &element_type, &index.array)?;
+            check_conversion(&element_type, variable_type)
+        },
+        Expression::Field(field) => {
+            let location = field_location(program_data, stack_frame, field)?;
+
+            check_field_value(&location, field)?;
+            check_conversion(&location.variable_type, variable_type)
+        },
+        Expression::BinaryOperation(binary_operation) => {
+            if binary_operation.operator.is_comparison() {
+                if !variable_type.is_integer() {
+                    return Err(TypeError::new(DiagnosticCode::TypeMismatch, &format!("A comparison can't be used as {:?}", variable_type)));
+                }
+
+                let operand_type = comparison_operand_type(program_data, stack_frame, binary_operation)?;
+
+                if operand_type.is_struct() {
+                    return Err(TypeError::new(DiagnosticCode::TypeMismatch, &format!("{:?} can't be compared", operand_type))
+                        .with_help("compare its fields instead"));
+                }
+
+                check_expression(program_data, stack_frame, &binary_operation.left, &operand_type)?;
+                check_expression(program_data, stack_frame, &binary_operation.right, &operand_type)
+            } else if variable_type.is_pointer() {
+                if !matches!(binary_operation.operator, BinaryOperators::Add | BinaryOperators::Subtract) {
+                    return Err(TypeError::new(DiagnosticCode::TypeMismatch, &format!("Only + and - can be used to get a {:?}", variable_type))
+                        .with_help("cast the pointer to u64 for other operations"));
+                }
+
+                // The pointer is on the left, the number of pointees to move by on the right.
+                check_expression(program_data, stack_frame, &binary_operation.left, variable_type)?;
+                check_expression(program_data, stack_frame, &binary_operation.right, &VariableType::I64)
+            } else {
+                check_expression(program_data, stack_frame, &binary_operation.left, variable_type)?;
+                check_expression(program_data, stack_frame, &binary_operation.right, variable_type)
+            }
+        },
+        Expression::UnaryOperation(unary_operation) => {
+            if variable_type.is_pointer() {
+                return Err(TypeError::new(DiagnosticCode::TypeMismatch, &format!("- and ~ can't be used to get a {:?}", variable_type)));
+            }
+
+            // -128 is a literal of its own, 128 alone doesn't fit an i8.
+            if let (UnaryOperators::Negate, Expression::Literal(Literal::Number(num))) = (&unary_operation.operator, unary_operation.operand.as_ref()) {
+                return check_literal(-(*num as i128), variable_type);
+            }
+
+            check_expression(program_data, stack_frame, &unary_operation.operand, variable_type)
+        },
+        Expression::Cast(cast) => {
+            let source_type = cast_source_type(program_data, stack_frame, cast);
+
+            check_cast(&source_type, &cast.variable_type)?;
+            check_expression(program_data, stack_frame, &cast.value, &source_type)?;
+            check_conversion(&cast.variable_type, variable_type)
+        },
+        Expression::BuiltInFunction(BuiltInFunctionsAst::Address(identifier)) => {
+            check_conversion(&address_type(program_data, stack_frame, identifier)?, variable_type)
+        },
+        Expression::BuiltInFunction(BuiltInFunctionsAst::Load(pointer)) => {
+            let pointer_type = load_pointer_type(program_data, stack_frame, pointer)?;
+
+            check_expression(program_data, stack_frame, pointer, &pointer_type)?;
+            check_conversion(pointer_type.pointee().unwrap(), variable_type)
+        },
+        Expression::BuiltInFunction(size_of @ (BuiltInFunctionsAst::SizeOf(_) | BuiltInFunctionsAst::SizeOfString(_) | BuiltInFunctionsAst::OffsetOf(_, _))) => {
+            match size_of.parse(program_data, stack_frame) {
+                Ok(Literal::Number(size)) => check_literal(size as i128, variable_type),
+                Ok(_) => unreachable!(),
+                Err(err) => Err(TypeError::new(DiagnosticCode::InvalidBuiltInArgs, &err))
+            }
+        },
+        // Checked against the return type of the function when the call is converted.
+        Expression::BuiltInFunction(BuiltInFunctionsAst::BranchLinked(_)) => Ok(()),
+        _ => Err(TypeError::new(DiagnosticCode::InvalidExpression, "Invalid Expression"))
+    };
+}
+
+// Both operands of a comparison are converted to the wider of their types, i64 if both sides
+// are literals.
+pub fn comparison_operand_type(program_data : &ProgramData, stack_frame : usize, binary_operation : &BinaryOperation) -> Result<VariableType, TypeError> {
+    let left = infer_expression_type(program_data, stack_frame, &binary_operation.left);
+    let right = infer_expression_type(program_data, stack_frame, &binary_operation.right);
+
+    return match (left, right) {
+        (Some(left), Some(right)) => common_type(&left, &right).ok_or(
+            TypeError::new(DiagnosticCode::TypeMismatch, &format!("{:?} and {:?} can't be compared", left, right))
+                .with_help("neither type holds every value of the other")
+        ),
+        (Some(operand_type), None) | (None, Some(operand_type)) => Ok(operand_type),
+        (None, None) => Ok(VariableType::I64)
+    };
+}
+
+// Type of addr(identifier), only variables and stack args have an address.
+pub fn address_type(program_data : &ProgramData, stack_frame : usize, identifier : &str) -> Result<VariableType, TypeError> {
+    if let Some((register, _)) = program_data.get_function_register_arg(stack_frame, identifier) {
+        if program_data.get_stack_variable_ref(stack_frame, identifier, 0).is_none() {
+            return Err(TypeError::new(DiagnosticCode::InvalidExpression, &format!("{} is passed in {} and has no address", identifier, register))
+                .with_help("copy it into a variable first"));
+        }
+    }
+
+    let Some(identifier_type) = identifier_type(program_data, stack_frame, identifier) else {
+        return Err(TypeError::new(DiagnosticCode::UnknownVariable, &format!("Variable not found: {}", identifier)));
+    };
+
+    // Arrays give a pointer to their first element.
+    return match identifier_type {
+        VariableType::Array(element_type, _) => Ok(VariableType::Pointer(element_type)),
+        _ => Ok(VariableType::Pointer(Box::new(identifier_type)))
+    };
+}
+
+// Type of the elements of the array called name.
+pub fn element_type(program_data : &ProgramData, stack_frame : usize, array : &FieldAst) -> Result<VariableType, TypeError> {
+    return match array_type(program_data, stack_frame, array)? {
+        VariableType::Array(element_type, _) => Ok(*element_type),
+        other_type => Err(TypeError::new(DiagnosticCode::TypeMismatch, &format!("{} is not an array, it is {:?}", array.path(), other_type)))
+    };
+}
+
+// Type of the variable or field that is indexed.
+fn array_type(program_data : &ProgramData, stack_frame : usize, array : &FieldAst) -> Result<VariableType, TypeError> {
+    if !array.fields.is_empty() {
+        return Ok(field_location(program_data, stack_frame, array)?.variable_type);
+    }
+
+    return identifier_type(program_data, stack_frame, &array.name).ok_or_else(|| TypeError::new(DiagnosticCode::UnknownVariable, &format!("Variable not found: {}", array.name)));
+}
+
+// Type of a field and where it is, relative to the variable or to the struct the variable points to.
+#[derive(Debug, PartialEq, Clone)]
+pub struct FieldLocation {
+    pub variable_type : VariableType,
+    // Bytes from the start of the outermost struct.
+    pub offset : usize,
+    pub through_pointer : bool,
+    // The struct is a global instead of a variable or stack arg.
+    pub global : bool
+}
+
+// Fields are read from struct variables and stack args, or from the struct a pointer variable
+// points to.
+pub fn field_location(program_data : &ProgramData, stack_frame : usize, field : &FieldAst) -> Result<FieldLocation, TypeError> {
+    let Some(variable_type) = identifier_type(program_data, stack_frame, &field.name) else {
+        return Err(TypeError::new(DiagnosticCode::UnknownVariable, &format!("Variable not found: {}", field.name)));
+    };
+
+    let (mut current_type, through_pointer) = match variable_type {
+        VariableType::Pointer(pointee) if pointee.is_struct() => (*pointee, true),
+        other_type => (other_type, false)
+    };
+
+    let mut offset = 0;
+    let mut path = field.name.clone();
+
+    for field_name in field.fields.iter() {
+        let VariableType::Struct(struct_type) = &current_type else {
+            return Err(TypeError::new(DiagnosticCode::TypeMismatch, &format!("{} is not a struct, it is {:?}", path, current_type)));
+        };
+
+        let struct_definition = program_data.structs.get(&struct_type.name).unwrap();
+
+        let Some(struct_field) = struct_definition.field(field_name) else {
+            let field_names : Vec<&str> = struct_definition.fields.iter().map(|field| field.name.as_str()).collect();
+
+            return Err(TypeError::new(DiagnosticCode::UnknownVariable, &format!("Struct {} has no field {}", struct_type.name, field_name))
+                .with_help(&format!("its fields are {}", field_names.join(", "))));
+        };
+
+        offset += struct_field.offset;
+        current_type = struct_field.variable_type.clone();
+
+        path.push('.');
+        path.push_str(field_name);
+    }
+
+    return Ok(FieldLocation{variable_type: current_type, offset, through_pointer, global: !through_pointer && is_global(program_data, stack_frame, &field.name)});
+}
+
+// Fields are used as values on their own, except arrays and structs behind a pointer.
+pub fn check_field_value(location : &FieldLocation, field : &FieldAst) -> Result<(), TypeError> {
+    if location.variable_type.is_array() {
+        return Err(TypeError::new(DiagnosticCode::InvalidExpression, &format!("Array field {} can't be used as a value", field.path())));
+    }
+
+    if (location.through_pointer || location.global) && location.variable_type.is_struct() {
+        let place = if location.global { "in a global" } else { "behind a pointer" };
+
+        return Err(TypeError::new(DiagnosticCode::InvalidExpression, &format!("{} is a struct {} and can't be copied as a whole", field.path(), place))
+            .with_help("use its fields one by one"));
+    }
+
+    return Ok(());
+}
+
+// Indices are i64, constant ones have to be inside the array.
+pub fn check_index(program_data : &ProgramData, stack_frame : usize, index : &Expression, element_type : &VariableType, array : &FieldAst) -> Result<(), TypeError> {
+    let length = match array_type(program_data, stack_frame, array) {
+        Ok(VariableType::Array(_, length)) => length,
+        _ => unreachable!()
+    };
+    let name = array.path();
+
+    if let Expression::Literal(Literal::Number(num)) = index {
+        if *num < 0 || *num as usize >= length {
+            return Err(TypeError::new(DiagnosticCode::IndexOutOfBounds, &format!("index {} is out of bounds for {} of type {}[{}]", num, name, element_type.name(), length))
+                .with_help(&format!("{} has elements 0 to {}", name, length - 1)));
+        }
+    }
+
+    return check_expression(program_data, stack_frame, index, &VariableType::I64);
+}
+
+// Type of the pointer given to load or store.
+pub fn load_pointer_type(program_data : &ProgramData, stack_frame : usize, pointer : &Expression) -> Result<VariableType, TypeError> {
+    return match infer_expression_type(program_data, stack_frame, pointer) {
+        Some(pointer_type) if pointer_type.is_pointer() => Ok(pointer_type),
+        Some(other_type) => Err(TypeError::new(DiagnosticCode::TypeMismatch, &format!("Expected a pointer, found {:?}", other_type))),
+        None => Err(TypeError::new(DiagnosticCode::TypeMismatch, "Expected a pointer, found a number")
+            .with_help("use `address as *u8` to use a number as an address"))
+    };
+}
+
+// Type the value of a cast is evaluated as before it is converted, i64 for literals.
+pub fn cast_source_type(program_data : &ProgramData, stack_frame : usize, cast : &Cast) -> VariableType {
+    return infer_expression_type(program_data, stack_frame, &cast.value).unwrap_or(VariableType::I64);
+}
+
+// Widest type of the variables and casts in the expression, None if it only contains literals.
+pub fn infer_expression_type(program_data : &ProgramData, stack_frame : usize, expression : &Expression) -> Option<VariableType> {
+    return match expression {
+        Expression::Identifier(Identifiers::Identifier(identifier)) => identifier_type(program_data, stack_frame, identifier),
+        Expression::Cast(cast) => Some(cast.variable_type.clone()),
+        Expression::Literal(Literal::String(_)) => Some(string_type()),
+        Expression::BinaryOperation(binary_operation) => {
+            if binary_operation.operator.is_comparison() {
+                None
+            } else {
+                let left = infer_expression_type(program_data, stack_frame, &binary_operation.left);
+                let right = infer_expression_type(program_data, stack_frame, &binary_operation.right);
+
+                match (left, right) {
+                    // Without a common type the left one is reported by check_expression.
+                    (Some(left), Some(right)) => Some(common_type(&left, &right).unwrap_or(left)),
+                    (left, right) => left.or(right)
+                }
+            }
+        },
+        Expression::UnaryOperation(unary_operation) => infer_expression_type(program_data, stack_frame, &unary_operation.operand),
+        Expression::BuiltInFunction(BuiltInFunctionsAst::Address(identifier)) => address_type(program_data, stack_frame, identifier).ok(),
+        Expression::Index(index) => element_type(program_data, stack_frame, &index.array).ok(),
+        Expression::Field(field) => field_location(program_data, stack_frame, field).ok().map(|location| location.variable_type),
+        Expression::BuiltInFunction(BuiltInFunctionsAst::Load(pointer)) => {
+            infer_expression_type(program_data, stack_frame, pointer).and_then(|pointer_type| pointer_type.pointee().cloned())
+        },
+        _ => None
+    };
+}
+
+// String literals are used through a pointer to their first byte.
+pub fn string_type() -> VariableType {
+    return VariableType::Pointer(Box::new(VariableType::U8));
+}
+
+pub fn identifier_type(program_data : &ProgramData, stack_frame : usize, identifier : &str) -> Option<VariableType> {
+    if let Some(var_ref) = program_data.get_stack_variable_ref(stack_frame, identifier, 0) {
+        return Some(var_ref.var.variable_type);
+    } else if let Some(func_arg) = program_data.get_function_stack_arg_ref(stack_frame, identifier) {
+        return Some(func_arg.var.arg_var_type);
+    } else if let Some((_, func_arg)) = program_data.get_function_register_arg(stack_frame, identifier) {
+        return Some(func_arg.arg_var_type);
+    } else if let Some((_, global)) = program_data.get_global_ref(stack_frame, identifier) {
+        return Some(global.variable_type);
+    }
+
+    return None;
+}
+
+// Globals are found after variables and args, scope analysis makes sure none of them share a name.
+pub fn is_global(program_data : &ProgramData, stack_frame : usize, identifier : &str) -> bool {
+    return program_data.get_stack_variable_ref(stack_frame, identifier, 0).is_none()
+        && program_data.get_function_stack_arg_ref(stack_frame, identifier).is_none()
+        && program_data.get_function_register_arg(stack_frame, identifier).is_none()
+        && program_data.get_global_ref(stack_frame, identifier).is_some();
+}
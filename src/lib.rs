@@ -0,0 +1,8 @@
+// The compiler as a library, the byte-lang binary and the tests are built on top of it.
+
+pub mod compile_asm;
+pub mod compiler;
+pub mod datatypes;
+pub mod manifest;
+
+pub use compiler::{compile_file, compile_source, Artifacts, CompileErrors, Options, Phase, Profile};
@@ -1,54 +1,79 @@
 use std::collections::HashMap;
 use std::io::{BufWriter, Write};
 use std::path::PathBuf;
-use std::process::Command;
-use std::{fs::File, io::Read};
+use std::process;
+use std::fs::File;
 use unzip::Unzipper;
 
-mod compile_asm;
-mod datatypes;
+mod cli;
 
-use compile_asm::compile_asm;
-use datatypes::semantic_analysis::SemanticAnaytis;
-use datatypes::tokenizer::Tokenizer;
-use datatypes::parser::Parser;
-use datatypes::scope_analysis::ScopeAnalysis;
-use datatypes::code_generator::CodeGenerator;
-
-use crate::datatypes::program_data::ProgramData;
+use cli::{usage, CliArgs, Command, Emit, Verbosity};
+use language::compile_asm::{assemble, build_library, compile_asm, LinkOptions};
+use language::compiler::{self, Artifacts, Options, Phase};
+use language::datatypes::target::Target;
+use language::manifest::{Manifest, MANIFEST_FILE_NAME};
 
 fn main() {
     let start = std::time::Instant::now();
 
-    let command = std::env::args().nth(1).expect("Please Provide a command");
-    match command.as_str() {
-        "run" => {
-            run_file();
+    let args : Vec<String> = std::env::args().skip(1).collect();
+
+    let cli_args = match CliArgs::parse(&args) {
+        Ok(cli_args) => cli_args,
+        Err(err) => {
+            eprintln!("error: {}\n\nRun byte-lang --help for usage", err);
+
+            std::process::exit(1);
+        }
+    };
+
+    if cli_args.help {
+        print!("{}", usage(cli_args.command));
+
+        return;
+    }
+
+    let Some(command) = cli_args.command else {
+        eprint!("{}", usage(None));
+
+        std::process::exit(1);
+    };
+
+    match command {
+        Command::Run => {
+            run_file(&cli_args);
         },
-        "build" => {
-            build_file();            
+        Command::Build => {
+            build_file(&cli_args);
         },
-        "init" => {
-            init_command();
+        Command::Check => {
+            compile_file(&cli_args);
         },
-        "install" => {
-            install_dependency();
+        Command::Init => {
+            init_command(&cli_args);
         },
-        _ => {}
+        Command::Install => {
+            install_dependency(&cli_args);
+        }
     };
 
-    println!("{:?}", start.elapsed());
+    if cli_args.verbosity == Verbosity::Verbose {
+        println!("{:?}", start.elapsed());
+    }
 }
 
 // This function was used like 50 commits ago and is useless right now. Keeping it in case I want
 // to add back dependencies
-fn install_dependency() {
-    let dependency_name = std::env::args().nth(2).unwrap();
+fn install_dependency(cli_args : &CliArgs) {
+    let Some(dependency_name) = cli_args.positional.first().cloned() else {
+        exit_with_usage(cli_args);
+    };
+
     let project_dir = get_project_folder().unwrap();
     let dependencies_dir = project_dir.join("dependencies");
 
-    let version : String = match std::env::args().nth(3) {
-        Some(version) => version,
+    let version : String = match cli_args.positional.get(1) {
+        Some(version) => version.clone(),
         None => String::new()
     };
 
@@ -106,7 +131,7 @@ fn get_project_folder() -> Result<PathBuf, String> {
     let mut dir = std::env::current_dir().unwrap();
 
     loop {
-        let config_file = dir.join("byte-config.json");
+        let config_file = dir.join(MANIFEST_FILE_NAME);
         if config_file.exists() {
             return Ok(dir);
         } else {
@@ -119,29 +144,35 @@ fn get_project_folder() -> Result<PathBuf, String> {
     }
 }
 
-fn build_file() {
-    compile_file();
-    println!("App Compiled \n \n \n--------------------------------------------------------------\n \n \n")
+fn build_file(cli_args : &CliArgs) {
+    compile_file(cli_args);
+
+    // Dumps can be printed to stdout, nothing else should be mixed into them.
+    if cli_args.verbosity >= Verbosity::Normal && matches!(cli_args.emit, Emit::Asm | Emit::Obj | Emit::Exe) {
+        println!("App Compiled \n \n \n--------------------------------------------------------------\n \n \n")
+    }
 }
 
 // This command creates a project directory with json file.
 // This is useless now but will be used in the future for importing files and dependencies
-fn init_command() {
-    let project_name = std::env::args().nth(2).expect("Please provide project name");
+fn init_command(cli_args : &CliArgs) {
+    let Some(project_name) = cli_args.positional.first().cloned() else {
+        exit_with_usage(cli_args);
+    };
 
     let dir = format!("{}/{}", std::env::current_dir().unwrap().to_str().unwrap(), project_name);
 
-    Command::new("mkdir")
+    process::Command::new("mkdir")
         .arg(dir.clone())
         .status()
         .unwrap();
 
-    Command::new("mkdir")
+    process::Command::new("mkdir")
         .arg(format!("{}/dependencies", dir.clone()))
         .status()
         .unwrap();
 
-    let project_config_location = format!("{}/byte-config.json", dir);
+    let project_config_location = format!("{}/{}", dir, MANIFEST_FILE_NAME);
 
     let project_config_file = File::create(project_config_location).unwrap();
 
@@ -170,88 +201,219 @@ term;"#).unwrap();
     config_writer.flush().unwrap();
 }
 
-fn compile_file() {
-    // Getting second arg that should provide location of file that they want to run.
-    let file_location = std::env::args()
-        .nth(2)
-        .expect("Please Provide File Location");
+// Where a build reads its source from and writes its artifacts to.
+struct BuildConfig {
+    root_file : PathBuf,
+    // Root file as shown in diagnostics.
+    root_file_name : String,
+    target : Target,
+    assembly_file : PathBuf,
+    object_file : PathBuf,
+    executable_file : PathBuf,
+    // Static library built with --lib.
+    library_file : PathBuf
+}
+
+// Prints the usage of the command and exits, for missing arguments.
+fn exit_with_usage(cli_args : &CliArgs) -> ! {
+    eprint!("{}", usage(cli_args.command));
+
+    std::process::exit(1);
+}
+
+// A file given on the command line is built into output.s and output in the current directory,
+// without one the project around the current directory is built as described by its manifest.
+fn get_build_config(cli_args : &CliArgs) -> BuildConfig {
+    let target_arg = cli_args.target;
+
+    let mut build_config = if let Some(file_location) = cli_args.file().cloned() {
+        // Get the path that user is in when running the run command!
+        let current_dir = std::env::current_dir().expect("Error getting current Path");
+
+        BuildConfig {
+            root_file: PathBuf::from(&file_location),
+            root_file_name: file_location,
+            target: target_arg.unwrap_or(Target::host()),
+            assembly_file: current_dir.join("output.s"),
+            object_file: current_dir.join("output.o"),
+            executable_file: current_dir.join("output"),
+            library_file: current_dir.join("liboutput.a")
+        }
+    } else {
+        get_project_build_config(target_arg)
+    };
+
+    // -o names the last artifact, the ones before it are placed next to it.
+    if let Some(output) = cli_args.output.clone() {
+        match cli_args.emit {
+            Emit::Asm => build_config.assembly_file = output,
+            Emit::Obj => {
+                build_config.assembly_file = output.with_extension("s");
+                build_config.object_file = output;
+            },
+            Emit::Exe if cli_args.lib => {
+                build_config.assembly_file = output.with_extension("s");
+                build_config.object_file = output.with_extension("o");
+                build_config.library_file = output;
+            },
+            Emit::Exe => {
+                build_config.assembly_file = output.with_extension("s");
+                build_config.object_file = output.with_extension("o");
+                build_config.executable_file = output;
+            },
+            // Written by write_dump.
+            Emit::Tokens | Emit::Ast | Emit::Ir | Emit::Layout | Emit::Header => {}
+        }
+    }
+
+    return build_config;
+}
+
+fn get_project_build_config(target_arg : Option<Target>) -> BuildConfig {
+    let project_dir = match get_project_folder() {
+        Ok(project_dir) => project_dir,
+        Err(_) => {
+            eprintln!("error: No file given and no {} found in this directory or its parents", MANIFEST_FILE_NAME);
 
-    // Open the file.
-    let mut file = File::open(file_location).expect("Error Oppening File");
+            std::process::exit(1);
+        }
+    };
 
-    let mut program_data = ProgramData::new();
+    let manifest = match Manifest::read(&project_dir) {
+        Ok(manifest) => manifest,
+        Err(errors) => {
+            for error in errors.iter() {
+                eprintln!("error: {}", error);
+            }
 
-    file.read_to_string(&mut program_data.source_code).expect("Error Reading As String");
+            eprintln!("  --> {}", project_dir.join(MANIFEST_FILE_NAME).display());
 
-    // Get the path that user is in when running the run command!
-    let current_dir = std::env::current_dir().expect("Error getting current Path");
+            std::process::exit(1);
+        }
+    };
 
-    // Create a file that will contain output assembly code.
-    let mut new_file_location = PathBuf::from(current_dir.clone());
-    new_file_location.push("output.s");
-    let created_file = File::create(new_file_location.clone()).expect("Error creating File");
+    let out_dir = project_dir.join(&manifest.out_dir);
 
-    // Create a writer for assembly code.
-    let mut writer = BufWriter::new(created_file);
+    std::fs::create_dir_all(&out_dir).expect("Error creating output directory");
 
-    // Compile Code.
-    //as -o output.o output.s
-    //ld -macos_version_min 11.0.0 -o output output.o -lSystem -syslibroot `xcrun -sdk macosx --show-sdk-path` -e _main -arch arm64
+    return BuildConfig {
+        root_file: project_dir.join(&manifest.root),
+        root_file_name: manifest.root.display().to_string(),
+        target: target_arg.or(manifest.target).unwrap_or(Target::host()),
+        assembly_file: out_dir.join(format!("{}.s", manifest.name)),
+        object_file: out_dir.join(format!("{}.o", manifest.name)),
+        executable_file: out_dir.join(&manifest.name),
+        library_file: out_dir.join(format!("lib{}.a", manifest.name))
+    };
+}
 
-    // Create _main function.
-    write!(writer, ".global _main\n.align 4\n.text\n").expect("Error Writing File");
+// Runs the phases needed for the command, check stops after semantic analysis and build after
+// the artifact given with --emit.
+fn compile_file(cli_args : &CliArgs) -> BuildConfig {
+    let build_config = get_build_config(cli_args);
+
+    let emit = if cli_args.command == Some(Command::Check) { None } else { Some(cli_args.emit) };
+
+    let mut options = Options::new(&build_config.root_file_name);
+    options.target = build_config.target;
+    options.profile = cli_args.profile;
+    options.verbose = cli_args.verbosity == Verbosity::Verbose;
+    // Objects and libraries are linked by whoever uses them, they get no entry point.
+    options.library = cli_args.lib || emit == Some(Emit::Obj);
+    options.stop_after = match emit {
+        Some(Emit::Tokens) => Phase::Tokens,
+        Some(Emit::Ast) => Phase::Ast,
+        Some(Emit::Ir) | Some(Emit::Layout) | Some(Emit::Header) | None => Phase::Ir,
+        Some(Emit::Asm) | Some(Emit::Obj) | Some(Emit::Exe) => Phase::Asm
+    };
 
-    let mut tokenizer = Tokenizer::new(&mut program_data);
-    tokenizer.tokenize_all();
+    let artifacts = match compiler::compile_file(&build_config.root_file, &options) {
+        Ok(artifacts) => artifacts,
+        Err(errors) => {
+            eprint!("{}", errors.render());
 
-    let mut parser = Parser::new(&mut program_data);
-    parser.parse_all();
+            std::process::exit(1);
+        }
+    };
 
-    if program_data.errors.len() > 0 {
-        panic!("Errors: {:?}\n", program_data.errors);
-    }
+    let Some(emit) = emit else {
+        print_warnings(cli_args, &artifacts);
 
-    let mut scope_analysis = ScopeAnalysis::new(&mut program_data);
-    scope_analysis.process_all();
+        if cli_args.verbosity >= Verbosity::Normal {
+            println!("No errors found in {}", build_config.root_file_name);
+        }
 
-    if program_data.errors.len() > 0 {
-        panic!("Errors: {:?}\n", program_data.errors);
-    }
+        return build_config;
+    };
 
-    print!("Functions: {:?}\nStack Frames: {:?}\n", program_data.functions, program_data.stack_frames);
+    match emit {
+        Emit::Tokens => write_dump(cli_args, &artifacts, &artifacts.token_dump()),
+        Emit::Ast => write_dump(cli_args, &artifacts, &artifacts.ast_dump()),
+        Emit::Ir => write_dump(cli_args, &artifacts, &artifacts.ir),
+        Emit::Layout => write_dump(cli_args, &artifacts, &artifacts.layout),
+        Emit::Header => write_dump(cli_args, &artifacts, &artifacts.header),
+        Emit::Asm | Emit::Obj | Emit::Exe => {
+            std::fs::write(&build_config.assembly_file, &artifacts.assembly).unwrap_or_else(|err| exit_with_error(&format!("Failed to write {}: {}", build_config.assembly_file.display(), err)));
+
+            // Only warnings are left at this point.
+            print_warnings(cli_args, &artifacts);
+
+            let link_options = LinkOptions { libc: artifacts.links_libc, libraries: cli_args.libraries.clone(), library_dirs: cli_args.library_dirs.clone() };
+
+            // Compile the assembly file.
+            let result = match emit {
+                Emit::Obj => assemble(&build_config.assembly_file, &build_config.object_file, build_config.target),
+                Emit::Exe if cli_args.lib => build_library(&build_config.assembly_file, &build_config.library_file, build_config.target),
+                Emit::Exe => compile_asm(&build_config.assembly_file, &build_config.executable_file, build_config.target, &link_options),
+                _ => Ok(())
+            };
+
+            if let Err(err) = result {
+                exit_with_error(&err);
+            }
+        }
+    }
 
-    let mut semantic_analysis = SemanticAnaytis::new(&mut program_data);
-    semantic_analysis.process_all_functions();
+    return build_config;
+}
 
-    if program_data.errors.len() > 0 {
-        panic!("Errors: {:?}\n", program_data.errors);
+// Tokens, AST, IR, layout and the header are written to -o or printed.
+fn write_dump(cli_args : &CliArgs, artifacts : &Artifacts, dump : &str) {
+    match &cli_args.output {
+        Some(output) => std::fs::write(output, dump).unwrap_or_else(|err| exit_with_error(&format!("Failed to write {}: {}", output.display(), err))),
+        None => print!("{}", dump)
     }
 
-    let mut code_generator = CodeGenerator::new(&mut program_data);
-    let compiled_code = code_generator.process_all_functions();
+    print_warnings(cli_args, artifacts);
+}
+
+fn print_warnings(cli_args : &CliArgs, artifacts : &Artifacts) {
+    if cli_args.verbosity >= Verbosity::Normal {
+        eprint!("{}", artifacts.render_diagnostics());
+    }
+}
 
-    write!(writer, "{}", compiled_code).unwrap();
-       
-    // Save the file with new content.
-    writer.flush().expect("Err Flushing To File");
+fn exit_with_error(error : &str) -> ! {
+    eprintln!("error: {}", error);
 
-    // Compile the assembly file.
-    compile_asm(current_dir);
+    std::process::exit(1);
 }
 
-fn run_file() {
-    compile_file();
+fn run_file(cli_args : &CliArgs) {
+    let build_config = compile_file(cli_args);
 
-    println!("Starting App \n \n \n--------------------------------------------------------------\n \n \n");
+    if cli_args.verbosity >= Verbosity::Normal {
+        println!("Starting App \n \n \n--------------------------------------------------------------\n \n \n");
+    }
 
-    // Run the app.
-    let status = Command::new("./output")
-        .status()
-        .expect("error executing command");
+    // Run the app, through an emulator if the host can't execute it.
+    let status = match build_config.target.runner() {
+        Some(runner) => process::Command::new(&runner[0]).args(&runner[1..]).arg(&build_config.executable_file).status(),
+        None => process::Command::new(&build_config.executable_file).status()
+    }.unwrap_or_else(|err| exit_with_error(&format!("Failed to run {}: {}", build_config.executable_file.display(), err)));
 
-    // Return if failed to run.
+    // Exit with the exit code of the program.
     if status.success() == false {
-        println!("error running program");
-        return;
+        std::process::exit(status.code().unwrap_or(1));
     }
 }
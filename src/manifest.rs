@@ -0,0 +1,124 @@
+use std::path::{Path, PathBuf};
+
+use serde_json::{Map, Value};
+
+use crate::datatypes::target::Target;
+
+// byte-config.json at the root of a project, written by init and read by build and run when no
+// file is given.
+//
+// {
+//     "name": "project",
+//     "root": "main.byte",
+//     "target": "aarch64-linux",
+//     "out_dir": "target"
+// }
+
+pub const MANIFEST_FILE_NAME : &str = "byte-config.json";
+
+const MANIFEST_KEYS : [&str; 4] = ["name", "root", "target", "out_dir"];
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Manifest {
+    pub name : String,
+    // Root file, relative to the project directory.
+    pub root : PathBuf,
+    // None builds for the host, --target still overrides it.
+    pub target : Option<Target>,
+    // Artifacts are written here, relative to the project directory.
+    pub out_dir : PathBuf
+}
+
+impl Manifest {
+    // Reads and validates the manifest, every problem found is returned instead of only the first.
+    pub fn read(project_dir : &Path) -> Result<Self, Vec<String>> {
+        let manifest_path = project_dir.join(MANIFEST_FILE_NAME);
+
+        let contents = match std::fs::read_to_string(&manifest_path) {
+            Ok(contents) => contents,
+            Err(err) => return Err(vec![format!("Failed to read {}: {}", manifest_path.display(), err)])
+        };
+
+        let json : Value = match serde_json::from_str(&contents) {
+            Ok(json) => json,
+            Err(err) => return Err(vec![format!("{} is not valid JSON: {}", MANIFEST_FILE_NAME, err)])
+        };
+
+        let Value::Object(fields) = json else {
+            return Err(vec![format!("{} must contain a JSON object", MANIFEST_FILE_NAME)]);
+        };
+
+        let mut errors : Vec<String> = Vec::new();
+
+        for key in fields.keys() {
+            if !MANIFEST_KEYS.contains(&key.as_str()) {
+                errors.push(format!("Unknown key \"{}\" in {}, expected one of {}", key, MANIFEST_FILE_NAME, MANIFEST_KEYS.join(", ")));
+            }
+        }
+
+        let name = required_string(&fields, "name", &mut errors);
+
+        if let Some(name) = &name {
+            if name.is_empty() || !name.chars().all(|character| character.is_ascii_alphanumeric() || character == '_' || character == '-') {
+                errors.push(format!("\"name\" must only contain letters, digits, '_' and '-', found \"{}\"", name));
+            }
+        }
+
+        let root = required_string(&fields, "root", &mut errors);
+
+        if let Some(root) = &root {
+            if !root.ends_with(".byte") {
+                errors.push(format!("\"root\" must point to a .byte file, found \"{}\"", root));
+            } else if !project_dir.join(root).is_file() {
+                errors.push(format!("\"root\" file {} doesn't exist", project_dir.join(root).display()));
+            }
+        }
+
+        let target = match optional_string(&fields, "target", &mut errors) {
+            Some(target_name) => {
+                let target = Target::from_name(&target_name);
+
+                if target.is_none() {
+                    errors.push(format!("Unknown \"target\" \"{}\", expected aarch64-macos or aarch64-linux", target_name));
+                }
+
+                target
+            },
+            None => None
+        };
+
+        let out_dir = optional_string(&fields, "out_dir", &mut errors).unwrap_or(String::from("target"));
+
+        if out_dir.is_empty() {
+            errors.push(String::from("\"out_dir\" can't be empty"));
+        }
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        return Ok(Self { name: name.unwrap(), root: PathBuf::from(root.unwrap()), target, out_dir: PathBuf::from(out_dir) });
+    }
+}
+
+fn required_string(fields : &Map<String, Value>, key : &str, errors : &mut Vec<String>) -> Option<String> {
+    if !fields.contains_key(key) {
+        errors.push(format!("Missing \"{}\" in {}", key, MANIFEST_FILE_NAME));
+
+        return None;
+    }
+
+    return optional_string(fields, key, errors);
+}
+
+fn optional_string(fields : &Map<String, Value>, key : &str, errors : &mut Vec<String>) -> Option<String> {
+    return match fields.get(key) {
+        Some(Value::String(value)) => Some(value.clone()),
+        Some(_) => {
+            errors.push(format!("\"{}\" in {} must be a string", key, MANIFEST_FILE_NAME));
+
+            None
+        },
+        None => None
+    };
+}
@@ -0,0 +1,72 @@
+.global _start
+.align 4
+.text
+_start:
+bl main
+mov x8, #93
+svc #0
+mixed:
+str x30, [sp, #-16]!
+ldr x9, [sp, #32]
+str x9, [sp, #16]
+ldr x30, [sp], #16
+ret
+tight:
+str x30, [sp, #-16]!
+sub sp, sp, #16
+ldrsb x10, [sp, #47]
+strb w10, [sp, #15]
+ldr x10, [sp, #39]
+str x10, [sp, #7]
+ldr x9, [sp, #7]
+mov x0, x9
+add sp, sp, #16
+ldr x30, [sp], #16
+ret
+main:
+str x30, [sp, #-16]!
+sub sp, sp, #48
+mov w10, #1
+strb w10, [sp, #47]
+mov x10, #2
+str x10, [sp, #32]
+mov w10, #3
+strh w10, [sp, #30]
+mov w10, #4
+str w10, [sp, #24]
+mov w10, #5
+strb w10, [sp, #23]
+sub sp, sp, #16
+mov w10, #6
+strb w10, [sp, #15]
+mov x10, #7
+str x10, [sp, #0]
+add sp, sp, #16
+sub sp, sp, #32
+ldrsb x10, [sp, #79]
+strb w10, [sp, #31]
+ldr x10, [sp, #64]
+str x10, [sp, #16]
+ldrsh x10, [sp, #62]
+strh w10, [sp, #14]
+ldrsw x10, [sp, #56]
+str w10, [sp, #8]
+bl mixed
+ldr x9, [sp, #0]
+add sp, sp, #32
+str x9, [sp, #8]
+sub sp, sp, #16
+ldrsb x10, [sp, #63]
+strb w10, [sp, #15]
+ldr x10, [sp, #48]
+str x10, [sp, #7]
+bl tight
+mov x9, x0
+add sp, sp, #16
+str x9, [sp, #8]
+ldr x9, [sp, #8]
+mov x0, x9
+add sp, sp, #48
+ldr x30, [sp], #16
+ret
+.section .note.GNU-stack,"",@progbits
@@ -0,0 +1,78 @@
+.global _start
+.align 4
+.text
+_start:
+bl main
+mov x8, #93
+svc #0
+main:
+str x30, [sp, #-16]!
+sub sp, sp, #128
+mov x10, #0
+str x10, [sp, #24]
+.Lloop_1_start:
+ldr x9, [sp, #24]
+mov x10, #4
+cmp x9, x10
+b.ge .Lcompare_arm_3
+b .Lcompare_2_end
+.Lcompare_arm_3:
+b .Lloop_1_end
+b .Lcompare_2_end
+.Lcompare_2_end:
+ldr x9, [sp, #24]
+add x9, sp, x9, lsl #3
+add x9, x9, #32
+ldr x10, [sp, #24]
+mov x11, #10
+mul x10, x10, x11
+str x10, [x9]
+ldr x9, [sp, #24]
+mov x10, #1
+add x9, x9, x10
+add x9, sp, x9
+add x9, x9, #64
+mov x10, #65
+strb w10, [x9]
+ldr x9, [sp, #24]
+mov x10, #1
+add x9, x9, x10
+str x9, [sp, #24]
+b .Lloop_1_start
+.Lloop_1_end:
+ldr x9, [sp, #56]
+ldr x10, [sp, #24]
+mov x11, #1
+sub x10, x10, x11
+add x10, sp, x10, lsl #3
+add x10, x10, #32
+ldr x10, [x10]
+add x9, x9, x10
+str x9, [sp, #32]
+ldrb w9, [sp, #65]
+mov x10, #1
+add x9, x9, x10
+uxtb w9, w9
+strb w9, [sp, #64]
+add x9, sp, #32
+str x9, [sp, #16]
+ldr x9, [sp, #16]
+mov x10, #1
+add x9, x9, x10, lsl #3
+ldr x9, [x9]
+str x9, [sp, #8]
+mov x9, #64
+mov x10, #32
+add x9, x9, x10
+str x9, [sp, #0]
+add x9, sp, #48
+ldr x9, [sp, #32]
+ldr x10, [sp, #8]
+add x9, x9, x10
+ldr x10, [sp, #0]
+add x9, x9, x10
+mov x0, x9
+add sp, sp, #128
+ldr x30, [sp], #16
+ret
+.section .note.GNU-stack,"",@progbits
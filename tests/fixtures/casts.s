@@ -0,0 +1,68 @@
+.global _start
+.align 4
+.text
+_start:
+bl main
+mov x8, #93
+svc #0
+low_byte:
+str x30, [sp, #-16]!
+mov x9, x0
+uxtb w9, w9
+mov x0, x9
+ldr x30, [sp], #16
+ret
+main:
+str x30, [sp, #-16]!
+sub sp, sp, #48
+mov w10, #-5
+str w10, [sp, #44]
+ldrsw x9, [sp, #44]
+str x9, [sp, #32]
+ldrsw x9, [sp, #44]
+str x9, [sp, #24]
+ldrb w9, [sp, #32]
+strb w9, [sp, #23]
+mov x9, #200
+sxtb x9, w9
+strb w9, [sp, #22]
+ldr w9, [sp, #44]
+str w9, [sp, #16]
+ldr x9, [sp, #32]
+movz x10, #0x1170
+movk x10, #0x1, lsl #16
+add x9, x9, x10
+uxth w9, w9
+strh w9, [sp, #14]
+ldrsh x9, [sp, #16]
+neg x9, x9
+sxth x9, w9
+ldrsh x10, [sp, #14]
+add x9, x9, x10
+sxth x9, w9
+strh w9, [sp, #12]
+ldr x9, [sp, #32]
+mov x0, x9
+bl low_byte
+mov x9, x0
+uxtb w9, w9
+strb w9, [sp, #11]
+ldrsb x9, [sp, #11]
+ldrsb x10, [sp, #44]
+cmp x9, x10
+b.lt .Lcompare_arm_3
+b .Lcompare_2_end
+.Lcompare_arm_3:
+ldr x9, [sp, #32]
+mov x0, x9
+add sp, sp, #48
+ldr x30, [sp], #16
+ret
+b .Lcompare_2_end
+.Lcompare_2_end:
+ldrb w9, [sp, #23]
+mov x0, x9
+add sp, sp, #48
+ldr x30, [sp], #16
+ret
+.section .note.GNU-stack,"",@progbits
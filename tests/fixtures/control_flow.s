@@ -0,0 +1,60 @@
+.global _start
+.align 4
+.text
+_start:
+bl main
+mov x8, #93
+svc #0
+main:
+str x30, [sp, #-16]!
+sub sp, sp, #32
+mov x10, #0
+str x10, [sp, #24]
+mov x10, #0
+str x10, [sp, #16]
+.Lloop_1_start:
+ldr x9, [sp, #24]
+mov x10, #10
+cmp x9, x10
+b.ge .Lcompare_arm_3
+b.eq .Lcompare_arm_4
+b .Lcompare_arm_5
+.Lcompare_arm_3:
+b .Lloop_1_end
+b .Lcompare_2_end
+.Lcompare_arm_4:
+b .Lloop_1_start
+b .Lcompare_2_end
+.Lcompare_arm_5:
+sub sp, sp, #16
+mov x10, #2
+str x10, [sp, #8]
+ldr x9, [sp, #32]
+ldr x10, [sp, #8]
+add x9, x9, x10
+str x9, [sp, #32]
+add sp, sp, #16
+b .Lcompare_2_end
+.Lcompare_2_end:
+ldr x9, [sp, #24]
+mov x10, #1
+add x9, x9, x10
+str x9, [sp, #24]
+b .Lloop_1_start
+.Lloop_1_end:
+mov w10, #1
+strb w10, [sp, #15]
+sub sp, sp, #16
+mov w10, #3
+strh w10, [sp, #14]
+sub sp, sp, #16
+mov x10, #4
+str x10, [sp, #8]
+add sp, sp, #16
+add sp, sp, #16
+ldr x9, [sp, #16]
+mov x0, x9
+add sp, sp, #32
+ldr x30, [sp], #16
+ret
+.section .note.GNU-stack,"",@progbits
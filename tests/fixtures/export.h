@@ -0,0 +1,23 @@
+/* Generated by byte-lang from export.byte, do not edit. */
+#ifndef EXPORT_H
+#define EXPORT_H
+
+#include <stdint.h>
+
+struct Point {
+    int32_t x;
+    int32_t y;
+};
+
+struct Header {
+    uint8_t kind;
+    uint32_t length;
+    uint8_t tag[4];
+} __attribute__((packed));
+
+int64_t add(int64_t a, int64_t b);
+int32_t dot(struct Point *a, struct Point *b, uint8_t shift);
+uint32_t header_length(struct Header *header, int64_t a, int64_t b, int64_t c, int64_t d, int64_t e, int64_t f, int64_t g, uint16_t extra);
+void reset(uint8_t **buffer);
+
+#endif /* EXPORT_H */
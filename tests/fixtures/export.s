@@ -0,0 +1,147 @@
+.global _start
+.align 4
+.text
+_start:
+bl main
+mov x8, #93
+svc #0
+.global add
+add:
+stp x29, x30, [sp, #-96]!
+mov x29, sp
+stp x19, x20, [sp, #16]
+stp x21, x22, [sp, #32]
+stp x23, x24, [sp, #48]
+stp x25, x26, [sp, #64]
+stp x27, x28, [sp, #80]
+mov x9, x0
+mov x10, x1
+add x9, x9, x10
+mov x0, x9
+ldp x19, x20, [sp, #16]
+ldp x21, x22, [sp, #32]
+ldp x23, x24, [sp, #48]
+ldp x25, x26, [sp, #64]
+ldp x27, x28, [sp, #80]
+ldp x29, x30, [sp], #96
+ret
+scale:
+str x30, [sp, #-16]!
+mov x9, x19
+mov x10, x20
+mul x9, x9, x10
+mov x0, x9
+ldr x30, [sp], #16
+ret
+.global dot
+dot:
+stp x29, x30, [sp, #-96]!
+mov x29, sp
+stp x19, x20, [sp, #16]
+stp x21, x22, [sp, #32]
+stp x23, x24, [sp, #48]
+stp x25, x26, [sp, #64]
+stp x27, x28, [sp, #80]
+sub sp, sp, #16
+uxtb w2, w2
+sub sp, sp, #32
+str x0, [sp, #0]
+str x1, [sp, #8]
+str x2, [sp, #16]
+mov x9, x0
+ldrsw x9, [x9]
+mov x10, x1
+ldrsw x10, [x10]
+mov x19, x9
+mov x20, x10
+bl scale
+mov x9, x0
+ldr x0, [sp, #0]
+ldr x1, [sp, #8]
+ldr x2, [sp, #16]
+add sp, sp, #32
+str x9, [sp, #8]
+sub sp, sp, #32
+str x0, [sp, #0]
+str x1, [sp, #8]
+str x2, [sp, #16]
+mov x9, x0
+add x9, x9, #4
+ldrsw x9, [x9]
+mov x10, x1
+add x10, x10, #4
+ldrsw x10, [x10]
+mov x19, x9
+mov x20, x10
+bl scale
+mov x9, x0
+ldr x0, [sp, #0]
+ldr x1, [sp, #8]
+ldr x2, [sp, #16]
+add sp, sp, #32
+str x9, [sp, #0]
+ldr x9, [sp, #8]
+ldr x10, [sp, #0]
+add x9, x9, x10
+mov x10, x2
+uxtb w10, w10
+asr x9, x9, x10
+sxtw x9, w9
+mov x0, x9
+add sp, sp, #16
+ldp x19, x20, [sp, #16]
+ldp x21, x22, [sp, #32]
+ldp x23, x24, [sp, #48]
+ldp x25, x26, [sp, #64]
+ldp x27, x28, [sp, #80]
+ldp x29, x30, [sp], #96
+ret
+.global header_length
+header_length:
+stp x29, x30, [sp, #-96]!
+mov x29, sp
+stp x19, x20, [sp, #16]
+stp x21, x22, [sp, #32]
+stp x23, x24, [sp, #48]
+stp x25, x26, [sp, #64]
+stp x27, x28, [sp, #80]
+mov x9, x0
+add x9, x9, #1
+ldr w9, [x9]
+ldrh w10, [sp, #96]
+add x9, x9, x10
+mov w9, w9
+mov x0, x9
+ldp x19, x20, [sp, #16]
+ldp x21, x22, [sp, #32]
+ldp x23, x24, [sp, #48]
+ldp x25, x26, [sp, #64]
+ldp x27, x28, [sp, #80]
+ldp x29, x30, [sp], #96
+ret
+.global reset
+reset:
+stp x29, x30, [sp, #-96]!
+mov x29, sp
+stp x19, x20, [sp, #16]
+stp x21, x22, [sp, #32]
+stp x23, x24, [sp, #48]
+stp x25, x26, [sp, #64]
+stp x27, x28, [sp, #80]
+ldp x19, x20, [sp, #16]
+ldp x21, x22, [sp, #32]
+ldp x23, x24, [sp, #48]
+ldp x25, x26, [sp, #64]
+ldp x27, x28, [sp, #80]
+ldp x29, x30, [sp], #96
+ret
+main:
+str x30, [sp, #-16]!
+mov x0, #1
+mov x1, #2
+bl add
+mov x9, x0
+mov x0, x9
+ldr x30, [sp], #16
+ret
+.section .note.GNU-stack,"",@progbits
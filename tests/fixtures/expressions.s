@@ -0,0 +1,62 @@
+.global _start
+.align 4
+.text
+_start:
+bl main
+mov x8, #93
+svc #0
+main:
+str x30, [sp, #-16]!
+sub sp, sp, #48
+mov w10, #5
+str w10, [sp, #44]
+ldrsw x9, [sp, #44]
+mov x10, #1
+add x9, x9, x10
+sxtw x9, w9
+mov x10, #2
+mul x9, x9, x10
+sxtw x9, w9
+str w9, [sp, #40]
+ldrsw x9, [sp, #44]
+ldrsw x10, [sp, #40]
+cmp x9, x10
+cset x9, lt
+strb w9, [sp, #39]
+mov x10, #100
+str x10, [sp, #24]
+ldr x9, [sp, #24]
+neg x9, x9
+mov x10, #7
+sdiv x17, x9, x10
+msub x9, x17, x10, x9
+ldr x10, [sp, #24]
+mov x11, #2
+lsl x10, x10, x11
+add x9, x9, x10
+ldr x10, [sp, #24]
+mvn x10, x10
+sub x9, x9, x10
+str x9, [sp, #16]
+mov x10, #40
+str x10, [sp, #8]
+ldr x9, [sp, #8]
+mov x10, #3
+lsr x9, x9, x10
+ldr x10, [sp, #8]
+mov x11, #12
+and x10, x10, x11
+mov x11, #1
+eor x10, x10, x11
+orr x9, x9, x10
+str x9, [sp, #0]
+ldr x9, [sp, #16]
+mov x10, #3
+sdiv x9, x9, x10
+str x9, [sp, #16]
+ldr x9, [sp, #16]
+mov x0, x9
+add sp, sp, #48
+ldr x30, [sp], #16
+ret
+.section .note.GNU-stack,"",@progbits
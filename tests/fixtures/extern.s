@@ -0,0 +1,78 @@
+.global _start
+.align 4
+.text
+_start:
+bl main
+bl exit
+greet:
+str x30, [sp, #-16]!
+sub sp, sp, #16
+sub sp, sp, #16
+str x0, [sp, #0]
+str x1, [sp, #8]
+mov x9, x0
+mov x0, x9
+bl puts
+mov x9, x0
+sxtw x9, w9
+ldr x0, [sp, #0]
+ldr x1, [sp, #8]
+add sp, sp, #16
+str w9, [sp, #12]
+sub sp, sp, #16
+str x0, [sp, #0]
+str x1, [sp, #8]
+mov x9, x0
+mov x1, x9
+mov x0, #1
+mov x2, #5
+bl write
+ldr x0, [sp, #0]
+ldr x1, [sp, #8]
+add sp, sp, #16
+mov x9, x1
+ldrsw x10, [sp, #12]
+add x9, x9, x10
+mov x10, x20
+add x9, x9, x10
+mov x0, x9
+add sp, sp, #16
+ldr x30, [sp], #16
+ret
+main:
+str x30, [sp, #-16]!
+sub sp, sp, #16
+adrp x9, .Lstr0
+add x9, x9, :lo12:.Lstr0
+mov x0, x9
+mov x1, #2
+mov x20, #3
+bl greet
+mov x9, x0
+str x9, [sp, #8]
+sub sp, sp, #16
+mov w10, #9
+str w10, [sp, #0]
+ldrb w9, [sp, #24]
+strb w9, [sp, #8]
+mov x0, #1
+mov x1, #2
+mov x2, #3
+mov x3, #4
+mov x4, #5
+mov x5, #6
+mov x6, #7
+mov x7, #8
+bl sum10
+mov x9, x0
+add sp, sp, #16
+str x9, [sp, #0]
+ldr x9, [sp, #0]
+mov x0, x9
+add sp, sp, #16
+ldr x30, [sp], #16
+ret
+.section .rodata
+.Lstr0:
+.asciz "hello"
+.section .note.GNU-stack,"",@progbits
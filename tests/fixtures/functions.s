@@ -0,0 +1,80 @@
+.global _start
+.align 4
+.text
+_start:
+bl main
+mov x8, #93
+svc #0
+add:
+str x30, [sp, #-16]!
+mov x9, x0
+ldr x10, [sp, #24]
+add x9, x9, x10
+mov x0, x9
+mov x10, #7
+str x10, [sp, #24]
+mov x9, #3
+mov x2, x9
+mov x9, x0
+mov x0, x9
+ldr x30, [sp], #16
+ret
+pick:
+str x30, [sp, #-16]!
+ldrsw x9, [sp, #28]
+ldrsw x10, [sp, #24]
+cmp x9, x10
+b.gt .Lcompare_arm_3
+b .Lcompare_arm_4
+.Lcompare_arm_3:
+ldrsw x9, [sp, #28]
+str w9, [sp, #20]
+ldr x30, [sp], #16
+ret
+b .Lcompare_2_end
+.Lcompare_arm_4:
+ldrsw x9, [sp, #24]
+str w9, [sp, #20]
+ldr x30, [sp], #16
+ret
+b .Lcompare_2_end
+.Lcompare_2_end:
+ldr x30, [sp], #16
+ret
+nothing:
+str x30, [sp, #-16]!
+ldr x30, [sp], #16
+ret
+main:
+str x30, [sp, #-16]!
+sub sp, sp, #32
+mov x10, #1
+str x10, [sp, #24]
+ldr x10, [sp, #24]
+str x10, [sp, #16]
+sub sp, sp, #16
+ldr x10, [sp, #32]
+str x10, [sp, #8]
+ldr x9, [sp, #40]
+mov x0, x9
+mov x2, #4
+bl add
+mov x9, x0
+add sp, sp, #16
+str x9, [sp, #24]
+sub sp, sp, #16
+mov w10, #5
+str w10, [sp, #12]
+mov w10, #9
+str w10, [sp, #8]
+bl pick
+ldrsw x9, [sp, #4]
+add sp, sp, #16
+str w9, [sp, #12]
+bl nothing
+ldr x9, [sp, #24]
+mov x0, x9
+add sp, sp, #32
+ldr x30, [sp], #16
+ret
+.section .note.GNU-stack,"",@progbits
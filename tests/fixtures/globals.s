@@ -0,0 +1,115 @@
+.global _start
+.align 4
+.text
+_start:
+bl main
+mov x8, #93
+svc #0
+bump:
+str x30, [sp, #-16]!
+adrp x9, total
+add x9, x9, :lo12:total
+adrp x10, total
+add x10, x10, :lo12:total
+ldr x10, [x10]
+mov x11, x0
+add x10, x10, x11
+str x10, [x9]
+adrp x9, counter
+add x9, x9, :lo12:counter
+adrp x10, counter
+add x10, x10, :lo12:counter
+ldr w10, [x10]
+mov x11, #1
+add x10, x10, x11
+mov w10, w10
+str w10, [x9]
+ldr x30, [sp], #16
+ret
+main:
+str x30, [sp, #-16]!
+sub sp, sp, #32
+mov x0, #5
+bl bump
+mov x10, #2
+str x10, [sp, #24]
+ldr x9, [sp, #24]
+adrp x16, buffer
+add x16, x16, :lo12:buffer
+add x9, x16, x9
+mov x10, #7
+strb w10, [x9]
+adrp x9, buffer
+add x9, x9, :lo12:buffer
+ldr x10, [sp, #24]
+adrp x16, buffer
+add x16, x16, :lo12:buffer
+add x10, x16, x10
+ldrb w10, [x10]
+mov x11, #1
+add x10, x10, x11
+uxtb w10, w10
+strb w10, [x9]
+adrp x9, total
+add x9, x9, :lo12:total
+str x9, [sp, #16]
+ldr x9, [sp, #16]
+ldr x10, [sp, #16]
+ldr x10, [x10]
+mov x11, #2
+mul x10, x10, x11
+str x10, [x9]
+ldr x9, [sp, #24]
+adrp x16, table
+add x16, x16, :lo12:table
+add x9, x16, x9, lsl #1
+ldrsh x9, [x9]
+adrp x10, table
+add x10, x10, :lo12:table
+add x10, x10, #2
+ldrsh x10, [x10]
+add x9, x9, x10
+adrp x10, limit
+add x10, x10, :lo12:limit
+ldr w10, [x10]
+add x9, x9, x10
+str x9, [sp, #8]
+ldr x9, [sp, #8]
+adrp x10, total
+add x10, x10, :lo12:total
+ldr x10, [x10]
+add x9, x9, x10
+adrp x10, counter
+add x10, x10, :lo12:counter
+ldr w10, [x10]
+add x9, x9, x10
+adrp x10, buffer
+add x10, x10, :lo12:buffer
+ldrb w10, [x10]
+add x9, x9, x10
+mov x0, x9
+add sp, sp, #32
+ldr x30, [sp], #16
+ret
+.data
+.p2align 3
+total:
+.8byte 10
+.section .rodata
+.p2align 1
+table:
+.2byte 1
+.2byte -2
+.2byte 3
+.zero 2
+.p2align 2
+limit:
+.4byte 4096
+.bss
+.p2align 0
+buffer:
+.zero 6
+.p2align 2
+counter:
+.zero 8
+.section .note.GNU-stack,"",@progbits
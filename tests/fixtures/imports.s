@@ -0,0 +1,53 @@
+.global _start
+.align 4
+.text
+_start:
+bl main
+mov x0, #0
+mov x8, #93
+svc #0
+main:
+str x30, [sp, #-16]!
+sub sp, sp, #16
+bl util__two
+mov x9, x0
+str x9, [sp, #8]
+ldr x9, [sp, #8]
+mov x0, x9
+bl general_syscall_functions__term
+add sp, sp, #16
+ldr x30, [sp], #16
+ret
+general_syscall_functions__term:
+str x30, [sp, #-16]!
+mov x8, #93
+svc #0
+ldr x30, [sp], #16
+ret
+general_syscall_functions__print:
+str x30, [sp, #-16]!
+mov x0, #1
+mov x8, #64
+svc #0
+ldr x30, [sp], #16
+ret
+util__one:
+str x30, [sp, #-16]!
+mov x9, #1
+mov x0, x9
+ldr x30, [sp], #16
+ret
+util__two:
+str x30, [sp, #-16]!
+sub sp, sp, #16
+bl util__one
+mov x9, x0
+str x9, [sp, #8]
+ldr x9, [sp, #8]
+ldr x10, [sp, #8]
+add x9, x9, x10
+mov x0, x9
+add sp, sp, #16
+ldr x30, [sp], #16
+ret
+.section .note.GNU-stack,"",@progbits
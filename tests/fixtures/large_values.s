@@ -0,0 +1,152 @@
+.global _start
+.align 4
+.text
+_start:
+bl main
+mov x8, #93
+svc #0
+far_away:
+str x30, [sp, #-16]!
+sub sp, sp, #16
+ldrb w10, [sp, #47]
+strb w10, [sp, #15]
+movz x10, #0x1170
+movk x10, #0x1, lsl #16
+str x10, [sp, #7]
+sub sp, sp, #272
+mov x10, #0
+str x10, [sp, #264]
+mov x10, #1
+str x10, [sp, #256]
+mov x10, #2
+str x10, [sp, #248]
+mov x10, #3
+str x10, [sp, #240]
+mov x10, #4
+str x10, [sp, #232]
+mov x10, #5
+str x10, [sp, #224]
+mov x10, #6
+str x10, [sp, #216]
+mov x10, #7
+str x10, [sp, #208]
+mov x10, #8
+str x10, [sp, #200]
+mov x10, #9
+str x10, [sp, #192]
+mov x10, #10
+str x10, [sp, #184]
+mov x10, #11
+str x10, [sp, #176]
+mov x10, #12
+str x10, [sp, #168]
+mov x10, #13
+str x10, [sp, #160]
+mov x10, #14
+str x10, [sp, #152]
+mov x10, #15
+str x10, [sp, #144]
+mov x10, #16
+str x10, [sp, #136]
+mov x10, #17
+str x10, [sp, #128]
+mov x10, #18
+str x10, [sp, #120]
+mov x10, #19
+str x10, [sp, #112]
+mov x10, #20
+str x10, [sp, #104]
+mov x10, #21
+str x10, [sp, #96]
+mov x10, #22
+str x10, [sp, #88]
+mov x10, #23
+str x10, [sp, #80]
+mov x10, #24
+str x10, [sp, #72]
+mov x10, #25
+str x10, [sp, #64]
+mov x10, #26
+str x10, [sp, #56]
+mov x10, #27
+str x10, [sp, #48]
+mov x10, #28
+str x10, [sp, #40]
+mov x10, #29
+str x10, [sp, #32]
+mov x10, #30
+str x10, [sp, #24]
+mov x10, #31
+str x10, [sp, #16]
+mov x10, #32
+str x10, [sp, #8]
+mov x10, #33
+str x10, [sp, #0]
+// sp offset 279 is out of range for ldr, addressed through x16
+mov x16, #279
+ldr x9, [sp, x16]
+ldrb w10, [sp, #287]
+add x9, x9, x10
+ldr x10, [sp, #0]
+add x9, x9, x10
+// sp offset 279 is out of range for str, addressed through x16
+mov x16, #279
+str x9, [sp, x16]
+add sp, sp, #272
+ldr x9, [sp, #7]
+mov x0, x9
+add sp, sp, #16
+ldr x30, [sp], #16
+ret
+main:
+str x30, [sp, #-16]!
+sub sp, sp, #80
+movz x10, #0x1, lsl #32
+str x10, [sp, #72]
+movn x10, #0x423f
+movk x10, #0xfff0, lsl #16
+str x10, [sp, #64]
+movz x10, #0xffff, lsl #32
+movk x10, #0xffff, lsl #48
+str x10, [sp, #56]
+movz x10, #0xcdef
+movk x10, #0x89ab, lsl #16
+movk x10, #0x4567, lsl #32
+movk x10, #0x123, lsl #48
+str x10, [sp, #48]
+movz w10, #0x2800
+movk w10, #0xee6b, lsl #16
+str w10, [sp, #44]
+movz w10, #0x7960
+movk w10, #0xfffe, lsl #16
+str w10, [sp, #40]
+mov w10, #65535
+strh w10, [sp, #38]
+movn w10, #0
+str w10, [sp, #32]
+mov w10, #-1
+str w10, [sp, #28]
+ldr x9, [sp, #72]
+ldr x10, [sp, #64]
+add x9, x9, x10
+ldr x10, [sp, #56]
+add x9, x9, x10
+movz x10, #0xcd15
+movk x10, #0x75b, lsl #16
+add x9, x9, x10
+str x9, [sp, #16]
+sub sp, sp, #16
+mov w10, #7
+strb w10, [sp, #15]
+bl far_away
+mov x9, x0
+add sp, sp, #16
+str x9, [sp, #8]
+ldr x9, [sp, #8]
+ldr x10, [sp, #16]
+add x9, x9, x10
+mov x0, x9
+add sp, sp, #80
+ldr x30, [sp], #16
+ret
+.section .note.GNU-stack,"",@progbits
@@ -0,0 +1,131 @@
+.global _start
+.align 4
+.text
+_start:
+bl main
+mov x8, #93
+svc #0
+fill:
+str x30, [sp, #-16]!
+sub sp, sp, #16
+mov x10, #0
+str x10, [sp, #8]
+.Lloop_1_start:
+ldr x9, [sp, #8]
+ldr x10, [sp, #48]
+cmp x9, x10
+b.ge .Lcompare_arm_3
+b .Lcompare_2_end
+.Lcompare_arm_3:
+b .Lloop_1_end
+b .Lcompare_2_end
+.Lcompare_2_end:
+ldr x9, [sp, #56]
+ldr x10, [sp, #8]
+add x9, x9, x10
+ldrb w10, [sp, #47]
+strb w10, [x9]
+ldr x9, [sp, #8]
+mov x10, #1
+add x9, x9, x10
+str x9, [sp, #8]
+b .Lloop_1_start
+.Lloop_1_end:
+add sp, sp, #16
+ldr x30, [sp], #16
+ret
+sum_pair:
+str x30, [sp, #-16]!
+sub sp, sp, #16
+mov x9, x0
+ldr x9, [x9]
+str x9, [sp, #8]
+mov x9, x0
+mov x10, #1
+add x9, x9, x10, lsl #3
+ldr x9, [x9]
+str x9, [sp, #0]
+ldr x9, [sp, #8]
+ldr x10, [sp, #0]
+add x9, x9, x10
+mov x0, x9
+add sp, sp, #16
+ldr x30, [sp], #16
+ret
+main:
+str x30, [sp, #-16]!
+sub sp, sp, #112
+mov x10, #40
+str x10, [sp, #104]
+mov x10, #2
+str x10, [sp, #96]
+add x9, sp, #104
+str x9, [sp, #88]
+ldr x9, [sp, #88]
+ldr x10, [sp, #88]
+ldr x10, [x10]
+mov x11, #1
+add x10, x10, x11
+str x10, [x9]
+ldr x9, [sp, #88]
+mov x10, #1
+sub x9, x9, x10, lsl #3
+str x9, [sp, #80]
+ldr x9, [sp, #80]
+add x10, sp, #96
+cmp x9, x10
+b.eq .Lcompare_arm_7
+b .Lcompare_6_end
+.Lcompare_arm_7:
+ldr x9, [sp, #104]
+mov x10, #1
+sub x9, x9, x10
+str x9, [sp, #104]
+b .Lcompare_6_end
+.Lcompare_6_end:
+mov w10, #-7
+str w10, [sp, #76]
+add x9, sp, #76
+str x9, [sp, #64]
+ldr x9, [sp, #64]
+ldrsw x9, [x9]
+str x9, [sp, #56]
+ldr x9, [sp, #64]
+str x9, [sp, #48]
+ldr x9, [sp, #48]
+mov x10, #3
+add x9, x9, x10
+ldrb w9, [x9]
+strb w9, [sp, #47]
+ldr x9, [sp, #88]
+str x9, [sp, #32]
+add x9, sp, #88
+str x9, [sp, #24]
+ldr x9, [sp, #24]
+ldr x9, [x9]
+ldr x9, [x9]
+str x9, [sp, #16]
+sub sp, sp, #32
+ldr x10, [sp, #80]
+str x10, [sp, #24]
+mov x10, #4
+str x10, [sp, #16]
+mov w10, #255
+strb w10, [sp, #15]
+bl fill
+add sp, sp, #32
+ldr x9, [sp, #80]
+mov x0, x9
+bl sum_pair
+mov x9, x0
+str x9, [sp, #8]
+ldr x9, [sp, #8]
+ldr x10, [sp, #56]
+add x9, x9, x10
+ldr x10, [sp, #16]
+add x9, x9, x10
+mov x0, x9
+add sp, sp, #112
+ldr x30, [sp], #16
+ret
+.section .note.GNU-stack,"",@progbits
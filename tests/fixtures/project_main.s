@@ -0,0 +1,64 @@
+.global _start
+.align 4
+.text
+_start:
+bl main
+mov x0, #0
+mov x8, #93
+svc #0
+test:
+str x30, [sp, #-16]!
+sub sp, sp, #16
+ldrsw x10, [sp, #44]
+str w10, [sp, #12]
+add sp, sp, #16
+ldr x30, [sp], #16
+ret
+main:
+str x30, [sp, #-16]!
+sub sp, sp, #48
+mov w10, #5
+str w10, [sp, #44]
+mov w10, #2
+strh w10, [sp, #42]
+mov w10, #1
+strb w10, [sp, #41]
+mov x10, #30
+str x10, [sp, #32]
+ldr x10, [sp, #32]
+str x10, [sp, #24]
+mov x10, #10
+str x10, [sp, #16]
+mov w10, #30
+strb w10, [sp, #15]
+ldrb w10, [sp, #15]
+strb w10, [sp, #14]
+mov x10, #0
+str x10, [sp, #0]
+sub sp, sp, #16
+ldrsw x10, [sp, #60]
+str w10, [sp, #12]
+ldr x10, [sp, #40]
+str x10, [sp, #0]
+bl test
+add sp, sp, #16
+ldr x9, [sp, #0]
+mov x0, x9
+bl general_syscall_functions__term
+add sp, sp, #48
+ldr x30, [sp], #16
+ret
+general_syscall_functions__term:
+str x30, [sp, #-16]!
+mov x8, #93
+svc #0
+ldr x30, [sp], #16
+ret
+general_syscall_functions__print:
+str x30, [sp, #-16]!
+mov x0, #1
+mov x8, #64
+svc #0
+ldr x30, [sp], #16
+ret
+.section .note.GNU-stack,"",@progbits
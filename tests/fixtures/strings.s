@@ -0,0 +1,95 @@
+.global _start
+.align 4
+.text
+_start:
+bl main
+mov x8, #93
+svc #0
+first_byte:
+str x30, [sp, #-16]!
+mov x9, x0
+ldrb w9, [x9]
+mov x0, x9
+ldr x30, [sp], #16
+ret
+main:
+str x30, [sp, #-16]!
+sub sp, sp, #32
+adrp x9, .Lstr0
+add x9, x9, :lo12:.Lstr0
+mov x1, x9
+mov x2, #11
+bl general_syscall_functions__print
+adrp x9, greeting
+add x9, x9, :lo12:greeting
+mov x10, #7
+mov x11, #1
+sub x10, x10, x11
+mov x1, x9
+mov x2, x10
+bl general_syscall_functions__print
+movz x10, #0x7962
+movk x10, #0x6574, lsl #16
+movk x10, #0x6c20, lsl #32
+movk x10, #0x6e61, lsl #48
+str x10, [sp, #21]
+mov w10, #103
+strh w10, [sp, #29]
+mov w10, #0
+strb w10, [sp, #31]
+mov w10, #95
+strb w10, [sp, #25]
+add x9, sp, #21
+mov x1, x9
+mov x2, #9
+bl general_syscall_functions__print
+adrp x9, .Lstr1
+add x9, x9, :lo12:.Lstr1
+str x9, [sp, #8]
+adrp x9, .Lstr1
+add x9, x9, :lo12:.Lstr1
+mov x0, x9
+bl first_byte
+mov x9, x0
+uxtb w9, w9
+strb w9, [sp, #7]
+ldrb w9, [sp, #7]
+ldrb w10, [sp, #22]
+add x9, x9, x10
+ldr x10, [sp, #8]
+mov x11, #0
+add x10, x10, x11
+ldrb w10, [x10]
+add x9, x9, x10
+mov x0, x9
+add sp, sp, #32
+ldr x30, [sp], #16
+ret
+general_syscall_functions__term:
+str x30, [sp, #-16]!
+mov x8, #93
+svc #0
+ldr x30, [sp], #16
+ret
+general_syscall_functions__print:
+str x30, [sp, #-16]!
+mov x0, #1
+mov x8, #64
+svc #0
+ldr x30, [sp], #16
+ret
+.section .rodata
+.p2align 0
+greeting:
+.byte 104
+.byte 101
+.byte 108
+.byte 108
+.byte 111
+.byte 10
+.zero 1
+.Lstr0:
+.asciz "hi \042there\042\012"
+.Lstr1:
+.asciz "\011"
+.section .note.GNU-stack,"",@progbits
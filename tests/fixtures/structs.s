@@ -0,0 +1,105 @@
+.global _start
+.align 4
+.text
+_start:
+bl main
+mov x8, #93
+svc #0
+magic_of:
+str x30, [sp, #-16]!
+ldr w9, [sp, #24]
+mov x0, x9
+ldr x30, [sp], #16
+ret
+set_flags:
+str x30, [sp, #-16]!
+mov x9, x0
+add x9, x9, #6
+mov x10, x1
+uxth w10, w10
+strh w10, [x9]
+ldr x30, [sp], #16
+ret
+main:
+str x30, [sp, #-16]!
+sub sp, sp, #96
+movz w10, #0xbabe
+movk w10, #0xcafe, lsl #16
+str w10, [sp, #88]
+mov w10, #2
+strh w10, [sp, #92]
+mov w10, #0
+strh w10, [sp, #94]
+add x9, sp, #88
+mov x0, x9
+mov x1, #5
+bl set_flags
+mov w10, #1
+strb w10, [sp, #64]
+ldr x10, [sp, #88]
+str x10, [sp, #68]
+mov x9, #24
+mov x10, #16
+add x9, x9, x10
+str x9, [sp, #80]
+sub sp, sp, #16
+ldr x10, [sp, #84]
+str x10, [sp, #8]
+bl magic_of
+mov x9, x0
+mov w9, w9
+add sp, sp, #16
+str w9, [sp, #60]
+add x9, sp, #44
+mov x10, #1
+add x9, x9, x10, lsl #3
+str x9, [sp, #32]
+ldr x9, [sp, #32]
+add x9, x9, #4
+ldrh w10, [sp, #74]
+strh w10, [x9]
+mov x9, #7
+mov x10, #5
+mov x10, #3
+str x10, [sp, #8]
+mov w10, #7
+strh w10, [sp, #26]
+ldr x9, [sp, #8]
+add x9, sp, x9, lsl #1
+add x9, x9, #24
+ldrh w10, [sp, #26]
+strh w10, [x9]
+add x9, sp, #22
+str x9, [sp, #0]
+ldr x9, [sp, #0]
+add x9, x9, #6
+mov x10, #5
+strh w10, [x9]
+ldr x9, [sp, #8]
+ldr x10, [sp, #0]
+add x9, x10, x9, lsl #1
+add x9, x9, #2
+ldr x10, [sp, #0]
+add x10, x10, #6
+ldrh w10, [x10]
+mov x11, #1
+add x10, x10, x11
+uxth w10, w10
+strh w10, [x9]
+ldr x9, [sp, #80]
+ldrh w10, [sp, #94]
+add x9, x9, x10
+ldr x10, [sp, #32]
+add x10, x10, #4
+ldrh w10, [x10]
+add x9, x9, x10
+ldr x10, [sp, #8]
+add x10, sp, x10, lsl #1
+add x10, x10, #24
+ldrh w10, [x10]
+add x9, x9, x10
+mov x0, x9
+add sp, sp, #96
+ldr x30, [sp], #16
+ret
+.section .note.GNU-stack,"",@progbits
@@ -0,0 +1,73 @@
+.global _start
+.align 4
+.text
+_start:
+bl main
+mov x8, #93
+svc #0
+widen:
+str x30, [sp, #-16]!
+ldr x9, [sp, #24]
+mov x0, x9
+ldr x30, [sp], #16
+ret
+narrow_arg:
+str x30, [sp, #-16]!
+mov x9, x0
+sxth x9, w9
+mov x0, x9
+ldr x30, [sp], #16
+ret
+main:
+str x30, [sp, #-16]!
+sub sp, sp, #48
+mov w10, #255
+strb w10, [sp, #47]
+mov w10, #-128
+strb w10, [sp, #46]
+ldrsb x9, [sp, #46]
+strh w9, [sp, #44]
+ldrb w9, [sp, #47]
+ldrsh x10, [sp, #44]
+add x9, x9, x10
+sxtw x9, w9
+str w9, [sp, #40]
+ldrb w9, [sp, #47]
+str x9, [sp, #32]
+ldrsw x9, [sp, #40]
+mov x10, #1
+add x9, x9, x10
+str x9, [sp, #24]
+sub sp, sp, #16
+mov x10, #0
+str x10, [sp, #8]
+mov x1, #0
+bl widen
+mov x9, x0
+add sp, sp, #16
+str x9, [sp, #16]
+ldrsh x9, [sp, #44]
+mov x0, x9
+bl narrow_arg
+mov x9, x0
+sxtw x9, w9
+str x9, [sp, #8]
+ldrb w9, [sp, #47]
+ldrsh x10, [sp, #44]
+cmp x9, x10
+b.lt .Lcompare_arm_4
+b .Lcompare_3_end
+.Lcompare_arm_4:
+ldr x9, [sp, #24]
+mov x0, x9
+add sp, sp, #48
+ldr x30, [sp], #16
+ret
+b .Lcompare_3_end
+.Lcompare_3_end:
+ldr x9, [sp, #8]
+mov x0, x9
+add sp, sp, #48
+ldr x30, [sp], #16
+ret
+.section .note.GNU-stack,"",@progbits
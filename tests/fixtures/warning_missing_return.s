@@ -0,0 +1,38 @@
+.global _start
+.align 4
+.text
+_start:
+bl main
+mov x0, #0
+mov x8, #93
+svc #0
+maybe:
+str x30, [sp, #-16]!
+mov x9, x0
+mov x10, #0
+cmp x9, x10
+b.gt .Lcompare_arm_2
+b .Lcompare_1_end
+.Lcompare_arm_2:
+mov x9, x0
+mov x0, x9
+ldr x30, [sp], #16
+ret
+b .Lcompare_1_end
+.Lcompare_1_end:
+ldr x30, [sp], #16
+ret
+main:
+str x30, [sp, #-16]!
+sub sp, sp, #16
+mov x10, #1
+str x10, [sp, #8]
+ldr x9, [sp, #8]
+mov x0, x9
+bl maybe
+mov x9, x0
+str x9, [sp, #8]
+add sp, sp, #16
+ldr x30, [sp], #16
+ret
+.section .note.GNU-stack,"",@progbits
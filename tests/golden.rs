@@ -0,0 +1,294 @@
+use std::path::{Path, PathBuf};
+
+use language::datatypes::diagnostic::DiagnosticCode;
+use language::datatypes::target::Target;
+use language::{compile_file, compile_source, Artifacts, Options, Phase, Profile};
+
+// Every .byte file directly inside tests/fixtures is compiled for aarch64-linux and the output of
+// every phase is compared with the snapshot next to it:
+//
+//   name.tokens       tokens of every file
+//   name.ast          statements of every file
+//   name.layout       stack frame layout of every function
+//   name.s            emitted assembly
+//   name.h            C header of the exported functions
+//   name.diagnostics  rendered errors and warnings
+//
+// Programs that fail to compile only have a .diagnostics snapshot. Snapshots that would be empty
+// are left out. Files inside subdirectories are only there to be imported.
+//
+// BLESS=1 cargo test --test golden writes the current output as the new snapshots.
+
+fn fixtures_dir() -> PathBuf {
+    return PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures");
+}
+
+fn fixture_files() -> Vec<PathBuf> {
+    let mut fixtures : Vec<PathBuf> = std::fs::read_dir(fixtures_dir())
+        .expect("Error reading tests/fixtures")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.is_file() && path.extension().is_some_and(|extension| extension == "byte"))
+        .collect();
+
+    fixtures.sort();
+
+    return fixtures;
+}
+
+fn is_bless() -> bool {
+    return std::env::var("BLESS").is_ok_and(|bless| bless == "1");
+}
+
+fn snapshots(artifacts : &Artifacts) -> Vec<(&'static str, String)> {
+    return vec![
+        ("tokens", artifacts.token_dump()),
+        ("ast", artifacts.ast_dump()),
+        ("layout", artifacts.layout.clone()),
+        ("s", artifacts.assembly.clone()),
+        ("h", artifacts.header.clone()),
+        ("diagnostics", artifacts.render_diagnostics())
+    ];
+}
+
+// Compares actual with the snapshot at path, or overwrites the snapshot in bless mode. Returns a
+// description of the mismatch.
+fn check_snapshot(path : &Path, actual : &str) -> Option<String> {
+    if is_bless() {
+        if actual.is_empty() {
+            let _ = std::fs::remove_file(path);
+        } else {
+            std::fs::write(path, actual).expect("Error writing snapshot");
+        }
+
+        return None;
+    }
+
+    let expected = std::fs::read_to_string(path).unwrap_or_default();
+
+    if expected == actual {
+        return None;
+    }
+
+    let expected_lines : Vec<&str> = expected.lines().collect();
+    let actual_lines : Vec<&str> = actual.lines().collect();
+
+    let line = (0..expected_lines.len().max(actual_lines.len()))
+        .find(|line| expected_lines.get(*line) != actual_lines.get(*line))
+        .unwrap_or(0);
+
+    return Some(format!("{} differs at line {}\n  expected: {}\n  actual:   {}",
+        path.display(),
+        line + 1,
+        expected_lines.get(line).unwrap_or(&"<end of file>"),
+        actual_lines.get(line).unwrap_or(&"<end of file>")));
+}
+
+#[test]
+fn golden_snapshots() {
+    let fixtures = fixture_files();
+
+    assert!(!fixtures.is_empty(), "No fixtures found in {}", fixtures_dir().display());
+
+    let mut failures : Vec<String> = Vec::new();
+
+    for fixture in fixtures.iter() {
+        let file_name = fixture.file_name().unwrap().to_string_lossy().to_string();
+
+        let mut options = Options::new(&file_name);
+        options.target = Target::Aarch64Linux;
+
+        let results = match compile_file(fixture, &options) {
+            Ok(artifacts) => snapshots(&artifacts),
+            Err(errors) => vec![("tokens", String::new()), ("ast", String::new()), ("layout", String::new()), ("s", String::new()), ("h", String::new()), ("diagnostics", errors.render())]
+        };
+
+        for (extension, actual) in results {
+            if let Some(failure) = check_snapshot(&fixture.with_extension(extension), &actual) {
+                failures.push(failure);
+            }
+        }
+    }
+
+    assert!(failures.is_empty(), "{} snapshots differ, run BLESS=1 cargo test --test golden to accept them:\n\n{}", failures.len(), failures.join("\n\n"));
+}
+
+// The in-memory API has to produce the same artifacts as compiling the file from disk.
+#[test]
+fn compile_source_matches_compile_file() {
+    let fixture = fixtures_dir().join("functions.byte");
+    let source = std::fs::read_to_string(&fixture).unwrap();
+
+    let mut options = Options::new("functions.byte");
+    options.target = Target::Aarch64Linux;
+
+    let from_file = compile_file(&fixture, &options).unwrap();
+    let from_source = compile_source(&source, &options).unwrap();
+
+    assert_eq!(from_file.token_dump(), from_source.token_dump());
+    assert_eq!(from_file.ast_dump(), from_source.ast_dump());
+    assert_eq!(from_file.layout, from_source.layout);
+    assert_eq!(from_file.assembly, from_source.assembly);
+}
+
+// A file that can't be read is an error diagnostic like the ones found in the source.
+#[test]
+fn unreadable_files_are_diagnostics() {
+    let options = Options::new("missing.byte");
+
+    let errors = compile_file(&fixtures_dir().join("missing.byte"), &options).unwrap_err();
+
+    assert_eq!(errors.diagnostics[0].code, DiagnosticCode::FileNotFound);
+    assert!(errors.render().starts_with("error[E0104]: Failed to read the file: "), "{}", errors.render());
+    assert!(errors.render().contains(" --> missing.byte\n"), "{}", errors.render());
+}
+
+#[test]
+fn stop_after_leaves_later_phases_empty() {
+    let mut options = Options::new("main.byte");
+    options.stop_after = Phase::Ast;
+
+    let artifacts = compile_source("void : main() {\n    i64 a = 1;\n}\n", &options).unwrap();
+
+    assert!(!artifacts.tokens.is_empty());
+    assert!(!artifacts.statements.is_empty());
+    assert!(artifacts.layout.is_empty());
+    assert!(artifacts.assembly.is_empty());
+}
+
+// A function ending with a return has no second epilogue after it, one without gets the default.
+#[test]
+fn trailing_return_has_a_single_epilogue() {
+    let mut options = Options::new("main.byte");
+    options.target = Target::Aarch64Linux;
+
+    let assembly = compile_source("i64 : one() {\n    return 1;\n}\n\nvoid : main() {\n    i64 a = bl(one);\n}\n", &options).unwrap().assembly;
+
+    assert!(assembly.contains("one:\nstr x30, [sp, #-16]!\nmov x9, #1\nmov x0, x9\nldr x30, [sp], #16\nret\nmain:\n"), "{}", assembly);
+    assert!(assembly.contains("add sp, sp, #16\nldr x30, [sp], #16\nret\n"), "{}", assembly);
+    assert_eq!(assembly.matches("ret\n").count(), 2, "{}", assembly);
+}
+
+// Functions are emitted in declaration order, so separate compilations give the same bytes.
+#[test]
+fn assembly_is_reproducible() {
+    let fixture = fixtures_dir().join("imports.byte");
+
+    let mut options = Options::new("imports.byte");
+    options.target = Target::Aarch64Linux;
+
+    let first = compile_file(&fixture, &options).unwrap().assembly;
+
+    for _ in 0..8 {
+        assert_eq!(first, compile_file(&fixture, &options).unwrap().assembly);
+    }
+}
+
+// Indices only known at runtime are checked in the debug profile, constant ones never need it.
+#[test]
+fn debug_profile_checks_array_bounds() {
+    let source = "i64 : main() {\n    u8[16] buf;\n    i64 i = 3;\n    buf[i] = 1;\n    buf[2] = 2;\n    return 0;\n}\n";
+
+    let mut options = Options::new("main.byte");
+    options.target = Target::Aarch64Linux;
+
+    let release = compile_source(source, &options).unwrap().assembly;
+
+    options.profile = Profile::Debug;
+
+    let debug = compile_source(source, &options).unwrap().assembly;
+
+    assert!(!release.contains("brk"));
+    assert_eq!(debug.matches("brk #1").count(), 1);
+    assert!(debug.contains("cmp x9, #16\n"));
+}
+
+// Apple packs the stack args of C functions at their natural alignment, Linux gives each one 8
+// bytes.
+#[test]
+fn extern_stack_args_follow_the_target() {
+    let source = "extern void : many(i64 a, i64 b, i64 c, i64 d, i64 e, i64 f, i64 g, i64 h, u8 i, u8 j);\n\nvoid : main() {\n    bl(many, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10);\n}\n";
+
+    let mut options = Options::new("main.byte");
+    options.target = Target::Aarch64Linux;
+
+    let linux = compile_source(source, &options).unwrap();
+
+    options.target = Target::Aarch64Macos;
+
+    let macos = compile_source(source, &options).unwrap();
+
+    assert!(linux.links_libc);
+    assert!(linux.assembly.contains("mov w10, #9\nstrb w10, [sp, #0]\nmov w10, #10\nstrb w10, [sp, #8]\n"));
+    assert!(linux.assembly.contains("bl many\n"));
+    assert!(macos.assembly.contains("mov w10, #9\nstrb w10, [sp, #0]\nmov w10, #10\nstrb w10, [sp, #1]\n"));
+    assert!(macos.assembly.contains("bl _many\n"));
+}
+
+// Exported functions are linked without their module, two modules can't export the same name.
+#[test]
+fn exported_symbols_are_unique() {
+    let mut options = Options::new("main.byte");
+    options.target = Target::Aarch64Linux;
+    options.memory_files.insert(String::from("other.byte"), String::from("export i64 : shared() {\n    return 1;\n}\n"));
+
+    let errors = compile_source("import \"other.byte\";\n\nexport i64 : shared() {\n    return 2;\n}\n\nvoid : main() {\n}\n", &options).unwrap_err().render();
+
+    assert!(errors.contains("has the same symbol shared as"), "{}", errors);
+}
+
+// Objects and libraries are linked into another program, they don't need a main and bring no
+// entry point of their own.
+#[test]
+fn library_builds_have_no_entry_point() {
+    let source = "export i64 : add(i64 a, i64 b) {\n    return a + b;\n}\n";
+
+    let mut options = Options::new("mathlib.byte");
+    options.target = Target::Aarch64Linux;
+    options.library = true;
+
+    let linux = compile_source(source, &options).unwrap().assembly;
+
+    options.target = Target::Aarch64Macos;
+
+    let macos = compile_source(source, &options).unwrap().assembly;
+
+    assert!(!linux.contains("_start") && !linux.contains("main"));
+    assert!(linux.contains(".global add\nadd:\n"));
+    assert!(linux.ends_with(".section .note.GNU-stack,\"\",@progbits\n"));
+    assert!(!macos.contains("GNU-stack"));
+    assert!(!macos.contains("main"));
+    assert!(macos.contains(".global _add\n_add:\n"));
+}
+
+// A main inside a library is an ordinary local function, only exported functions are global.
+#[test]
+fn library_main_stays_local() {
+    let source = "static i64 total = 1;\n\nexport i64 : add(i64 a, i64 b) {\n    return a + b;\n}\n\ni64 : main() {\n    return total;\n}\n";
+
+    let mut options = Options::new("mathlib.byte");
+    options.target = Target::Aarch64Linux;
+    options.library = true;
+
+    let library = compile_source(source, &options).unwrap().assembly;
+
+    assert!(library.starts_with(".align 4\n.text\n"), "{}", library);
+    assert_eq!(library.matches(".global").count(), 1, "{}", library);
+    assert!(!library.contains("bl main"), "{}", library);
+}
+
+// An exported function imported from another module is called and declared under its bare name,
+// the module prefix only applies to ordinary functions.
+#[test]
+fn imported_exports_keep_their_c_symbol() {
+    let mut options = Options::new("mathlib.byte");
+    options.target = Target::Aarch64Linux;
+    options.memory_files.insert(String::from("util.byte"), String::from("export i64 : scale(i64 value, u8 shift) {\n    return value << shift;\n}\n"));
+
+    let artifacts = compile_source("import \"util.byte\";\n\nvoid : main() {\n    i64 scaled = bl(util::scale, 3, 2);\n}\n", &options).unwrap();
+
+    assert!(artifacts.assembly.contains(".global scale\nscale:\n"), "{}", artifacts.assembly);
+    assert!(artifacts.assembly.contains("bl scale\n"), "{}", artifacts.assembly);
+    assert!(!artifacts.assembly.contains("util__scale"), "{}", artifacts.assembly);
+    assert!(artifacts.header.contains("int64_t scale(int64_t value, uint8_t shift);\n"), "{}", artifacts.header);
+    assert!(!artifacts.header.contains("main"), "{}", artifacts.header);
+}